@@ -1,11 +1,12 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     io,
     io::Write,
     path::PathBuf,
     process::{Command, Stdio},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use std::sync::mpsc;
 use std::thread;
@@ -13,6 +14,7 @@ use std::thread;
 use anyhow::Result;
 use arboard::Clipboard as SystemClipboardHandle;
 use base64::Engine;
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
@@ -21,20 +23,214 @@ use crossterm::{
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Terminal,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = parse_cli(&args)?;
+    if let Some(CliCommand::Help) = command {
+        println!("{CLI_USAGE}");
+        return Ok(());
+    }
     let config = Config::from_env()?;
+    if let Some(command) = command {
+        return run_cli(command, config);
+    }
     let mut terminal = setup_terminal()?;
     let result = run_app(&mut terminal, config);
     restore_terminal(&mut terminal)?;
     result
 }
 
+const CLI_USAGE: &str = "\
+usage: gitlab-tree [COMMAND]
+
+Without a command the interactive tree is started.
+
+commands:
+  access-review [--format csv|json] [--output FILE] [--privileged-threshold N]
+      list who has which access level on every group and project in the tree
+  visibility-audit [--format csv|json] [--output FILE]
+      list public/internal groups and projects and flag over-exposed projects
+  stale-projects [--days N] [--format csv|json] [--output FILE]
+      list projects without activity for N days (default GITLAB_STALE_DAYS) by group
+  help
+      show this message";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CliCommand {
+    Help,
+    AccessReview(AccessReviewOptions),
+    VisibilityAudit(ReportOptions),
+    StaleProjects(StaleProjectOptions),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct StaleProjectOptions {
+    report: ReportOptions,
+    days: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ReportOptions {
+    format: ReportFormat,
+    output: Option<PathBuf>,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self { format: ReportFormat::Csv, output: None }
+    }
+}
+
+impl ReportOptions {
+    /// Applies a shared report flag; returns false for flags it does not know.
+    fn apply(&mut self, flag: &str, value: &str) -> Result<bool> {
+        match flag {
+            "--format" => self.format = parse_report_format(value)?,
+            "--output" | "-o" => self.output = Some(PathBuf::from(value)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn write(&self, report: &str) -> Result<()> {
+        match &self.output {
+            Some(path) => std::fs::write(path, report)?,
+            None => io::stdout().write_all(report.as_bytes())?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AccessReviewOptions {
+    report: ReportOptions,
+    privileged_threshold: usize,
+}
+
+impl Default for AccessReviewOptions {
+    fn default() -> Self {
+        Self { report: ReportOptions::default(), privileged_threshold: 5 }
+    }
+}
+
+/// Pairs up `--flag value` arguments; every command flag takes a value.
+fn parse_flags(args: &[String]) -> Result<Vec<(&str, &str)>> {
+    let mut pairs = Vec::new();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if !flag.starts_with('-') {
+            anyhow::bail!("unexpected argument {flag}\n\n{CLI_USAGE}");
+        }
+        let Some(value) = args.next() else {
+            anyhow::bail!("{flag} needs a value\n\n{CLI_USAGE}");
+        };
+        pairs.push((flag.as_str(), value.as_str()));
+    }
+    Ok(pairs)
+}
+
+fn parse_cli(args: &[String]) -> Result<Option<CliCommand>> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    match command.as_str() {
+        "help" | "-h" | "--help" => Ok(Some(CliCommand::Help)),
+        "access-review" => {
+            let mut options = AccessReviewOptions::default();
+            for (flag, value) in parse_flags(rest)? {
+                match flag {
+                    "--privileged-threshold" => {
+                        options.privileged_threshold = value.parse().map_err(|_| {
+                            anyhow::anyhow!("--privileged-threshold expects a number")
+                        })?;
+                    }
+                    _ if options.report.apply(flag, value)? => {}
+                    _ => anyhow::bail!("unknown option {flag}\n\n{CLI_USAGE}"),
+                }
+            }
+            Ok(Some(CliCommand::AccessReview(options)))
+        }
+        "visibility-audit" => {
+            let mut options = ReportOptions::default();
+            for (flag, value) in parse_flags(rest)? {
+                if !options.apply(flag, value)? {
+                    anyhow::bail!("unknown option {flag}\n\n{CLI_USAGE}");
+                }
+            }
+            Ok(Some(CliCommand::VisibilityAudit(options)))
+        }
+        "stale-projects" => {
+            let mut options = StaleProjectOptions::default();
+            for (flag, value) in parse_flags(rest)? {
+                match flag {
+                    "--days" => {
+                        let days = value
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("--days expects a number"))?;
+                        options.days = Some(days);
+                    }
+                    _ if options.report.apply(flag, value)? => {}
+                    _ => anyhow::bail!("unknown option {flag}\n\n{CLI_USAGE}"),
+                }
+            }
+            Ok(Some(CliCommand::StaleProjects(options)))
+        }
+        _ => anyhow::bail!("unknown command {command}\n\n{CLI_USAGE}"),
+    }
+}
+
+fn parse_report_format(value: &str) -> Result<ReportFormat> {
+    match value {
+        "csv" => Ok(ReportFormat::Csv),
+        "json" => Ok(ReportFormat::Json),
+        _ => anyhow::bail!("unknown format {value} (expected csv or json)"),
+    }
+}
+
+fn run_cli(command: CliCommand, config: Config) -> Result<()> {
+    let api = HttpGitLabApi::new(&config)?;
+    let app = App::from_api(&api, config)?;
+    match command {
+        CliCommand::Help => println!("{CLI_USAGE}"),
+        CliCommand::AccessReview(options) => {
+            let review = access_review(&app, &api, &options, SystemTime::now());
+            options.report.write(&format_access_review(&review.records, options.report.format)?)?;
+            for failure in &review.failures {
+                eprintln!("access-review: {failure}");
+            }
+            if !review.failures.is_empty() {
+                anyhow::bail!(
+                    "access review incomplete: members of {} nodes could not be fetched",
+                    review.failures.len()
+                );
+            }
+        }
+        CliCommand::VisibilityAudit(options) => {
+            let findings = visibility_audit(&app);
+            options.write(&format_visibility_audit(&findings, options.format)?)?;
+        }
+        CliCommand::StaleProjects(options) => {
+            let days = options.days.unwrap_or(app.config.stale_days);
+            let groups = stale_projects(&app, days, SystemTime::now());
+            options.report.write(&format_stale_projects(&groups, options.report.format)?)?;
+        }
+    }
+    Ok(())
+}
+
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<io::Stdout>>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -51,22 +247,27 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Re
 }
 
 fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config: Config) -> Result<()> {
-    let mut loader = Some(start_loader(config.clone()));
+    let api = Arc::new(HttpGitLabApi::new(&config)?);
+    let mut loader = Some(start_loader(api.clone()));
     let mut app = None;
     let mut clipboard = build_clipboard();
     let mut browser = SystemBrowser;
+    let (mut fetch_sender, mut fetch_receiver) = mpsc::channel();
     loop {
         if let Some(handle) = loader.as_mut() {
             match handle.receiver.try_recv() {
                 Ok(result) => {
                     app = Some(match result {
                         Ok(app) => app,
-                        Err(err) => App::sample_with_status(config.clone(), format!("load error: {err}")),
+                        Err(err) => {
+                            config.log.record(LogLevel::Error, "load", format!("load failed: {err}"));
+                            App::load_failed(config.clone(), format!("load error: {err}"))
+                        }
                     });
                     loader = None;
                 }
                 Err(mpsc::TryRecvError::Disconnected) => {
-                    app = Some(App::sample_with_status(
+                    app = Some(App::load_failed(
                         config.clone(),
                         "load error: channel closed".to_string(),
                     ));
@@ -78,35 +279,46 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config: Config
 
         let mut pending_action = None;
         if let Some(app_ref) = app.as_mut() {
+            while let Ok(result) = fetch_receiver.try_recv() {
+                app_ref.apply_fetch(result);
+            }
             let visible = app_ref.visible_nodes();
             app_ref.ensure_selection(visible.len());
             app_ref.tick_toast();
+            if let Some(request) = app_ref.lazy_fetch(&visible) {
+                spawn_fetch(api.clone(), request, fetch_sender.clone());
+            }
+            if let Some(request) = app_ref.pipeline_badge_fetch(&visible, Instant::now()) {
+                spawn_fetch(api.clone(), request, fetch_sender.clone());
+            }
+            if let Some(request) = app_ref.poll_fetch(Instant::now()) {
+                spawn_fetch(api.clone(), request, fetch_sender.clone());
+            }
 
             terminal.draw(|frame| ui(frame, app_ref, &visible))?;
 
-            if event::poll(Duration::from_millis(200))? {
-                if let Event::Key(key) = event::read()? {
-                    let action = if let Some(mut cb) = clipboard.take() {
-                        let action =
-                            app_ref.handle_key(key, &visible, Some(&mut *cb), &mut browser)?;
-                        clipboard = Some(cb);
-                        action
-                    } else {
-                        app_ref.handle_key(key, &visible, None, &mut browser)?
-                    };
-                    pending_action = Some(action);
-                }
+            if event::poll(Duration::from_millis(200))?
+                && let Event::Key(key) = event::read()?
+            {
+                let action = if let Some(mut cb) = clipboard.take() {
+                    let action =
+                        app_ref.handle_key(key, &visible, Some(&mut *cb), &mut browser)?;
+                    clipboard = Some(cb);
+                    action
+                } else {
+                    app_ref.handle_key(key, &visible, None, &mut browser)?
+                };
+                pending_action = Some(action);
             }
         } else if let Some(handle) = loader.as_mut() {
             terminal.draw(|frame| ui_loading(frame, handle.tick))?;
             handle.tick = handle.tick.wrapping_add(1);
 
-            if event::poll(Duration::from_millis(200))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.code == KeyCode::Char('q') {
-                        return Ok(());
-                    }
-                }
+            if event::poll(Duration::from_millis(200))?
+                && let Event::Key(key) = event::read()?
+                && key.code == KeyCode::Char('q')
+            {
+                return Ok(());
             }
         } else {
             return Ok(());
@@ -116,8 +328,12 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, config: Config
             match action {
                 KeyAction::Quit => return Ok(()),
                 KeyAction::Reload => {
-                    loader = Some(start_loader(config.clone()));
+                    loader = Some(start_loader(api.clone()));
                     app = None;
+                    (fetch_sender, fetch_receiver) = mpsc::channel();
+                }
+                KeyAction::Fetch(request) => {
+                    spawn_fetch(api.clone(), request, fetch_sender.clone());
                 }
                 KeyAction::None => {}
             }
@@ -135,6 +351,24 @@ fn ui(
         .constraints([Constraint::Min(1), Constraint::Length(2)])
         .split(frame.size());
 
+    let body_chunks = if app.show_log {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .split(chunks[0])
+    } else {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1)])
+            .split(chunks[0])
+    };
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(body_chunks[0]);
+
+    let now = SystemTime::now();
+    let list_width = usize::from(main_chunks[0].width.saturating_sub(2));
     let items: Vec<ListItem> = visible
         .iter()
         .map(|node| {
@@ -150,16 +384,62 @@ fn ui(
             let kind = match data.kind {
                 NodeKind::Group => "group",
                 NodeKind::Project => "project",
+                NodeKind::Shared => "--",
+                NodeKind::Directory => "dir",
+                NodeKind::File => "file",
+            };
+            let mut spans = vec![Span::raw(format!("{indent}{marker} {kind} "))];
+            let age = match (data.kind, data.last_activity.as_deref()) {
+                (NodeKind::Project, Some(activity)) => days_since(activity, now),
+                _ => None,
             };
-            let line = format!("{indent}{marker} {kind} {}", data.name);
-            ListItem::new(line)
+            let badge = match (data.kind, data.gitlab_id) {
+                (NodeKind::Project, Some(id)) => app.pipeline_badges.get(&id).and_then(|badge| {
+                    badge.status().map(str::to_string)
+                }),
+                _ => None,
+            };
+            if let Some(status) = badge {
+                spans.push(Span::styled(
+                    format!("{} ", pipeline_glyph(&status)),
+                    Style::default().fg(pipeline_color(&status)),
+                ));
+            }
+            let mut line = data.name.clone();
+            line.push_str(&relation_tags(&data.relation));
+            if data.error.is_some() {
+                line.push_str(" [error]");
+                spans.push(Span::raw(line));
+                return ListItem::new(Line::from(spans)).style(Style::default().fg(Color::Red));
+            }
+            if data.archived {
+                line.push_str(" [archived]");
+                spans.push(Span::raw(line));
+                return ListItem::new(Line::from(spans))
+                    .style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::DIM));
+            }
+            spans.push(Span::raw(line));
+            if let Some(days) = age {
+                spans.push(Span::styled(
+                    format!(" {}", format_compact_age(days)),
+                    Style::default().fg(age_color(days, app.config.stale_days)),
+                ));
+            }
+            if app.show_group_stats && data.kind == NodeKind::Group {
+                let stats = app.group_stats.get(&node.id).cloned().unwrap_or_default();
+                let column = format_group_stats(&stats, now);
+                let used = Line::from(spans.clone()).width() + column.chars().count();
+                let padding = list_width.saturating_sub(used).max(1);
+                spans.push(Span::raw(" ".repeat(padding)));
+                spans.push(Span::styled(column, Style::default().fg(Color::DarkGray)));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
-        .split(chunks[0]);
+    if app.show_log {
+        render_log_panel(frame, &app.config.log, body_chunks[1]);
+    }
 
     let list = List::new(items)
         .block(Block::default().title("GitLab Tree").borders(Borders::ALL))
@@ -170,22 +450,59 @@ fn ui(
     }
     frame.render_stateful_widget(list, main_chunks[0], &mut state);
 
-    let details_lines = if visible.is_empty() {
+    let details_lines = if app.nodes.is_empty() {
+        vec!["No data loaded".to_string(), "Press r to retry".to_string()]
+    } else if visible.is_empty() {
         vec!["No selection".to_string()]
     } else {
         let node_id = visible[app.selected].id;
-        format_node_details(&app.nodes[node_id])
+        let time = &app.config.time;
+        let mut lines = format_node_details(&app.nodes[node_id], time, now);
+        if let Some(details) = app.selected_project_details(visible) {
+            lines.extend(format_project_details(details, time, now));
+            let http_clone = details
+                .project
+                .as_ref()
+                .is_some_and(|project| project.http_url_to_repo.is_some());
+            if http_clone && let Some(warning) = app.scope_warning(TokenAction::CloneHttps) {
+                lines.push(format!("Warning: {warning}"));
+            }
+        }
+        if let Some(details) = app.selected_group_details(visible) {
+            let summary = app.group_stats.get(&node_id).cloned().unwrap_or_default();
+            lines.extend(format_group_details(details, &summary, time, now));
+        }
+        lines
+    };
+    let readme = app.selected_readme(visible);
+    let details_area = if readme.is_some() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(main_chunks[1]);
+        if let Some(readme) = readme {
+            render_readme(frame, readme, chunks[1]);
+        }
+        chunks[0]
+    } else {
+        main_chunks[1]
     };
     let details = Paragraph::new(details_lines.join("\n"))
+        .wrap(Wrap { trim: false })
         .block(Block::default().title("Details").borders(Borders::ALL));
-    frame.render_widget(details, main_chunks[1]);
+    frame.render_widget(details, details_area);
 
-    let token_state = if app.config.gitlab_token.is_empty() {
-        "token: unset"
-    } else {
-        "token: set"
+    let token_state = match &app.token {
+        Some(token) => format_token_summary(token),
+        None if app.config.gitlab_token.is_empty() => "token: unset".to_string(),
+        None => "token: set".to_string(),
     };
     let mut footer = format!("? help | {} | {}", app.config.gitlab_url, token_state);
+    if let Some(token) = &app.token {
+        for warning in token_warnings(token, app.config.token_warn_days) {
+            footer.push_str(&format!(" | warning: {warning}"));
+        }
+    }
     if let Some(status) = &app.status {
         footer.push_str(&format!(" | {status}"));
     }
@@ -199,2601 +516,11673 @@ fn ui(
     if let Some(toast) = &app.toast {
         render_toast(frame, toast);
     }
+    if let Some(view) = &app.view {
+        render_view(frame, view, &app.config.time);
+    }
+    if let Some(pager) = &app.pager {
+        render_pager(frame, pager);
+    }
+    if let Some(picker) = &app.ref_picker {
+        render_ref_picker(frame, picker);
+    }
     if app.show_help {
-        render_help(frame);
+        render_help(frame, app.token.as_ref());
     }
 }
 
-fn ui_loading(frame: &mut ratatui::Frame, tick: usize) {
-    let block = Block::default().title("GitLab Tree").borders(Borders::ALL);
-    let message = loading_message(tick);
-    let paragraph = Paragraph::new(message).block(block);
-    frame.render_widget(paragraph, frame.size());
-}
-
-fn format_node_details(node: &Node) -> Vec<String> {
-    let kind = match node.kind {
-        NodeKind::Group => "Group",
-        NodeKind::Project => "Project",
-    };
-    let mut lines = vec![
-        format!("Name: {}", node.name),
-        format!("Kind: {kind}"),
-        format!("Path: {}", node.path),
-        format!("Visibility: {}", node.visibility),
-        format!("URL: {}", node.url),
-    ];
-    if let Some(last_activity) = &node.last_activity {
-        lines.push(format!("Last activity: {last_activity}"));
+fn render_view(frame: &mut ratatui::Frame, view: &View, time: &TimeDisplay) {
+    match view {
+        View::MergeRequests(view) => render_merge_requests(frame, view),
+        View::Issues(view) => render_issues(frame, view, time),
+        View::Pipelines(view) => render_pipelines(frame, view),
+        View::Refs(view) => render_refs(frame, view),
+        View::Members(view) => render_members(frame, view),
+        View::Visibility(view) => render_visibility(frame, view),
     }
-    lines
 }
 
-fn render_toast(frame: &mut ratatui::Frame, toast: &Toast) {
+fn render_visibility(frame: &mut ratatui::Frame, view: &VisibilityView) {
     let area = frame.size();
-    let width = (toast.message.len() as u16).saturating_add(4);
-    let height = 3;
-    let x = area.width.saturating_sub(width + 1);
-    let y = 1;
-    let rect = Rect::new(x, y, width.min(area.width), height.min(area.height));
-    let block = Block::default().title("Notice").borders(Borders::ALL);
-    let paragraph = Paragraph::new(toast.message.clone()).block(block);
-    frame.render_widget(paragraph, rect);
+    frame.render_widget(Clear, area);
+    let scope = if view.flagged_only { "flagged" } else { "public/internal" };
+    let title = format!("Visibility audit [{scope}] (f flagged only, enter jump, q close)");
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let rows = view.visible_rows();
+    if rows.is_empty() {
+        let message = if view.flagged_only { "No flagged items" } else { "Nothing is public" };
+        frame.render_widget(Paragraph::new(message).block(block), area);
+        return;
+    }
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let style = match (row.flags.is_empty(), row.visibility.as_str()) {
+                (false, _) => Style::default().fg(Color::Red),
+                (true, "public") => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
+            };
+            ListItem::new(format_visibility_row(row)).style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+    frame.render_stateful_widget(list, area, &mut state);
 }
 
-fn render_help(frame: &mut ratatui::Frame) {
+fn render_members(frame: &mut ratatui::Frame, view: &MemberView) {
     let area = frame.size();
-    let width = (area.width.saturating_mul(3)).saturating_div(4).max(40);
-    let height = 14u16.min(area.height);
-    let x = area.width.saturating_sub(width) / 2;
-    let y = area.height.saturating_sub(height) / 2;
-    let rect = Rect::new(x, y, width, height);
-    let lines = [
-        "q / ctrl-c: quit",
-        "r: refresh",
-        "?: toggle help",
-        "enter: toggle expand/collapse",
-        "up/down or j/k: move selection",
-        "left/right or h/l: collapse/expand",
-        "pgup/pgdn: page navigation",
-        "gg/G: jump to top/bottom",
-        "y: copy URL",
-        "o: open in browser",
-        "/: search",
-        "esc: clear search",
-    ];
-    let paragraph = Paragraph::new(lines.join("\n"))
-        .block(Block::default().title("Help").borders(Borders::ALL));
-    frame.render_widget(paragraph, rect);
+    frame.render_widget(Clear, area);
+    let filter = match &view.input {
+        Some(input) => format!("user*: {input}"),
+        None if view.filter.is_empty() => "all users".to_string(),
+        None => format!("user: {}", view.filter),
+    };
+    let title = format!("Members: {} [{filter}] (/ filter, r refresh, q close)", view.path);
+    let block = Block::default().title(title).borders(Borders::ALL);
+    match &view.state {
+        DetailState::Loaded => {
+            let rows = view.visible_rows();
+            if rows.is_empty() {
+                frame.render_widget(Paragraph::new("No matching members").block(block), area);
+                return;
+            }
+            let now = SystemTime::now();
+            let items: Vec<ListItem> = rows
+                .iter()
+                .map(|row| {
+                    let style = match member_expiry_days(&row.member, now) {
+                        Some(days) if days < 0 => Style::default().fg(Color::Red),
+                        Some(days) if days <= MEMBERSHIP_EXPIRY_WARNING_DAYS => {
+                            Style::default().fg(Color::Yellow)
+                        }
+                        _ if row.source != MemberSource::Direct => {
+                            Style::default().fg(Color::DarkGray)
+                        }
+                        _ => Style::default(),
+                    };
+                    ListItem::new(format_member_row(row, now)).style(style)
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(view.selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
+    }
 }
 
-struct LoadHandle {
-    receiver: mpsc::Receiver<Result<App>>,
-    tick: usize,
+const MEMBERSHIP_EXPIRY_WARNING_DAYS: i64 = 30;
+
+fn member_expiry_days(member: &GitLabMember, now: SystemTime) -> Option<i64> {
+    days_until(member.expires_at.as_deref()?, now)
 }
 
-fn start_loader(config: Config) -> LoadHandle {
-    let (sender, receiver) = mpsc::channel();
-    thread::spawn(move || {
-        let result = App::from_gitlab(config);
-        let _ = sender.send(result);
-    });
-    LoadHandle { receiver, tick: 0 }
+fn format_member_row(row: &MemberRow, now: SystemTime) -> String {
+    let member = &row.member;
+    let mut line = format!("@{}", member.username);
+    if let Some(name) = &member.name {
+        line.push_str(&format!(" ({name})"));
+    }
+    line.push_str(&format!(
+        " | {} | {}",
+        access_level_name(member.access_level),
+        row.source.label()
+    ));
+    match (member.expires_at.as_deref(), member_expiry_days(member, now)) {
+        (Some(date), Some(days)) if days < 0 => line.push_str(&format!(" | expired {date}")),
+        (Some(date), Some(days)) => line.push_str(&format!(" | expires {date} ({days}d)")),
+        _ => line.push_str(" | no expiry"),
+    }
+    match member.two_factor_enabled {
+        Some(true) => line.push_str(" | 2FA on"),
+        Some(false) => line.push_str(" | 2FA off"),
+        None => {}
+    }
+    line
 }
 
-fn loading_message(tick: usize) -> String {
-    let frames = ["|", "/", "-", "\\"];
-    let frame = frames[tick % frames.len()];
-    format!("{frame} loading GitLab data...")
+fn render_refs(frame: &mut ratatui::Frame, view: &RefView) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    if let Some(notes) = &view.notes {
+        let title = format!("{} (j/k scroll, y copy, o open, q back)", notes.title);
+        let muted = Style::default().fg(Color::DarkGray);
+        let lines = match &notes.body {
+            Some(body) if notes.markdown => render_markdown(body),
+            Some(body) => body.lines().map(|line| Line::from(line.to_string())).collect(),
+            None => vec![Line::from(Span::styled("No release notes", muted))],
+        };
+        let paragraph = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((notes.scroll, 0))
+            .block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+        return;
+    }
+    let title = format!(
+        "{}: {} (tab switch, enter notes, y copy compare, o open compare, r refresh, q close)",
+        view.tab.label(),
+        view.path
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    match &view.state {
+        DetailState::Loaded if view.len() == 0 => {
+            let message = format!("No {}", view.tab.label());
+            frame.render_widget(Paragraph::new(message).block(block), area);
+        }
+        DetailState::Loaded => {
+            let now = SystemTime::now();
+            let rows: Vec<String> = match view.tab {
+                RefTab::Branches => {
+                    view.branches.iter().map(|row| format_branch_row(row, now)).collect()
+                }
+                RefTab::Tags => view.tags.iter().map(|tag| format_tag_row(tag, now)).collect(),
+                RefTab::Releases => {
+                    view.releases.iter().map(|release| format_release_row(release, now)).collect()
+                }
+            };
+            let items: Vec<ListItem> = rows.into_iter().map(ListItem::new).collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(view.selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
+    }
 }
 
-#[derive(Clone)]
-struct Config {
-    gitlab_url: String,
-    gitlab_token: String,
-    filters: ApiFilters,
-    cache_path: PathBuf,
-    cache_ttl: Duration,
-    group_sort: SortOrder,
-    project_sort: SortOrder,
+fn format_commit_summary(commit: Option<&GitLabCommit>, now: SystemTime) -> String {
+    let Some(commit) = commit else {
+        return "-".to_string();
+    };
+    let author = commit.author_name.as_deref().unwrap_or("-");
+    let age = commit
+        .committed_date
+        .as_deref()
+        .map_or_else(|| "-".to_string(), |date| relative_age(date, now));
+    format!("{} {} | {author} | {age}", commit.short_id, commit.title)
 }
 
-impl Config {
-    fn from_env() -> Result<Self> {
-        Self::from_env_reader(|key| env::var(key).ok())
+fn format_branch_row(row: &BranchRow, now: SystemTime) -> String {
+    let branch = &row.branch;
+    let mut name = branch.name.clone();
+    if branch.default {
+        name.push_str(" [default]");
     }
-
-    fn from_env_reader<F>(reader: F) -> Result<Self>
-    where
-        F: Fn(&str) -> Option<String>,
-    {
-        let gitlab_url =
-            read_env_optional(&reader, "GITLAB_URL").unwrap_or_else(|| "https://gitlab.com".to_string());
-        let gitlab_token = read_env_required(&reader, "GITLAB_TOKEN")?;
-        let filters = ApiFilters::from_env_reader(&reader)?;
-        let cache_ttl_seconds =
-            read_env_u64_optional(&reader, "GITLAB_CACHE_TTL_SECONDS")?.unwrap_or(300);
-        let cache_path = read_env_optional(&reader, "GITLAB_CACHE_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(default_cache_path);
-        let group_sort =
-            SortOrder::from_env(&reader, "GITLAB_GROUP_SORT")?.unwrap_or(SortOrder::Alpha);
-        let project_sort =
-            SortOrder::from_env(&reader, "GITLAB_PROJECT_SORT")?.unwrap_or(SortOrder::Alpha);
-
-        Ok(Self {
-            gitlab_url,
-            gitlab_token,
-            filters,
-            cache_path,
-            cache_ttl: Duration::from_secs(cache_ttl_seconds),
-            group_sort,
-            project_sort,
-        })
+    if branch.protected {
+        name.push_str(" [protected]");
     }
+    let divergence = match (row.divergence, &row.state) {
+        _ if branch.default => "-".to_string(),
+        (Some((ahead, behind)), _) => {
+            format!("↑{} ↓{}", format_commit_total(ahead), format_commit_total(behind))
+        }
+        (None, DetailState::Loading) => "…".to_string(),
+        (None, _) => "?".to_string(),
+    };
+    format!("{name} | {} | {divergence}", format_commit_summary(branch.commit.as_ref(), now))
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum SortOrder {
-    Alpha,
-    Activity,
+/// GitLab stops counting above 10,000 commits; those ranges show as "10000+".
+fn format_commit_total(count: Option<usize>) -> String {
+    count.map_or_else(|| format!("{COMMIT_COUNT_CAP}+"), |count| count.to_string())
 }
 
-impl SortOrder {
-    fn from_env<F>(reader: &F, key: &str) -> Result<Option<Self>>
-    where
-        F: Fn(&str) -> Option<String>,
-    {
-        let Some(value) = read_env_optional(reader, key) else {
-            return Ok(None);
-        };
-        match value.to_lowercase().as_str() {
-            "alpha" | "alphabetical" => Ok(Some(SortOrder::Alpha)),
-            "activity" | "last_activity" | "recent" => Ok(Some(SortOrder::Activity)),
-            _ => anyhow::bail!("invalid sort order for {key}: {value}"),
-        }
-    }
+fn format_tag_row(tag: &GitLabRef, now: SystemTime) -> String {
+    let message = tag
+        .message
+        .as_deref()
+        .and_then(|message| message.lines().next())
+        .filter(|line| !line.trim().is_empty())
+        .unwrap_or("-");
+    format!("{} | {message} | {}", tag.name, format_commit_summary(tag.commit.as_ref(), now))
 }
 
-#[derive(Clone, Debug, Default)]
-struct ApiFilters {
-    all_available: Option<bool>,
-    owned: Option<bool>,
-    top_level_only: Option<bool>,
-    include_subgroups: Option<bool>,
-    visibility: Option<String>,
-    per_page: u16,
+fn format_release_row(release: &GitLabRelease, now: SystemTime) -> String {
+    let name = release.name.as_deref().unwrap_or(&release.tag_name);
+    let age = release
+        .released_at
+        .as_deref()
+        .map_or_else(|| "-".to_string(), |date| relative_age(date, now));
+    format!("{name} ({}) | {age}", release.tag_name)
 }
 
-impl ApiFilters {
-    fn from_env_reader<F>(reader: &F) -> Result<Self>
-    where
-        F: Fn(&str) -> Option<String>,
-    {
-        let per_page = read_env_u16_optional(reader, "GITLAB_PER_PAGE")?.unwrap_or(100);
-        Ok(Self {
-            all_available: read_env_bool_optional(reader, "GITLAB_ALL_AVAILABLE")?,
-            owned: read_env_bool_optional(reader, "GITLAB_OWNED")?,
-            top_level_only: read_env_bool_optional(reader, "GITLAB_TOP_LEVEL_ONLY")?,
-            include_subgroups: read_env_bool_optional(reader, "GITLAB_INCLUDE_SUBGROUPS")?,
-            visibility: read_env_optional(reader, "GITLAB_VISIBILITY"),
-            per_page,
-        })
+fn render_pipelines(frame: &mut ratatui::Frame, view: &PipelineView) {
+    if let Some(jobs) = &view.jobs {
+        render_jobs(frame, jobs);
+        return;
+    }
+    let area = frame.size();
+    let title = format!(
+        "Pipelines: {} (enter jobs, r refresh, y copy, o open, q close)",
+        view.path
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    frame.render_widget(Clear, area);
+    match &view.state {
+        DetailState::Loaded if view.rows.is_empty() => {
+            frame.render_widget(Paragraph::new("No pipelines").block(block), area);
+        }
+        DetailState::Loaded => {
+            let now = SystemTime::now();
+            let items: Vec<ListItem> = view
+                .rows
+                .iter()
+                .map(|pipeline| {
+                    let color = Style::default().fg(pipeline_color(&pipeline.status));
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} ", pipeline_glyph(&pipeline.status)), color),
+                        Span::raw(format_pipeline_row(pipeline, now)),
+                    ]))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(view.selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
     }
 }
 
-trait ClipboardSink {
-    fn set_text(&mut self, text: String) -> Result<()>;
+fn render_jobs(frame: &mut ratatui::Frame, jobs: &JobsPanel) {
+    if let Some(log) = &jobs.log {
+        render_job_log(frame, log);
+        return;
+    }
+    let area = frame.size();
+    let title = format!(
+        "Pipeline #{} jobs (enter log, R retry, C cancel, y copy, o open, q back)",
+        jobs.pipeline_id
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    frame.render_widget(Clear, area);
+    match &jobs.state {
+        DetailState::Loaded if jobs.rows.is_empty() => {
+            frame.render_widget(Paragraph::new("No jobs").block(block), area);
+        }
+        DetailState::Loaded => {
+            let mut items = Vec::new();
+            let mut selected = 0;
+            let mut stage: Option<&str> = None;
+            for (idx, job) in jobs.rows.iter().enumerate() {
+                if stage != Some(job.stage.as_str()) {
+                    stage = Some(job.stage.as_str());
+                    let header = Style::default().add_modifier(Modifier::BOLD);
+                    items.push(ListItem::new(Line::from(Span::styled(job.stage.clone(), header))));
+                }
+                if idx == jobs.selected {
+                    selected = items.len();
+                }
+                let color = Style::default().fg(pipeline_color(&job.status));
+                items.push(ListItem::new(Line::from(vec![
+                    Span::styled(format!("  {} ", pipeline_glyph(&job.status)), color),
+                    Span::raw(format_job_row(job)),
+                ])));
+            }
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ClipboardBackend {
-    Arboard,
-    WlCopy,
-    Xclip,
-    Osc52,
-    None,
+fn format_job_row(job: &GitLabJob) -> String {
+    let duration = job
+        .duration
+        .map_or_else(|| "-".to_string(), |seconds| format_duration(seconds as u64));
+    format!("{} #{} | {} | {duration}", job.name, job.id, job.status)
 }
 
-trait ClipboardProbe {
-    fn arboard_ok(&self) -> bool;
-    fn has_wayland(&self) -> bool;
-    fn has_display(&self) -> bool;
-    fn has_tmux(&self) -> bool;
-    fn command_exists(&self, command: &str) -> bool;
+fn render_job_log(frame: &mut ratatui::Frame, log: &JobLog) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    let follow = if log.follow { "on" } else { "off" };
+    let title = format!(
+        "{} #{} {} (enter fold, z fold all, f follow: {follow}, R retry, C cancel, q back)",
+        log.job.name, log.job.id, log.job.status
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if log.lines.is_empty() {
+        let message = match &log.state {
+            DetailState::Failed(err) => format!("Failed: {err}"),
+            DetailState::Loaded => "Empty log".to_string(),
+            DetailState::Partial | DetailState::Loading => "Loading...".to_string(),
+        };
+        frame.render_widget(Paragraph::new(message).block(block), area);
+        return;
+    }
+    let items: Vec<ListItem> = log
+        .visible_lines()
+        .into_iter()
+        .map(|idx| {
+            let line = &log.lines[idx];
+            let Some(section) = line.header else {
+                return ListItem::new(ansi_line(&line.text));
+            };
+            let marker = if log.is_folded(section) { "▸ " } else { "▾ " };
+            let mut rendered = ansi_line(&line.text);
+            rendered.spans.insert(0, Span::raw(marker));
+            if let Some(duration) = log.sections[section].duration {
+                let dim = Style::default().fg(Color::DarkGray);
+                rendered.spans.push(Span::styled(format!(" ({})", format_duration(duration)), dim));
+            }
+            ListItem::new(rendered)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(log.cursor));
+    frame.render_stateful_widget(list, area, &mut state);
 }
 
-trait BrowserOpener {
-    fn open(&mut self, url: &str) -> Result<()>;
+/// Splits a raw job trace into display lines, tracking GitLab's
+/// `section_start`/`section_end` markers so sections can be folded.
+fn parse_job_trace(raw: &str) -> (Vec<TraceLine>, Vec<TraceSection>) {
+    let mut lines = Vec::new();
+    let mut sections: Vec<TraceSection> = Vec::new();
+    let mut open: Vec<(usize, u64)> = Vec::new();
+    for raw_line in raw.lines() {
+        let mut rest = raw_line;
+        let mut header = None;
+        while let Some(pos) = rest.find("section_") {
+            let marker = &rest[pos..];
+            let end = marker.find("\r\x1b[0K").unwrap_or(marker.len());
+            let mut parts = marker[..end].splitn(3, ':');
+            let kind = parts.next().unwrap_or_default();
+            let timestamp = parts.next().and_then(|ts| ts.parse::<u64>().ok());
+            let name_with_options = parts.next().unwrap_or_default();
+            let (name, options) = match name_with_options.split_once('[') {
+                Some((name, options)) => (name, options),
+                None => (name_with_options, ""),
+            };
+            match (kind, timestamp) {
+                ("section_start", Some(started)) => {
+                    sections.push(TraceSection {
+                        name: name.to_string(),
+                        collapsed: options.contains("collapsed=true"),
+                        duration: None,
+                    });
+                    open.push((sections.len() - 1, started));
+                    header = Some(sections.len() - 1);
+                }
+                ("section_end", Some(ended)) => {
+                    if let Some(idx) = open.iter().rposition(|(id, _)| sections[*id].name == name) {
+                        let (id, started) = open.remove(idx);
+                        sections[id].duration = Some(ended.saturating_sub(started));
+                        open.truncate(idx);
+                    }
+                }
+                _ => break,
+            }
+            rest = &marker[(end + "\r\x1b[0K".len()).min(marker.len())..];
+        }
+        let text = rest.rsplit('\r').next().unwrap_or_default();
+        if header.is_none() && text.is_empty() && rest.len() != raw_line.len() {
+            continue;
+        }
+        lines.push(TraceLine {
+            text: text.to_string(),
+            sections: open.iter().map(|(id, _)| *id).collect(),
+            header,
+        });
+    }
+    (lines, sections)
 }
 
-struct CommandClipboard {
-    command: String,
-    args: Vec<String>,
+fn ansi_color(code: u16) -> Color {
+    match code {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        15 => Color::White,
+        _ => Color::Indexed(code as u8),
+    }
 }
 
-impl CommandClipboard {
-    fn new(command: &str, args: &[&str]) -> Self {
-        Self {
-            command: command.to_string(),
-            args: args.iter().map(|arg| arg.to_string()).collect(),
-        }
+fn apply_sgr(style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params.split(';').map(|code| code.parse().unwrap_or(0)).collect();
+    let mut style = style;
+    let mut idx = 0;
+    while idx < codes.len() {
+        let code = codes[idx];
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            30..=37 => style.fg(ansi_color(code - 30)),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(ansi_color(code - 40)),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(ansi_color(code - 90 + 8)),
+            100..=107 => style.bg(ansi_color(code - 100 + 8)),
+            38 | 48 => {
+                let color = match codes.get(idx + 1) {
+                    Some(5) => {
+                        let color = codes.get(idx + 2).map(|n| ansi_color(*n));
+                        idx += 2;
+                        color
+                    }
+                    Some(2) => {
+                        let channel = |offset: usize| codes.get(idx + offset).copied();
+                        let color = match (channel(2), channel(3), channel(4)) {
+                            (Some(r), Some(g), Some(b)) => {
+                                Some(Color::Rgb(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        };
+                        idx += 4;
+                        color
+                    }
+                    _ => None,
+                };
+                match (code, color) {
+                    (38, Some(color)) => style.fg(color),
+                    (_, Some(color)) => style.bg(color),
+                    _ => style,
+                }
+            }
+            _ => style,
+        };
+        idx += 1;
     }
+    style
 }
 
-impl ClipboardSink for CommandClipboard {
-    fn set_text(&mut self, text: String) -> Result<()> {
-        let mut child = Command::new(&self.command)
-            .args(&self.args)
-            .stdin(Stdio::piped())
-            .spawn()
-            .map_err(|err| anyhow::anyhow!("clipboard command failed: {err}"))?;
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(text.as_bytes())?;
+/// Renders a line containing ANSI SGR escapes as styled spans; other CSI
+/// sequences (cursor movement, erase) are dropped.
+fn ansi_line(text: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\x1b' {
+            current.push(ch);
+            continue;
         }
-        let status = child.wait()?;
-        if !status.success() {
-            anyhow::bail!("clipboard command exited with {status}");
+        if chars.peek() != Some(&'[') {
+            continue;
+        }
+        chars.next();
+        let mut params = String::new();
+        let mut command = None;
+        for next in chars.by_ref() {
+            if next.is_ascii_alphabetic() {
+                command = Some(next);
+                break;
+            }
+            params.push(next);
+        }
+        if command == Some('m') {
+            if !current.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut current), style));
+            }
+            style = apply_sgr(style, &params);
         }
-        Ok(())
     }
+    if !current.is_empty() {
+        spans.push(Span::styled(current, style));
+    }
+    Line::from(spans)
 }
 
-struct SystemClipboard {
-    inner: SystemClipboardHandle,
+fn format_pipeline_row(pipeline: &GitLabPipeline, now: SystemTime) -> String {
+    let duration = pipeline.duration.map_or_else(|| "-".to_string(), format_duration);
+    let source = pipeline.source.as_deref().unwrap_or("-");
+    let age = pipeline
+        .created_at
+        .as_deref()
+        .map_or_else(|| "-".to_string(), |created| relative_age(created, now));
+    format!(
+        "#{} {} | {} | {duration} | {source} | {age}",
+        pipeline.id, pipeline.status, pipeline.ref_name
+    )
 }
 
-impl SystemClipboard {
-    fn new() -> Result<Self> {
-        let inner = SystemClipboardHandle::new()
-            .map_err(|err| anyhow::anyhow!("clipboard init failed: {err}"))?;
-        Ok(Self { inner })
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3_599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3_600, (seconds % 3_600) / 60),
     }
 }
 
-impl ClipboardSink for SystemClipboard {
-    fn set_text(&mut self, text: String) -> Result<()> {
-        self.inner
-            .set_text(text)
-            .map_err(|err| anyhow::anyhow!("clipboard write failed: {err}"))?;
-        Ok(())
+fn render_issues(frame: &mut ratatui::Frame, view: &IssueView, time: &TimeDisplay) {
+    let area = frame.size();
+    frame.render_widget(Clear, area);
+    if let Some(detail) = &view.detail {
+        let title = format!(
+            "#{} {} (j/k scroll, y copy, o open, q back)",
+            detail.issue.iid, detail.issue.title
+        );
+        let paragraph = Paragraph::new(format_issue_detail(detail, time, SystemTime::now()))
+            .wrap(Wrap { trim: false })
+            .scroll((detail.scroll, 0))
+            .block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+        return;
+    }
+    let query = match &view.input {
+        Some(input) => format!("query*: {input}"),
+        None => format!("query: {}", view.query),
+    };
+    let title = format!(
+        "Issues: {} [{query}] (/ query, enter details, r refresh, y copy, o open, q close)",
+        view.path
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    match &view.state {
+        DetailState::Loaded if view.rows.is_empty() => {
+            frame.render_widget(Paragraph::new("No matching issues").block(block), area);
+        }
+        DetailState::Loaded => {
+            let now = SystemTime::now();
+            let group_scope = matches!(view.scope, ViewScope::Group(_));
+            let items: Vec<ListItem> = view
+                .rows
+                .iter()
+                .map(|issue| {
+                    let style = if issue.state == "closed" {
+                        Style::default().fg(Color::DarkGray)
+                    } else {
+                        Style::default()
+                    };
+                    ListItem::new(format_issue_row(issue, group_scope, now)).style(style)
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(view.selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
     }
 }
 
-struct Osc52Clipboard {
-    tmux: bool,
+fn format_issue_row(issue: &GitLabIssue, group_scope: bool, now: SystemTime) -> String {
+    let reference = match (&issue.references, group_scope) {
+        (Some(references), true) => references.full.clone(),
+        _ => format!("#{}", issue.iid),
+    };
+    let mut line = format!("{reference} [{}] {}", issue.state, issue.title);
+    if !issue.labels.is_empty() {
+        line.push_str(&format!(" | ~{}", issue.labels.join(" ~")));
+    }
+    let assignees: Vec<String> =
+        issue.assignees.iter().map(|user| format!("@{}", user.username)).collect();
+    line.push_str(&format!(
+        " | {}",
+        if assignees.is_empty() { "unassigned".to_string() } else { assignees.join(" ") }
+    ));
+    if let Some(milestone) = &issue.milestone {
+        line.push_str(&format!(" | %{}", milestone.title));
+    }
+    if let Some(updated) = &issue.updated_at {
+        line.push_str(&format!(" | updated {}", relative_age(updated, now)));
+    }
+    line
 }
 
-impl ClipboardSink for Osc52Clipboard {
-    fn set_text(&mut self, text: String) -> Result<()> {
-        let sequence = osc52_sequence(&text, self.tmux);
-        let mut stdout = io::stdout();
-        stdout.write_all(sequence.as_bytes())?;
-        stdout.flush()?;
-        Ok(())
+fn format_issue_detail(
+    detail: &IssueDetail,
+    time: &TimeDisplay,
+    now: SystemTime,
+) -> Vec<Line<'static>> {
+    let issue = &detail.issue;
+    let muted = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::from(format!("State: {}", issue.state))];
+    if let Some(author) = &issue.author {
+        lines.push(Line::from(format!("Author: @{}", author.username)));
+    }
+    if !issue.labels.is_empty() {
+        lines.push(Line::from(format!("Labels: {}", issue.labels.join(", "))));
+    }
+    if let Some(milestone) = &issue.milestone {
+        lines.push(Line::from(format!("Milestone: {}", milestone.title)));
+    }
+    lines.push(Line::from(format!("URL: {}", issue.web_url)));
+    lines.push(Line::from(""));
+    match issue.description.as_deref().filter(|text| !text.trim().is_empty()) {
+        Some(description) => lines.extend(render_markdown(description)),
+        None => lines.push(Line::from(Span::styled("No description", muted))),
+    }
+    lines.push(Line::from(""));
+    match &detail.state {
+        DetailState::Loaded => {
+            let comments = detail.notes.iter().filter(|note| !note.system).count();
+            lines.push(Line::from(Span::styled(
+                format!("Comments ({comments})"),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            for note in &detail.notes {
+                let author = note
+                    .author
+                    .as_ref()
+                    .map_or_else(|| "unknown".to_string(), |user| format!("@{}", user.username));
+                let when = note
+                    .created_at
+                    .as_deref()
+                    .map_or_else(String::new, |created| time.describe(created, now));
+                if note.system {
+                    lines.push(Line::from(Span::styled(
+                        format!("{author} {} {when}", note.body),
+                        muted,
+                    )));
+                    continue;
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    format!("{author} {when}"),
+                    Style::default().fg(Color::Yellow),
+                )));
+                lines.extend(render_markdown(&note.body));
+            }
+        }
+        DetailState::Failed(err) => lines.push(Line::from(format!("Comments unavailable: {err}"))),
+        DetailState::Partial | DetailState::Loading => {
+            lines.push(Line::from("Loading comments..."));
+        }
     }
+    lines
 }
 
-struct SystemClipboardProbe;
-
-impl ClipboardProbe for SystemClipboardProbe {
-    fn arboard_ok(&self) -> bool {
-        SystemClipboardHandle::new().is_ok()
+fn split_query(query: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for ch in query.chars() {
+        match ch {
+            '"' => quoted = !quoted,
+            ch if ch.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            ch => current.push(ch),
+        }
     }
-
-    fn has_wayland(&self) -> bool {
-        env::var_os("WAYLAND_DISPLAY").is_some()
+    if quoted {
+        anyhow::bail!("unterminated quote");
     }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
 
-    fn has_display(&self) -> bool {
-        env::var_os("DISPLAY").is_some()
+fn parse_issue_query(query: &str) -> Result<Vec<(&'static str, String)>> {
+    let mut labels = Vec::new();
+    let mut excluded_labels = Vec::new();
+    let mut search = Vec::new();
+    let mut params: Vec<(&'static str, String)> = Vec::new();
+    let mut state = "opened".to_string();
+    for token in split_query(query)? {
+        let Some((key, value)) = token.split_once(':') else {
+            search.push(token);
+            continue;
+        };
+        if value.is_empty() {
+            anyhow::bail!("missing value for {key}");
+        }
+        match key {
+            "label" => labels.push(value.to_string()),
+            "-label" => excluded_labels.push(value.to_string()),
+            "assignee" => match value.to_lowercase().as_str() {
+                "none" => params.push(("assignee_id", "None".to_string())),
+                "any" => params.push(("assignee_id", "Any".to_string())),
+                _ => params.push(("assignee_username", value.trim_start_matches('@').to_string())),
+            },
+            "author" => params.push(("author_username", value.trim_start_matches('@').to_string())),
+            "milestone" => params.push(("milestone", value.to_string())),
+            "state" => match value {
+                "opened" | "open" => state = "opened".to_string(),
+                "closed" => state = "closed".to_string(),
+                "all" => state = "all".to_string(),
+                _ => anyhow::bail!("unknown state {value}"),
+            },
+            _ => anyhow::bail!("unknown filter {key}"),
+        }
+    }
+    params.insert(0, ("state", state));
+    if !labels.is_empty() {
+        params.push(("labels", labels.join(",")));
     }
+    if !excluded_labels.is_empty() {
+        params.push(("not[labels]", excluded_labels.join(",")));
+    }
+    if !search.is_empty() {
+        params.push(("search", search.join(" ")));
+    }
+    Ok(params)
+}
 
-    fn has_tmux(&self) -> bool {
-        env::var_os("TMUX").is_some()
+fn render_merge_requests(frame: &mut ratatui::Frame, view: &MergeRequestView) {
+    let area = frame.size();
+    let title = format!(
+        "Merge requests: {} [{}] (f filter, r refresh, y copy, o open, q close)",
+        view.path,
+        view.filter.label()
+    );
+    let block = Block::default().title(title).borders(Borders::ALL);
+    frame.render_widget(Clear, area);
+    match &view.state {
+        DetailState::Loaded if view.rows.is_empty() => {
+            frame.render_widget(Paragraph::new("No open merge requests").block(block), area);
+        }
+        DetailState::Loaded => {
+            let now = SystemTime::now();
+            let group_scope = matches!(view.scope, ViewScope::Group(_));
+            let items: Vec<ListItem> = view
+                .rows
+                .iter()
+                .map(|row| {
+                    let text = format_merge_request_row(row, group_scope, now);
+                    let mut spans = vec![Span::raw(text)];
+                    if let Some(status) = &row.pipeline {
+                        spans.push(Span::styled(
+                            format!(" {status}"),
+                            Style::default().fg(pipeline_color(status)),
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            state.select(Some(view.selected));
+            frame.render_stateful_widget(list, area, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), area);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), area);
+        }
     }
+}
 
-    fn command_exists(&self, command: &str) -> bool {
-        Command::new("sh")
-            .arg("-c")
-            .arg(format!("command -v {command} >/dev/null 2>&1"))
-            .status()
-            .map(|status| status.success())
-            .unwrap_or(false)
+fn format_merge_request_row(row: &MergeRequestRow, group_scope: bool, now: SystemTime) -> String {
+    let merge_request = &row.merge_request;
+    let reference = match (&merge_request.references, group_scope) {
+        (Some(references), true) => references.full.clone(),
+        _ => format!("!{}", merge_request.iid),
+    };
+    let draft = if merge_request.draft { "[draft] " } else { "" };
+    let author = merge_request
+        .author
+        .as_ref()
+        .map_or_else(|| "-".to_string(), |author| format!("@{}", author.username));
+    let approvals = match row.approvals {
+        Some((given, required)) => format!("{given}/{required} approvals"),
+        None => "-".to_string(),
+    };
+    let age = merge_request
+        .created_at
+        .as_deref()
+        .map_or_else(|| "-".to_string(), |created| relative_age(created, now));
+    format!(
+        "{reference} {draft}{} | {author} -> {} | {approvals} | {age}",
+        merge_request.title, merge_request.target_branch
+    )
+}
+
+fn days_since(timestamp: &str, now: SystemTime) -> Option<u64> {
+    days_until(timestamp, now).map(|days| (-days).max(0) as u64)
+}
+
+fn format_compact_age(days: u64) -> String {
+    match days {
+        0..=59 => format!("{days}d"),
+        60..=729 => format!("{}mo", days / 30),
+        _ => format!("{}y", days / 365),
     }
 }
 
-fn select_clipboard_backend(probe: &dyn ClipboardProbe) -> ClipboardBackend {
-    if probe.arboard_ok() {
-        ClipboardBackend::Arboard
-    } else if probe.has_wayland() && probe.command_exists("wl-copy") {
-        ClipboardBackend::WlCopy
-    } else if probe.has_display() && probe.command_exists("xclip") {
-        ClipboardBackend::Xclip
-    } else if probe.has_tmux() {
-        ClipboardBackend::Osc52
-    } else {
-        ClipboardBackend::None
+/// Grades time since last activity: green for the last month, yellow up to
+/// a quarter, light red until the stale threshold and red beyond it.
+fn age_color(days: u64, stale_days: u64) -> Color {
+    match days {
+        _ if days >= stale_days => Color::Red,
+        0..=30 => Color::Green,
+        31..=90 => Color::Yellow,
+        _ => Color::LightRed,
     }
 }
 
-fn build_clipboard() -> Option<Box<dyn ClipboardSink>> {
-    let probe = SystemClipboardProbe;
-    match select_clipboard_backend(&probe) {
-        ClipboardBackend::Arboard => SystemClipboard::new()
-            .ok()
-            .map(|clipboard| Box::new(clipboard) as Box<dyn ClipboardSink>),
-        ClipboardBackend::WlCopy => Some(Box::new(CommandClipboard::new("wl-copy", &[]))),
-        ClipboardBackend::Xclip => Some(Box::new(CommandClipboard::new(
-            "xclip",
-            &["-selection", "clipboard"],
-        ))),
-        ClipboardBackend::Osc52 => Some(Box::new(Osc52Clipboard { tmux: true })),
-        ClipboardBackend::None => None,
+fn pipeline_glyph(status: &str) -> &'static str {
+    match status {
+        "success" => "✔",
+        "failed" => "✖",
+        "running" => "●",
+        "pending" | "preparing" | "waiting_for_resource" | "created" => "○",
+        "canceled" => "⊘",
+        "skipped" => "»",
+        "manual" | "scheduled" => "⚙",
+        _ => "?",
     }
 }
 
-fn osc52_sequence(text: &str, tmux: bool) -> String {
-    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
-    let osc = format!("\x1b]52;c;{}\x07", encoded);
-    if tmux {
-        format!("\x1bPtmux;\x1b{}\x1b\\", osc)
-    } else {
-        osc
+fn pipeline_color(status: &str) -> Color {
+    match status {
+        "success" => Color::Green,
+        "failed" => Color::Red,
+        "running" | "pending" | "preparing" | "waiting_for_resource" => Color::Yellow,
+        "canceled" | "skipped" => Color::DarkGray,
+        "manual" | "scheduled" | "created" => Color::Blue,
+        _ => Color::Gray,
     }
 }
 
-struct SystemBrowser;
+fn render_pager(frame: &mut ratatui::Frame, pager: &FilePager) {
+    let area = frame.size();
+    let lines = match (&pager.state, &pager.content) {
+        (DetailState::Loaded, Some(content)) => {
+            let width = pager.line_count().to_string().len();
+            content
+                .lines()
+                .enumerate()
+                .map(|(idx, line)| {
+                    let mut spans = vec![Span::styled(
+                        format!("{:>width$} ", idx + 1),
+                        Style::default().fg(Color::DarkGray),
+                    )];
+                    spans.extend(highlight_line(line, &pager.path));
+                    Line::from(spans)
+                })
+                .collect()
+        }
+        (DetailState::Failed(err), _) => vec![Line::from(format!("File unavailable: {err}"))],
+        _ => vec![Line::from("Loading...")],
+    };
+    let title = format!(
+        "{} @ {} (j/k scroll, y copy permalink, o open, q close)",
+        pager.path, pager.ref_name
+    );
+    let paragraph = Paragraph::new(lines)
+        .scroll((pager.scroll, 0))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
 
-impl BrowserOpener for SystemBrowser {
-    fn open(&mut self, url: &str) -> Result<()> {
-        open::that(url).map_err(|err| anyhow::anyhow!("open failed: {err}"))?;
-        Ok(())
+fn render_ref_picker(frame: &mut ratatui::Frame, picker: &RefPicker) {
+    let area = frame.size();
+    let width = (area.width / 2).max(30).min(area.width);
+    let height = (area.height * 2 / 3).max(5).min(area.height);
+    let rect = Rect::new(
+        area.width.saturating_sub(width) / 2,
+        area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    let block = Block::default()
+        .title("Switch branch/tag (enter select, esc cancel)")
+        .borders(Borders::ALL);
+    frame.render_widget(Clear, rect);
+    match &picker.state {
+        DetailState::Loaded => {
+            let items: Vec<ListItem> = picker
+                .refs
+                .iter()
+                .map(|(kind, name)| ListItem::new(format!("{kind:<6} {name}")))
+                .collect();
+            let list = List::new(items)
+                .block(block)
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            let mut state = ListState::default();
+            if !picker.refs.is_empty() {
+                state.select(Some(picker.selected));
+            }
+            frame.render_stateful_widget(list, rect, &mut state);
+        }
+        DetailState::Failed(err) => {
+            frame.render_widget(Paragraph::new(format!("Failed: {err}")).block(block), rect);
+        }
+        DetailState::Partial | DetailState::Loading => {
+            frame.render_widget(Paragraph::new("Loading...").block(block), rect);
+        }
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GitLabGroup {
-    id: usize,
-    name: String,
-    web_url: String,
-    full_path: String,
-    visibility: String,
-    #[serde(default)]
-    parent_id: Option<usize>,
+struct SyntaxRules {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    single_quote_strings: bool,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GitLabProject {
-    name: String,
-    web_url: String,
-    path_with_namespace: String,
-    visibility: String,
-    last_activity_at: Option<String>,
-    #[serde(default)]
-    namespace: Option<GitLabNamespace>,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GroupProjects {
-    group_id: usize,
-    projects: Vec<GitLabProject>,
-}
-
-#[derive(Debug, Deserialize)]
-struct GitLabUser {
-    username: String,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct GitLabNamespace {
-    kind: String,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
-struct PersonalProjects {
-    username: String,
-    web_url: String,
-    projects: Vec<GitLabProject>,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct CacheData {
-    created_at: u64,
-    groups: Vec<GitLabGroup>,
-    projects_by_group: Vec<GroupProjects>,
-    personal: Option<PersonalProjects>,
-}
-
-struct CacheStore {
-    path: PathBuf,
-    ttl: Duration,
+fn syntax_rules(path: &str) -> Option<SyntaxRules> {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase())?;
+    let rules = match extension.as_str() {
+        "rs" => SyntaxRules {
+            keywords: &[
+                "as", "break", "const", "continue", "crate", "else", "enum", "fn", "for", "if",
+                "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+                "return", "self", "Self", "static", "struct", "trait", "type", "use", "where",
+                "while", "true", "false",
+            ],
+            line_comment: "//",
+            single_quote_strings: false,
+        },
+        "py" => SyntaxRules {
+            keywords: &[
+                "and", "as", "class", "def", "elif", "else", "except", "False", "for", "from",
+                "if", "import", "in", "is", "lambda", "None", "not", "or", "pass", "raise",
+                "return", "True", "try", "while", "with", "yield",
+            ],
+            line_comment: "#",
+            single_quote_strings: true,
+        },
+        "js" | "jsx" | "ts" | "tsx" | "go" | "java" | "c" | "h" | "cpp" | "hpp" | "cs" => {
+            SyntaxRules {
+                keywords: &[
+                    "break", "case", "class", "const", "continue", "default", "else", "export",
+                    "false", "for", "func", "function", "if", "import", "interface", "let",
+                    "new", "null", "package", "private", "public", "return", "static",
+                    "struct", "switch", "this", "true", "type", "var", "void", "while",
+                ],
+                line_comment: "//",
+                single_quote_strings: true,
+            }
+        }
+        "sh" | "bash" | "yml" | "yaml" | "toml" | "rb" | "dockerfile" => SyntaxRules {
+            keywords: &[
+                "case", "do", "done", "elif", "else", "end", "esac", "fi", "for", "function",
+                "if", "in", "then", "while", "true", "false", "def", "class", "module",
+            ],
+            line_comment: "#",
+            single_quote_strings: true,
+        },
+        _ => return None,
+    };
+    Some(rules)
 }
 
-impl CacheStore {
-    fn new(path: PathBuf, ttl: Duration) -> Self {
-        Self { path, ttl }
-    }
-
-    fn load(&self) -> Result<Option<CacheData>> {
-        if !self.path.exists() {
-            return Ok(None);
+fn highlight_line(line: &str, path: &str) -> Vec<Span<'static>> {
+    let Some(rules) = syntax_rules(path) else {
+        return vec![Span::raw(line.to_string())];
+    };
+    let mut spans = Vec::new();
+    let mut rest = line;
+    while !rest.is_empty() {
+        if rest.starts_with(rules.line_comment) {
+            spans.push(Span::styled(rest.to_string(), Style::default().fg(Color::DarkGray)));
+            break;
         }
-        let data = match std::fs::read(&self.path) {
-            Ok(data) => data,
-            Err(_) => return Ok(None),
-        };
-        let cache: CacheData = match serde_json::from_slice(&data) {
-            Ok(cache) => cache,
-            Err(_) => return Ok(None),
-        };
-        if cache_is_valid(cache.created_at, self.ttl, SystemTime::now()) {
-            Ok(Some(cache))
-        } else {
-            Ok(None)
+        let ch = rest.chars().next().unwrap_or(' ');
+        if ch == '"' || (ch == '\'' && rules.single_quote_strings) {
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (idx, next) in rest.char_indices().skip(1) {
+                if next == ch && !escaped {
+                    end = idx + 1;
+                    break;
+                }
+                escaped = next == '\\' && !escaped;
+            }
+            spans.push(Span::styled(rest[..end].to_string(), Style::default().fg(Color::Green)));
+            rest = &rest[end..];
+            continue;
         }
-    }
-
-    fn store(&self, cache: &CacheData) -> Result<()> {
-        if let Some(parent) = self.path.parent() {
-            std::fs::create_dir_all(parent)?;
+        if ch.is_alphanumeric() || ch == '_' {
+            let end = rest
+                .find(|next: char| !(next.is_alphanumeric() || next == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let style = if rules.keywords.contains(&word) {
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+            } else if word.starts_with(|first: char| first.is_ascii_digit()) {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(word.to_string(), style));
+            rest = &rest[end..];
+            continue;
         }
-        let data = serde_json::to_vec_pretty(cache)?;
-        std::fs::write(&self.path, data)?;
-        Ok(())
+        spans.push(Span::raw(ch.to_string()));
+        rest = &rest[ch.len_utf8()..];
     }
+    spans
 }
 
-fn cache_is_valid(created_at: u64, ttl: Duration, now: SystemTime) -> bool {
-    let Ok(now) = now.duration_since(UNIX_EPOCH) else {
-        return false;
-    };
-    let now = now.as_secs();
-    let ttl = ttl.as_secs();
-    now.saturating_sub(created_at) <= ttl
+fn ui_loading(frame: &mut ratatui::Frame, tick: usize) {
+    let block = Block::default().title("GitLab Tree").borders(Borders::ALL);
+    let message = loading_message(tick);
+    let paragraph = Paragraph::new(message).block(block);
+    frame.render_widget(paragraph, frame.size());
 }
 
-fn fetch_groups(config: &Config) -> Result<Vec<GitLabGroup>> {
-    let client = reqwest::blocking::Client::new();
-    let base = config.gitlab_url.trim_end_matches('/');
-    let url = format!("{base}/api/v4/groups");
-    let mut page = 1usize;
-    let mut all = Vec::new();
+fn format_node_details(node: &Node, time: &TimeDisplay, now: SystemTime) -> Vec<String> {
+    let kind = match node.kind {
+        NodeKind::Group => "Group",
+        NodeKind::Project => "Project",
+        NodeKind::Shared => "Shared with this group",
+        NodeKind::Directory => "Directory",
+        NodeKind::File => "File",
+    };
+    let mut lines = vec![
+        format!("Name: {}", node.name),
+        format!("Kind: {kind}"),
+        format!("Path: {}", node.path),
+        format!("Visibility: {}", node.visibility),
+        format!("URL: {}", node.url),
+    ];
+    if node.archived {
+        lines.push("Archived: yes".to_string());
+    }
+    if let Some(upstream) = &node.relation.forked_from {
+        lines.push(format!("Forked from: {}", upstream.path_with_namespace));
+        lines.push(format!("Upstream URL: {}", upstream.web_url));
+    }
+    if node.relation.mirror {
+        lines.push("Mirror: pull mirror".to_string());
+    }
+    if node.relation.shared {
+        lines.push("Shared: yes (owned by another namespace)".to_string());
+    }
+    if let Some(last_activity) = &node.last_activity {
+        lines.push(format!("Last activity: {}", time.describe(last_activity, now)));
+    }
+    if let Some(error) = &node.error {
+        lines.push(format!("Error: {error}"));
+        lines.push("Press R to retry".to_string());
+    }
+    lines
+}
 
-    loop {
-        let mut query: Vec<(&str, String)> = vec![
-            ("per_page", config.filters.per_page.to_string()),
-            ("page", page.to_string()),
-            ("membership", "true".to_string()),
+fn format_project_details(
+    details: &ProjectDetails,
+    time: &TimeDisplay,
+    now: SystemTime,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(project) = &details.project {
+        lines.push(format!("Project ID: {}", project.id));
+        if let Some(description) = project.description.as_deref().filter(|text| !text.is_empty()) {
+            lines.push(format!("Description: {description}"));
+        }
+        if let Some(branch) = &project.default_branch {
+            lines.push(format!("Default branch: {branch}"));
+        }
+        if !project.topics.is_empty() {
+            lines.push(format!("Topics: {}", project.topics.join(", ")));
+        }
+        let counts = [
+            ("Stars", project.star_count),
+            ("Forks", project.forks_count),
+            ("Open issues", project.open_issues_count),
         ];
-        if let Some(value) = config.filters.all_available {
-            query.push(("all_available", value.to_string()));
+        let counts: Vec<String> = counts
+            .iter()
+            .filter_map(|(label, value)| value.map(|value| format!("{label}: {value}")))
+            .collect();
+        if !counts.is_empty() {
+            lines.push(counts.join(" | "));
         }
-        if let Some(value) = config.filters.owned {
-            query.push(("owned", value.to_string()));
+        if let Some(created_at) = &project.created_at {
+            lines.push(format!("Created: {}", time.describe(created_at, now)));
         }
-        if let Some(value) = config.filters.top_level_only {
-            query.push(("top_level_only", value.to_string()));
+        if let Some(creator) = &details.creator {
+            lines.push(format!("Creator: @{creator}"));
         }
-        if let Some(value) = &config.filters.visibility {
-            query.push(("visibility", value.to_string()));
+        if let Some(stats) = &project.statistics {
+            lines.push(format!("Repository size: {}", format_bytes(stats.repository_size)));
+            lines.push(format!(
+                "Storage: {} (wiki {}, LFS {}, artifacts {}, packages {}, snippets {}, uploads {})",
+                format_bytes(stats.storage_size),
+                format_bytes(stats.wiki_size),
+                format_bytes(stats.lfs_objects_size),
+                format_bytes(stats.job_artifacts_size),
+                format_bytes(stats.packages_size),
+                format_bytes(stats.snippets_size),
+                format_bytes(stats.uploads_size)
+            ));
+            lines.push(format!("Commits: {}", stats.commit_count));
         }
-
-        let resp = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", &config.gitlab_token)
-            .query(&query)
-            .send()?
-            .error_for_status()?;
-
-        let next_page = resp
-            .headers()
-            .get("x-next-page")
-            .and_then(|value| value.to_str().ok())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        let mut page_groups: Vec<GitLabGroup> = resp.json()?;
-        all.append(&mut page_groups);
-
-        if next_page.is_empty() {
-            break;
+        if let Some(ssh) = &project.ssh_url_to_repo {
+            lines.push(format!("Clone (SSH): {ssh}"));
+        }
+        if let Some(http) = &project.http_url_to_repo {
+            lines.push(format!("Clone (HTTP): {http}"));
         }
-
-        page = next_page
-            .parse()
-            .map_err(|_| anyhow::anyhow!("invalid x-next-page header: {next_page}"))?;
     }
-
-    Ok(all)
+    match &details.state {
+        DetailState::Loading => lines.push("Loading details...".to_string()),
+        DetailState::Failed(err) => lines.push(format!("Details unavailable: {err}")),
+        DetailState::Partial | DetailState::Loaded => {}
+    }
+    lines
 }
 
-fn fetch_group_projects(config: &Config, group_id: usize) -> Result<Vec<GitLabProject>> {
-    let client = reqwest::blocking::Client::new();
-    let base = config.gitlab_url.trim_end_matches('/');
-    let url = format!("{base}/api/v4/groups/{group_id}/projects");
-    let mut page = 1usize;
-    let mut all = Vec::new();
-
-    loop {
-        let mut query: Vec<(&str, String)> = vec![
-            ("per_page", config.filters.per_page.to_string()),
-            ("page", page.to_string()),
-            ("simple", "true".to_string()),
-        ];
-        if let Some(value) = config.filters.include_subgroups {
-            query.push(("include_subgroups", value.to_string()));
+/// Rolls up every group in one post-order pass, so each node is visited once
+/// however deep the tree is. Shared projects and groups are left out.
+fn rollup_group_stats(
+    nodes: &[Node],
+    project_details: &HashMap<usize, ProjectDetails>,
+) -> HashMap<usize, GroupStats> {
+    let mut rolled: HashMap<usize, GroupStats> = HashMap::new();
+    let mut stack: Vec<(usize, bool)> = (0..nodes.len())
+        .filter(|id| nodes[*id].kind == NodeKind::Group)
+        .map(|id| (id, false))
+        .collect();
+    while let Some((id, children_done)) = stack.pop() {
+        if rolled.contains_key(&id) {
+            continue;
         }
-        if let Some(value) = &config.filters.visibility {
-            query.push(("visibility", value.to_string()));
+        let children = nodes[id].children.iter().copied();
+        let children = children.filter(|child| !nodes[*child].relation.shared);
+        if !children_done {
+            stack.push((id, true));
+            stack.extend(
+                children
+                    .filter(|child| nodes[*child].kind == NodeKind::Group)
+                    .map(|child| (child, false)),
+            );
+            continue;
         }
-
-        let resp = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", &config.gitlab_token)
-            .query(&query)
-            .send()?
-            .error_for_status()?;
-
-        let next_page = resp
-            .headers()
-            .get("x-next-page")
-            .and_then(|value| value.to_str().ok())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        let mut page_projects: Vec<GitLabProject> = resp.json()?;
-        all.append(&mut page_projects);
-
-        if next_page.is_empty() {
-            break;
+        let mut stats = GroupStats::default();
+        for child in children {
+            match nodes[child].kind {
+                NodeKind::Group => {
+                    stats.merge(&rolled[&child]);
+                    stats.direct_subgroups += 1;
+                    stats.subgroups += 1;
+                }
+                NodeKind::Project => stats.add_project(&nodes[child], project_details),
+                NodeKind::Shared | NodeKind::Directory | NodeKind::File => {}
+            }
         }
+        rolled.insert(id, stats);
+    }
+    rolled
+}
 
-        page = next_page
-            .parse()
-            .map_err(|_| anyhow::anyhow!("invalid x-next-page header: {next_page}"))?;
+fn format_group_stats(stats: &GroupStats, now: SystemTime) -> String {
+    let mut parts = vec![format!("{} proj", stats.projects)];
+    if stats.public_projects > 0 {
+        parts.push(format!("{} public", stats.public_projects));
     }
+    if stats.sized_projects > 0 {
+        let size = format_bytes(stats.repository_size);
+        let partial = if stats.sized_projects < stats.projects { "+" } else { "" };
+        parts.push(format!("{size}{partial}"));
+    }
+    match stats.latest_activity {
+        Some(latest) => {
+            let days = (DateTime::<Utc>::from(now) - latest).num_days().max(0) as u64;
+            parts.push(format_compact_age(days));
+        }
+        None => parts.push("-".to_string()),
+    }
+    parts.join(", ")
+}
 
-    Ok(all)
+fn access_level_name(level: u64) -> &'static str {
+    match level {
+        0 => "No access",
+        5 => "Minimal access",
+        10 => "Guest",
+        15 => "Planner",
+        20 => "Reporter",
+        30 => "Developer",
+        40 => "Maintainer",
+        50 => "Owner",
+        _ => "Custom",
+    }
 }
 
-fn fetch_current_user(config: &Config) -> Result<GitLabUser> {
-    let client = reqwest::blocking::Client::new();
-    let base = config.gitlab_url.trim_end_matches('/');
-    let url = format!("{base}/api/v4/user");
-    let user = client
-        .get(&url)
-        .header("PRIVATE-TOKEN", &config.gitlab_token)
-        .send()?
-        .error_for_status()?
-        .json::<GitLabUser>()?;
-    Ok(user)
+fn format_group_details(
+    details: &GroupDetails,
+    summary: &GroupStats,
+    time: &TimeDisplay,
+    now: SystemTime,
+) -> Vec<String> {
+    let group = &details.group;
+    let mut lines = vec![format!("Group ID: {}", group.id)];
+    if let Some(full_name) = &group.full_name {
+        lines.push(format!("Full name: {full_name}"));
+    }
+    if let Some(description) = group.description.as_deref().filter(|text| !text.is_empty()) {
+        lines.push(format!("Description: {description}"));
+    }
+    lines.push(format!(
+        "Subgroups: {} direct, {} total",
+        summary.direct_subgroups, summary.subgroups
+    ));
+    lines.push(format!(
+        "Projects: {} direct, {} total",
+        summary.direct_projects, summary.projects
+    ));
+    if let Some(count) = details.member_count {
+        lines.push(format!("Members: {count}"));
+    }
+    if let Some(level) = details.access_level {
+        lines.push(format!("My access: {}", access_level_name(level)));
+    }
+    if let Some(created_at) = &group.created_at {
+        lines.push(format!("Created: {}", time.describe(created_at, now)));
+    }
+    if let Some((path, activity)) = &summary.latest_project {
+        lines.push(format!("Most recently active: {path}, {}", time.describe(activity, now)));
+    }
+    match &details.state {
+        DetailState::Loading => lines.push("Loading details...".to_string()),
+        DetailState::Failed(err) => lines.push(format!("Details unavailable: {err}")),
+        DetailState::Partial | DetailState::Loaded => {}
+    }
+    lines
 }
 
-fn fetch_owned_projects(config: &Config) -> Result<Vec<GitLabProject>> {
-    let client = reqwest::blocking::Client::new();
-    let base = config.gitlab_url.trim_end_matches('/');
-    let url = format!("{base}/api/v4/projects");
-    let mut page = 1usize;
-    let mut all = Vec::new();
+fn readme_path(project: &GitLabProject) -> Option<String> {
+    let url = project.readme_url.as_deref()?;
+    let (_, blob) = url.split_once("/-/blob/")?;
+    let branch = project.default_branch.as_deref()?;
+    let path = blob.strip_prefix(branch)?.strip_prefix('/')?;
+    Some(path.to_string())
+}
 
-    loop {
-        let mut query: Vec<(&str, String)> = vec![
-            ("per_page", config.filters.per_page.to_string()),
-            ("page", page.to_string()),
-            ("simple", "true".to_string()),
-            ("owned", "true".to_string()),
-        ];
-        if let Some(value) = &config.filters.visibility {
-            query.push(("visibility", value.to_string()));
-        }
-
-        let resp = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", &config.gitlab_token)
-            .query(&query)
-            .send()?
-            .error_for_status()?;
-
-        let next_page = resp
-            .headers()
-            .get("x-next-page")
-            .and_then(|value| value.to_str().ok())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        let page_projects: Vec<GitLabProject> = resp.json()?;
-        let mut personal_projects = filter_personal_projects(page_projects);
-        all.append(&mut personal_projects);
-
-        if next_page.is_empty() {
-            break;
+fn render_markdown(text: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for raw in text.lines() {
+        let trimmed = raw.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
         }
-
-        page = next_page
-            .parse()
-            .map_err(|_| anyhow::anyhow!("invalid x-next-page header: {next_page}"))?;
+        if in_code {
+            lines.push(Line::from(Span::styled(
+                format!("  {raw}"),
+                Style::default().fg(Color::Cyan),
+            )));
+            continue;
+        }
+        let level = trimmed.chars().take_while(|ch| *ch == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let style = if level == 1 {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+            } else {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            };
+            lines.push(Line::from(Span::styled(trimmed[level..].trim().to_string(), style)));
+            continue;
+        }
+        let indent = &raw[..raw.len() - trimmed.len()];
+        let mut spans = Vec::new();
+        let body = if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            spans.push(Span::raw(format!("{indent}• ")));
+            item
+        } else {
+            spans.push(Span::raw(indent.to_string()));
+            trimmed
+        };
+        spans.extend(markdown_inline(body));
+        lines.push(Line::from(spans));
     }
-
-    Ok(all)
+    lines
 }
 
-fn fetch_personal_projects(config: &Config) -> Result<PersonalProjects> {
-    let user = fetch_current_user(config)?;
-    let projects = fetch_owned_projects(config)?;
-    let base = config.gitlab_url.trim_end_matches('/');
-    let web_url = format!("{base}/{}", user.username);
-    Ok(PersonalProjects {
-        username: user.username,
-        web_url,
-        projects,
-    })
+fn markdown_inline(text: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            spans.push(Span::raw(std::mem::take(&mut plain)));
+            spans.push(Span::styled(
+                rest[1..=end].to_string(),
+                Style::default().fg(Color::Cyan),
+            ));
+            rest = &rest[end + 2..];
+            continue;
+        }
+        if ch == '['
+            && let Some(close) = rest.find("](")
+            && let Some(end) = rest[close..].find(')')
+        {
+            let label = &rest[1..close];
+            let url = &rest[close + 2..close + end];
+            spans.push(Span::raw(std::mem::take(&mut plain)));
+            spans.push(Span::styled(
+                label.to_string(),
+                Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+            ));
+            spans.push(Span::styled(format!(" <{url}>"), Style::default().fg(Color::DarkGray)));
+            rest = &rest[close + end + 1..];
+            continue;
+        }
+        plain.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+    if !plain.is_empty() {
+        spans.push(Span::raw(plain));
+    }
+    spans.retain(|span| !span.content.is_empty());
+    spans
 }
 
-fn filter_personal_projects(projects: Vec<GitLabProject>) -> Vec<GitLabProject> {
-    projects
-        .into_iter()
-        .filter(|project| project.namespace.as_ref().map(|ns| ns.kind.as_str()) == Some("user"))
-        .collect()
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
 }
 
-fn fetch_projects_by_group(
-    config: &Config,
-    groups: &[GitLabGroup],
-) -> Result<Vec<GroupProjects>> {
-    let mut projects = Vec::with_capacity(groups.len());
-    for group in groups {
-        let group_projects = fetch_group_projects(config, group.id)?;
-        projects.push(GroupProjects {
-            group_id: group.id,
-            projects: group_projects,
-        });
+fn relation_tags(relation: &NodeRelation) -> String {
+    let mut tags = String::new();
+    if relation.forked_from.is_some() {
+        tags.push_str(" [fork]");
     }
-    Ok(projects)
-}
-fn read_env_optional<F>(reader: &F, key: &str) -> Option<String>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    reader(key).filter(|value| !value.trim().is_empty())
+    if relation.mirror {
+        tags.push_str(" [mirror]");
+    }
+    if relation.shared {
+        tags.push_str(" [shared]");
+    }
+    tags
 }
 
-fn read_env_required<F>(reader: &F, key: &str) -> Result<String>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    match read_env_optional(reader, key) {
-        Some(value) => Ok(value),
-        None => anyhow::bail!("missing required environment variable: {key}"),
-    }
+fn render_log_panel(frame: &mut ratatui::Frame, log: &EventLog, area: Rect) {
+    let entries = log.entries();
+    let capacity = usize::from(area.height.saturating_sub(2));
+    let start = entries.len().saturating_sub(capacity);
+    let items: Vec<ListItem> = entries[start..]
+        .iter()
+        .map(|entry| {
+            let style = match entry.level {
+                LogLevel::Error => Style::default().fg(Color::Red),
+                LogLevel::Warn => Style::default().fg(Color::Yellow),
+                LogLevel::Info | LogLevel::Debug => Style::default(),
+            };
+            ListItem::new(format_log_entry(entry)).style(style)
+        })
+        .collect();
+    let list = List::new(items).block(Block::default().title("Log").borders(Borders::ALL));
+    frame.render_widget(list, area);
 }
 
-fn read_env_bool_optional<F>(reader: &F, key: &str) -> Result<Option<bool>>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    let Some(value) = read_env_optional(reader, key) else {
-        return Ok(None);
+fn render_readme(frame: &mut ratatui::Frame, readme: &ProjectReadme, area: Rect) {
+    let lines = match (&readme.state, &readme.content) {
+        (DetailState::Loaded, Some(content)) => render_markdown(content),
+        (DetailState::Failed(err), _) => vec![Line::from(format!("README unavailable: {err}"))],
+        (DetailState::Loaded, None) => vec![Line::from("No README")],
+        (DetailState::Partial | DetailState::Loading, _) => vec![Line::from("Loading README...")],
     };
-    let normalized = value.to_lowercase();
-    match normalized.as_str() {
-        "1" | "true" | "yes" | "on" => Ok(Some(true)),
-        "0" | "false" | "no" | "off" => Ok(Some(false)),
-        _ => anyhow::bail!("invalid boolean for {key}: {value}"),
-    }
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((readme.scroll, 0))
+        .block(Block::default().title("README (J/K scroll)").borders(Borders::ALL));
+    frame.render_widget(paragraph, area);
 }
 
-fn read_env_u16_optional<F>(reader: &F, key: &str) -> Result<Option<u16>>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    let Some(value) = read_env_optional(reader, key) else {
-        return Ok(None);
-    };
-    let parsed = value
-        .parse::<u16>()
-        .map_err(|_| anyhow::anyhow!("invalid integer for {key}: {value}"))?;
-    Ok(Some(parsed))
+fn format_log_entry(entry: &LogEntry) -> String {
+    let time = format_utc_timestamp(entry.timestamp);
+    let time = time.get(11..19).unwrap_or(&time);
+    format!(
+        "{time} {:<5} {:<9} {}",
+        entry.level.label(),
+        entry.category,
+        entry.message
+    )
 }
 
-fn read_env_u64_optional<F>(reader: &F, key: &str) -> Result<Option<u64>>
-where
-    F: Fn(&str) -> Option<String>,
-{
-    let Some(value) = read_env_optional(reader, key) else {
-        return Ok(None);
-    };
-    let parsed = value
-        .parse::<u64>()
-        .map_err(|_| anyhow::anyhow!("invalid integer for {key}: {value}"))?;
-    Ok(Some(parsed))
+fn render_toast(frame: &mut ratatui::Frame, toast: &Toast) {
+    let area = frame.size();
+    let width = (toast.message.len() as u16).saturating_add(4);
+    let height = 3;
+    let x = area.width.saturating_sub(width + 1);
+    let y = 1;
+    let rect = Rect::new(x, y, width.min(area.width), height.min(area.height));
+    let block = Block::default().title("Notice").borders(Borders::ALL);
+    let paragraph = Paragraph::new(toast.message.clone()).block(block);
+    frame.render_widget(paragraph, rect);
 }
 
-fn default_cache_path() -> PathBuf {
-    let base = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
-    base.join("gitlab-tree").join("cache.json")
+fn render_help(frame: &mut ratatui::Frame, token: Option<&TokenInfo>) {
+    let area = frame.size();
+    let mut lines: Vec<String> = [
+        "q / ctrl-c: quit",
+        "r: refresh",
+        "R: retry failed group",
+        "?: toggle help",
+        "L: toggle log panel",
+        "a: show/hide archived projects",
+        "S: show/hide group statistics column",
+        "s: cycle sort for groups/projects (by selected row)",
+        "enter: toggle expand/collapse",
+        "up/down or j/k: move selection",
+        "left/right or h/l: collapse/expand",
+        "pgup/pgdn: page navigation",
+        "J/K: scroll README preview",
+        "l/enter on project or directory: browse repository",
+        "l/enter on file: view file (y permalink, o open, q close)",
+        "b: switch branch/tag of the selected project",
+        "M: merge requests for the selected group/project (f filter)",
+        "I: issues for the selected group/project (/ query, enter details)",
+        "P: pipelines for the selected project (enter jobs, then job log)",
+        "B: branches, tags and releases (tab switch, y/o compare URL)",
+        "U: members with direct/inherited access (/ filter by user)",
+        "V: visibility audit (f flagged only, enter jump)",
+        "gg/G: jump to top/bottom",
+        "y: copy URL",
+        "o: open in browser",
+        "u: jump to/open fork upstream",
+        "/: search",
+        "esc: clear search",
+    ]
+    .iter()
+    .map(|line| line.to_string())
+    .collect();
+    if let Some(token) = token {
+        lines.push(String::new());
+        lines.extend(format_token_details(token));
+    }
+    let width = (area.width.saturating_mul(3)).saturating_div(4).max(40);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let x = area.width.saturating_sub(width) / 2;
+    let y = area.height.saturating_sub(height) / 2;
+    let rect = Rect::new(x, y, width, height);
+    let paragraph = Paragraph::new(lines.join("\n"))
+        .block(Block::default().title("Help").borders(Borders::ALL));
+    frame.render_widget(paragraph, rect);
 }
 
-#[derive(Clone)]
-struct Node {
-    name: String,
-    kind: NodeKind,
-    children: Vec<usize>,
-    expanded: bool,
-    url: String,
-    path: String,
-    visibility: String,
-    last_activity: Option<String>,
+struct LoadHandle {
+    receiver: mpsc::Receiver<Result<App>>,
+    tick: usize,
 }
 
-#[derive(Clone, Copy)]
-enum NodeKind {
-    Group,
-    Project,
+fn start_loader(api: Arc<HttpGitLabApi>) -> LoadHandle {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let result = App::from_gitlab(&api);
+        let _ = sender.send(result);
+    });
+    LoadHandle { receiver, tick: 0 }
 }
 
-struct Toast {
-    message: String,
-    remaining: u8,
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FetchRequest {
+    GroupProjects { node_id: usize, group_id: usize },
+    ProjectDetails { project_id: usize },
+    GroupDetails { group_id: usize, username: Option<String> },
+    Readme { project_id: usize, path: String, ref_name: String },
+    RepoTree { node_id: usize, project_id: usize, path: String, ref_name: String },
+    RepoFile { project_id: usize, path: String, ref_name: String },
+    Refs { project_id: usize },
+    MergeRequests { scope: ViewScope, filter: MergeRequestFilter, username: Option<String> },
+    MergeRequestDetail { project_id: usize, iid: usize },
+    Issues { scope: ViewScope, query: String },
+    IssueNotes { project_id: usize, iid: usize },
+    PipelineBadges { projects: Vec<(usize, Option<String>)> },
+    Pipelines { project_id: usize },
+    PipelineDetail { project_id: usize, pipeline_id: usize },
+    PipelineJobs { project_id: usize, pipeline_id: usize },
+    JobTrace { project_id: usize, job_id: usize },
+    JobAction { project_id: usize, job_id: usize, action: JobAction },
+    RefList { project_id: usize, tab: RefTab },
+    BranchDivergence { project_id: usize, branch: String, default: String },
+    Members { scope: ViewScope, ancestors: Vec<(usize, String)> },
 }
 
-#[derive(Clone, Copy)]
-enum KeyAction {
-    None,
-    Quit,
-    Reload,
+enum FetchPayload {
+    Projects(Vec<GitLabProject>),
+    ProjectDetails(Box<GitLabProject>, Option<String>),
+    GroupDetails(Box<GitLabGroup>, Option<usize>, Option<u64>),
+    File(String),
+    RepoTree(Vec<GitLabTreeEntry>),
+    RepoFile(String, Option<String>),
+    Refs(Vec<String>, Vec<String>),
+    MergeRequests(Vec<MergeRequestRow>),
+    MergeRequestDetail(Option<String>, (usize, usize)),
+    Issues(Vec<GitLabIssue>),
+    IssueNotes(Vec<GitLabNote>),
+    PipelineBadges(Vec<(usize, Option<String>)>),
+    Pipelines(Vec<GitLabPipeline>),
+    Pipeline(Box<GitLabPipeline>),
+    PipelineJobs(Vec<GitLabJob>),
+    JobTrace(Box<GitLabJob>, String),
+    Job(Box<GitLabJob>),
+    Branches(Vec<BranchRow>),
+    Divergence(Option<usize>, Option<usize>),
+    Tags(Vec<GitLabRef>),
+    Releases(Vec<GitLabRelease>),
+    Members(Vec<MemberRow>),
 }
 
-struct App {
-    nodes: Vec<Node>,
-    roots: Vec<usize>,
-    parent: Vec<Option<usize>>,
-    selected: usize,
-    config: Config,
-    status: Option<String>,
-    pending_g: bool,
-    toast: Option<Toast>,
-    search_query: Option<String>,
-    search_mode: bool,
-    show_help: bool,
+struct FetchResult {
+    request: FetchRequest,
+    payload: Result<FetchPayload>,
 }
 
-impl App {
-    const TOAST_TTL: u8 = 10;
+/// Runs a fetch on its own thread, sharing the app-wide HTTP client.
+fn spawn_fetch(
+    api: Arc<HttpGitLabApi>,
+    request: FetchRequest,
+    sender: mpsc::Sender<FetchResult>,
+) {
+    thread::spawn(move || {
+        let payload = run_fetch(&*api, &request);
+        let _ = sender.send(FetchResult { request, payload });
+    });
+}
 
-    fn sample_with_status(config: Config, status: String) -> Self {
-        let mut nodes = Vec::new();
+const DETAIL_CONCURRENCY: usize = 4;
+
+const PIPELINE_PAGE_SIZE: usize = 20;
+const PIPELINE_BADGE_BATCH: usize = 20;
+const PIPELINE_BADGE_RETRY: Duration = Duration::from_secs(60);
+const JOB_TAIL_INTERVAL: Duration = Duration::from_secs(3);
+/// Largest list GitLab still reports an `x-total` for.
+const COMMIT_COUNT_CAP: usize = 10_000;
+
+fn latest_pipeline_statuses(
+    api: &dyn GitLabApi,
+    projects: &[(usize, Option<String>)],
+) -> Vec<(usize, Option<String>)> {
+    let mut statuses = Vec::with_capacity(projects.len());
+    for chunk in projects.chunks(DETAIL_CONCURRENCY) {
+        thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|(project_id, default_branch)| {
+                    scope.spawn(move || {
+                        let mut query = vec![("per_page", "1".to_string())];
+                        if let Some(branch) = default_branch {
+                            query.push(("ref", branch.clone()));
+                        }
+                        let status = api
+                            .pipelines(*project_id, query)
+                            .ok()
+                            .and_then(|pipelines| pipelines.into_iter().next())
+                            .map(|pipeline| pipeline.status);
+                        (*project_id, status)
+                    })
+                })
+                .collect();
+            statuses.extend(handles.into_iter().filter_map(|handle| handle.join().ok()));
+        });
+    }
+    statuses
+}
 
-        let dev_platform = push_node(
-            &mut nodes,
-            "dev-platform",
-            NodeKind::Group,
-            "https://gitlab.example.com/dev-platform",
-            "dev-platform",
-            "private",
-            None,
-        );
-        let data = push_node(
-            &mut nodes,
-            "data",
-            NodeKind::Group,
-            "https://gitlab.example.com/data",
-            "data",
-            "private",
-            None,
-        );
-        let sec = push_node(
-            &mut nodes,
-            "security",
-            NodeKind::Group,
-            "https://gitlab.example.com/security",
-            "security",
-            "private",
-            None,
-        );
+/// Labels each effective member as direct or inherited, attributing
+/// inherited access to the nearest ancestor that grants the effective level.
+/// `ancestors` are ordered nearest first.
+fn resolve_members(
+    all: Vec<GitLabMember>,
+    direct: &[GitLabMember],
+    ancestors: &[(String, Vec<GitLabMember>)],
+) -> Vec<MemberRow> {
+    let mut rows: Vec<MemberRow> = all
+        .into_iter()
+        .map(|member| {
+            let source = if direct.iter().any(|other| other.id == member.id) {
+                MemberSource::Direct
+            } else {
+                let grants = |level: Option<u64>| {
+                    ancestors.iter().find(|(_, members)| {
+                        members.iter().any(|other| {
+                            other.id == member.id && level.is_none_or(|l| other.access_level == l)
+                        })
+                    })
+                };
+                match grants(Some(member.access_level)).or_else(|| grants(None)) {
+                    Some((path, _)) => MemberSource::Inherited(path.clone()),
+                    None => MemberSource::Shared,
+                }
+            };
+            MemberRow { member, source }
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.member
+            .access_level
+            .cmp(&a.member.access_level)
+            .then_with(|| a.member.username.cmp(&b.member.username))
+    });
+    rows
+}
 
-        let dev_backend = push_node(
-            &mut nodes,
-            "backend",
-            NodeKind::Group,
-            "https://gitlab.example.com/dev-platform/backend",
-            "dev-platform/backend",
-            "private",
-            None,
-        );
-        let dev_frontend = push_node(
-            &mut nodes,
-            "frontend",
-            NodeKind::Group,
-            "https://gitlab.example.com/dev-platform/frontend",
-            "dev-platform/frontend",
-            "private",
-            None,
-        );
-        let dev_platform_proj = push_node(
-            &mut nodes,
-            "platform-tools",
-            NodeKind::Project,
-            "https://gitlab.example.com/dev-platform/platform-tools",
-            "dev-platform/platform-tools",
-            "private",
-            None,
-        );
-        nodes[dev_platform].children.extend([dev_backend, dev_frontend, dev_platform_proj]);
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct AccessRecord {
+    path: String,
+    kind: &'static str,
+    username: String,
+    name: Option<String>,
+    access_level: &'static str,
+    source: String,
+    expires_at: Option<String>,
+    /// `None` when unknown: GitLab only reports `external` to admin tokens.
+    external: Option<bool>,
+    flags: Vec<String>,
+}
 
-        let api = push_node(
-            &mut nodes,
-            "api",
-            NodeKind::Project,
-            "https://gitlab.example.com/dev-platform/backend/api",
-            "dev-platform/backend/api",
-            "private",
-            None,
-        );
-        let auth = push_node(
-            &mut nodes,
-            "auth",
-            NodeKind::Project,
-            "https://gitlab.example.com/dev-platform/backend/auth",
-            "dev-platform/backend/auth",
-            "private",
-            None,
-        );
-        nodes[dev_backend].children.extend([api, auth]);
+/// Member rows of an access review, plus one message per group or project
+/// whose members could not be fetched.
+struct AccessReview {
+    records: Vec<AccessRecord>,
+    failures: Vec<String>,
+}
 
-        let web = push_node(
-            &mut nodes,
-            "web",
-            NodeKind::Project,
-            "https://gitlab.example.com/dev-platform/frontend/web",
-            "dev-platform/frontend/web",
-            "private",
-            None,
-        );
-        let design = push_node(
-            &mut nodes,
-            "design-system",
-            NodeKind::Project,
-            "https://gitlab.example.com/dev-platform/frontend/design-system",
-            "dev-platform/frontend/design-system",
-            "private",
-            None,
-        );
-        nodes[dev_frontend].children.extend([web, design]);
+/// Groups and projects of the loaded tree in display order, skipping shared
+/// entries, which are reviewed under their own namespace.
+fn review_targets(app: &App) -> Vec<(usize, ViewScope)> {
+    let mut targets = Vec::new();
+    let mut stack: Vec<usize> = app.roots.iter().rev().copied().collect();
+    while let Some(node_id) = stack.pop() {
+        let node = &app.nodes[node_id];
+        if node.relation.shared || node.kind == NodeKind::Shared {
+            continue;
+        }
+        let scope = match (node.kind, node.gitlab_id) {
+            (NodeKind::Group, Some(id)) => Some(ViewScope::Group(id)),
+            (NodeKind::Project, Some(id)) => Some(ViewScope::Project(id)),
+            _ => None,
+        };
+        if let Some(scope) = scope {
+            targets.push((node_id, scope));
+        }
+        stack.extend(node.children.iter().rev());
+    }
+    targets
+}
 
-        let data_ingest = push_node(
-            &mut nodes,
-            "ingest",
-            NodeKind::Group,
-            "https://gitlab.example.com/data/ingest",
-            "data/ingest",
-            "private",
-            None,
-        );
-        let data_models = push_node(
-            &mut nodes,
-            "models",
-            NodeKind::Group,
-            "https://gitlab.example.com/data/models",
-            "data/models",
-            "private",
-            None,
-        );
-        let data_tools = push_node(
-            &mut nodes,
-            "data-tools",
-            NodeKind::Project,
-            "https://gitlab.example.com/data/data-tools",
-            "data/data-tools",
-            "private",
-            None,
-        );
-        nodes[data].children.extend([data_ingest, data_models, data_tools]);
+fn visibility_rank(visibility: &str) -> u8 {
+    match visibility {
+        "public" => 2,
+        "internal" => 1,
+        _ => 0,
+    }
+}
 
-        let ingest = push_node(
-            &mut nodes,
-            "ingest",
-            NodeKind::Project,
-            "https://gitlab.example.com/data/ingest/ingest",
-            "data/ingest/ingest",
-            "private",
-            None,
-        );
-        let pipeline = push_node(
-            &mut nodes,
-            "pipeline",
-            NodeKind::Project,
-            "https://gitlab.example.com/data/ingest/pipeline",
-            "data/ingest/pipeline",
-            "private",
-            None,
-        );
-        nodes[data_ingest].children.extend([ingest, pipeline]);
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct VisibilityFinding {
+    #[serde(skip)]
+    node_id: usize,
+    path: String,
+    kind: &'static str,
+    visibility: String,
+    parent: Option<String>,
+    parent_visibility: Option<String>,
+    flags: Vec<&'static str>,
+}
 
-        let fraud = push_node(
-            &mut nodes,
-            "fraud",
-            NodeKind::Project,
-            "https://gitlab.example.com/data/models/fraud",
-            "data/models/fraud",
-            "private",
-            None,
-        );
-        let churn = push_node(
-            &mut nodes,
-            "churn",
-            NodeKind::Project,
-            "https://gitlab.example.com/data/models/churn",
-            "data/models/churn",
-            "private",
-            None,
-        );
-        nodes[data_models].children.extend([fraud, churn]);
+/// Lists public and internal groups and projects, flagging public projects
+/// below a private group and projects more visible than their parent group.
+fn visibility_audit(app: &App) -> Vec<VisibilityFinding> {
+    let mut findings = Vec::new();
+    for (node_id, scope) in review_targets(app) {
+        let node = &app.nodes[node_id];
+        let rank = visibility_rank(&node.visibility);
+        if rank == 0 {
+            continue;
+        }
+        let parent = app.parent[node_id]
+            .map(|id| &app.nodes[id])
+            .filter(|parent| parent.kind == NodeKind::Group && parent.gitlab_id.is_some());
+        let mut flags = Vec::new();
+        if matches!(scope, ViewScope::Project(_)) {
+            let private_ancestor = std::iter::successors(app.parent[node_id], |id| app.parent[*id])
+                .map(|id| &app.nodes[id])
+                .any(|group| {
+                    group.kind == NodeKind::Group
+                        && !group.relation.shared
+                        && visibility_rank(&group.visibility) == 0
+                });
+            if rank == 2 && private_ancestor {
+                flags.push("public-in-private-group");
+            }
+            if parent.is_some_and(|parent| rank > visibility_rank(&parent.visibility)) {
+                flags.push("broader-than-group");
+            }
+        }
+        findings.push(VisibilityFinding {
+            node_id,
+            path: node.path.clone(),
+            kind: match scope {
+                ViewScope::Group(_) => "group",
+                ViewScope::Project(_) => "project",
+            },
+            visibility: node.visibility.clone(),
+            parent: parent.map(|parent| parent.path.clone()),
+            parent_visibility: parent.map(|parent| parent.visibility.clone()),
+            flags,
+        });
+    }
+    findings
+}
 
-        let sec_tools = push_node(
-            &mut nodes,
-            "sec-tools",
-            NodeKind::Project,
-            "https://gitlab.example.com/security/sec-tools",
-            "security/sec-tools",
-            "private",
-            None,
-        );
-        let audits = push_node(
-            &mut nodes,
-            "audits",
-            NodeKind::Project,
-            "https://gitlab.example.com/security/audits",
-            "security/audits",
-            "private",
-            None,
-        );
-        nodes[sec].children.extend([sec_tools, audits]);
+fn format_visibility_audit(findings: &[VisibilityFinding], format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => Ok(serde_json::to_string_pretty(findings)? + "\n"),
+        ReportFormat::Csv => {
+            let mut out = "path,kind,visibility,parent,parent_visibility,flags\n".to_string();
+            for finding in findings {
+                let fields = [
+                    finding.path.as_str(),
+                    finding.kind,
+                    finding.visibility.as_str(),
+                    finding.parent.as_deref().unwrap_or_default(),
+                    finding.parent_visibility.as_deref().unwrap_or_default(),
+                    &finding.flags.join(";"),
+                ];
+                let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                out.push_str(&line.join(","));
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
 
-        nodes[dev_platform].expanded = true;
-        nodes[data].expanded = true;
-        nodes[sec].expanded = true;
+fn format_visibility_row(finding: &VisibilityFinding) -> String {
+    let mut line = format!("{} {} [{}]", finding.kind, finding.path, finding.visibility);
+    if let (Some(parent), Some(visibility)) = (&finding.parent, &finding.parent_visibility) {
+        line.push_str(&format!(" | in {parent} [{visibility}]"));
+    }
+    if !finding.flags.is_empty() {
+        line.push_str(&format!(" | {}", finding.flags.join(", ")));
+    }
+    line
+}
 
-        let parent = build_parent_map(&nodes);
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct StaleGroup {
+    group: String,
+    projects: Vec<StaleProject>,
+}
 
-        Self {
-            nodes,
-            roots: vec![dev_platform, data, sec],
-            parent,
-            selected: 0,
-            config,
-            status: Some(status),
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct StaleProject {
+    path: String,
+    last_activity_at: String,
+    days_inactive: u64,
+    archived: bool,
+    url: String,
+}
+
+/// Projects whose last activity is at least `days` old, grouped by parent
+/// path and sorted oldest first. Projects without a timestamp are
+/// left out since their age is unknown.
+fn stale_projects(app: &App, days: u64, now: SystemTime) -> Vec<StaleGroup> {
+    let mut groups: Vec<StaleGroup> = Vec::new();
+    for (node_id, scope) in review_targets(app) {
+        if !matches!(scope, ViewScope::Project(_)) {
+            continue;
+        }
+        let node = &app.nodes[node_id];
+        let Some(activity) = node.last_activity.as_deref() else {
+            continue;
+        };
+        let Some(inactive) = days_since(activity, now).filter(|inactive| *inactive >= days) else {
+            continue;
+        };
+        let group = app.parent[node_id]
+            .map(|parent| app.nodes[parent].path.clone())
+            .unwrap_or_default();
+        let project = StaleProject {
+            path: node.path.clone(),
+            last_activity_at: activity.to_string(),
+            days_inactive: inactive,
+            archived: node.archived,
+            url: node.url.clone(),
+        };
+        match groups.iter_mut().find(|entry| entry.group == group) {
+            Some(entry) => entry.projects.push(project),
+            None => groups.push(StaleGroup { group, projects: vec![project] }),
         }
     }
+    groups.sort_by(|a, b| a.group.cmp(&b.group));
+    for group in &mut groups {
+        group.projects.sort_by_key(|project| std::cmp::Reverse(project.days_inactive));
+    }
+    groups
+}
 
-    fn from_gitlab(config: Config) -> Result<Self> {
-        let cache = CacheStore::new(config.cache_path.clone(), config.cache_ttl);
-        if let Some(cache) = cache.load()? {
-            let total_projects: usize =
-                cache.projects_by_group.iter().map(|entry| entry.projects.len()).sum();
-            let personal_count = cache.personal.as_ref().map(|entry| entry.projects.len()).unwrap_or(0);
-            let status = format!(
-                "cache hit | groups: {}, projects: {}, personal: {}",
-                cache.groups.len(),
-                total_projects,
-                personal_count
-            );
-            return Ok(Self::from_gitlab_data(
-                cache.groups,
-                cache.projects_by_group,
-                cache.personal,
-                config,
-                status,
-            ));
+fn format_stale_projects(groups: &[StaleGroup], format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => Ok(serde_json::to_string_pretty(groups)? + "\n"),
+        ReportFormat::Csv => {
+            let mut out = "group,path,last_activity_at,days_inactive,archived,url\n".to_string();
+            for group in groups {
+                for project in &group.projects {
+                    let fields = [
+                        group.group.as_str(),
+                        project.path.as_str(),
+                        project.last_activity_at.as_str(),
+                        &project.days_inactive.to_string(),
+                        if project.archived { "true" } else { "false" },
+                        project.url.as_str(),
+                    ];
+                    let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                    out.push_str(&line.join(","));
+                    out.push('\n');
+                }
+            }
+            Ok(out)
         }
+    }
+}
 
-        let groups = fetch_groups(&config)?;
-        let projects = fetch_projects_by_group(&config, &groups)?;
-        let personal = fetch_personal_projects(&config).ok();
-        let total_projects: usize = projects.iter().map(|entry| entry.projects.len()).sum();
-        let personal_count = personal.as_ref().map(|entry| entry.projects.len()).unwrap_or(0);
-        let status = format!(
-            "groups: {}, projects: {}, personal: {}",
-            groups.len(),
-            total_projects,
-            personal_count
-        );
-        let cache_data = CacheData {
-            created_at: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs(),
-            groups: groups.clone(),
-            projects_by_group: projects.clone(),
-            personal: personal.clone(),
-        };
-        let _ = cache.store(&cache_data);
-        Ok(Self::from_gitlab_data(
-            groups,
-            projects,
-            personal,
-            config,
-            status,
-        ))
+/// Walks every group and project in the loaded tree and lists the
+/// effective members of each, flagging risky memberships. External status is
+/// looked up per user only for admin tokens, the only ones GitLab shows it to.
+fn access_review(
+    app: &App,
+    api: &dyn GitLabApi,
+    options: &AccessReviewOptions,
+    now: SystemTime,
+) -> AccessReview {
+    let targets = review_targets(app);
+    let mut fetched: HashMap<usize, (Vec<GitLabMember>, Vec<GitLabMember>)> = HashMap::new();
+    let mut failures = Vec::new();
+    for chunk in targets.chunks(DETAIL_CONCURRENCY) {
+        thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|(node_id, target)| {
+                    scope.spawn(move || {
+                        let all = api.members(*target, true);
+                        let direct = api.members(*target, false);
+                        (*node_id, *target, all, direct)
+                    })
+                })
+                .collect();
+            for ((node_id, _), handle) in chunk.iter().zip(handles) {
+                let path = &app.nodes[*node_id].path;
+                let message = match handle.join() {
+                    Ok((node_id, _, Ok(all), Ok(direct))) => {
+                        fetched.insert(node_id, (all, direct));
+                        continue;
+                    }
+                    Ok((_, target, Err(err), _) | (_, target, _, Err(err))) => {
+                        format!("members of {path} ({}) failed: {err}", target.api_path())
+                    }
+                    Err(_) => format!("members of {path} failed: worker panicked"),
+                };
+                app.config.log.record(LogLevel::Error, "access-review", message.clone());
+                failures.push(message);
+            }
+        });
     }
 
-    fn from_gitlab_data(
-        groups: Vec<GitLabGroup>,
-        projects_by_group: Vec<GroupProjects>,
-        personal: Option<PersonalProjects>,
-        config: Config,
-        status: String,
-    ) -> Self {
-        let mut nodes = Vec::new();
-        let mut id_to_node = HashMap::new();
-        for group in &groups {
-            let node_id = push_node(
-                &mut nodes,
-                &group.name,
-                NodeKind::Group,
-                &group.web_url,
-                &group.full_path,
-                &group.visibility,
-                None,
-            );
-            id_to_node.insert(group.id, node_id);
+    let group_nodes: HashMap<usize, usize> = targets
+        .iter()
+        .filter_map(|(node_id, target)| match target {
+            ViewScope::Group(group_id) => Some((*group_id, *node_id)),
+            ViewScope::Project(_) => None,
+        })
+        .collect();
+    let mut rows = Vec::new();
+    for (node_id, target) in &targets {
+        let Some((all, direct)) = fetched.get(node_id) else {
+            continue;
+        };
+        let ancestors: Vec<(String, Vec<GitLabMember>)> = app
+            .ancestor_groups(*node_id)
+            .into_iter()
+            .filter_map(|(group_id, path)| {
+                let (_, direct) = fetched.get(group_nodes.get(&group_id)?)?;
+                Some((path, direct.clone()))
+            })
+            .collect();
+        for row in resolve_members(all.clone(), direct, &ancestors) {
+            rows.push((*node_id, *target, row));
         }
+    }
 
-        let mut roots = Vec::new();
-        for group in &groups {
-            let child_id = match id_to_node.get(&group.id) {
-                Some(id) => *id,
-                None => continue,
-            };
-            if let Some(parent_id) = group.parent_id {
-                if let Some(parent_node) = id_to_node.get(&parent_id) {
-                    nodes[*parent_node].children.push(child_id);
-                    continue;
+    let mut user_ids: Vec<usize> = rows.iter().map(|(_, _, row)| row.member.id).collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    let admin = api.current_user().is_ok_and(|user| user.is_admin);
+    let mut external: HashMap<usize, bool> = HashMap::new();
+    for chunk in user_ids.chunks(DETAIL_CONCURRENCY).filter(|_| admin) {
+        thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|user_id| scope.spawn(move || (*user_id, api.user(*user_id))))
+                .collect();
+            for handle in handles {
+                if let Ok((user_id, Ok(user))) = handle.join()
+                    && let Some(is_external) = user.external
+                {
+                    external.insert(user_id, is_external);
                 }
             }
-            roots.push(child_id);
-        }
+        });
+    }
 
-        for entry in projects_by_group {
-            let Some(parent_node) = id_to_node.get(&entry.group_id).copied() else {
-                continue;
-            };
-            for project in entry.projects {
-                let project_node = push_node(
-                    &mut nodes,
-                    &project.name,
-                    NodeKind::Project,
-                    &project.web_url,
-                    &project.path_with_namespace,
-                    &project.visibility,
-                    project.last_activity_at.clone(),
-                );
-                nodes[parent_node].children.push(project_node);
-            }
+    // Inherited rows repeat one grant down the whole subtree, so only access
+    // granted on the node itself counts towards the privileged flag.
+    let granted = |row: &MemberRow| {
+        row.member.access_level >= 40 && !matches!(row.source, MemberSource::Inherited(_))
+    };
+    let mut privileged: HashMap<usize, usize> = HashMap::new();
+    for (_, _, row) in &rows {
+        if granted(row) {
+            *privileged.entry(row.member.id).or_default() += 1;
         }
-
-        if let Some(personal) = personal {
-            let root = push_node(
-                &mut nodes,
-                &personal.username,
-                NodeKind::Group,
-                &personal.web_url,
-                &personal.username,
-                "private",
-                None,
-            );
-            for project in personal.projects {
-                let project_node = push_node(
-                    &mut nodes,
-                    &project.name,
-                    NodeKind::Project,
-                    &project.web_url,
-                    &project.path_with_namespace,
-                    &project.visibility,
-                    project.last_activity_at.clone(),
-                );
-                nodes[root].children.push(project_node);
+    }
+    let records = rows
+        .into_iter()
+        .map(|(node_id, target, row)| {
+            let is_granted = granted(&row);
+            let member = row.member;
+            let is_external = external.get(&member.id).copied();
+            let mut flags = Vec::new();
+            let privileged_count = privileged.get(&member.id).copied().unwrap_or(0);
+            if is_granted && privileged_count >= options.privileged_threshold {
+                flags.push(format!("privileged-on-{privileged_count}-nodes"));
             }
-            roots.push(root);
-        }
+            match member_expiry_days(&member, now) {
+                Some(days) if days < 0 => flags.push("expired".to_string()),
+                Some(days) if days <= MEMBERSHIP_EXPIRY_WARNING_DAYS => {
+                    flags.push("expiring".to_string());
+                }
+                _ => {}
+            }
+            if is_external == Some(true) {
+                flags.push("external".to_string());
+            }
+            AccessRecord {
+                path: app.nodes[node_id].path.clone(),
+                kind: match target {
+                    ViewScope::Group(_) => "group",
+                    ViewScope::Project(_) => "project",
+                },
+                username: member.username,
+                name: member.name,
+                access_level: access_level_name(member.access_level),
+                source: row.source.label(),
+                expires_at: member.expires_at,
+                external: is_external,
+                flags,
+            }
+        })
+        .collect();
+    AccessReview { records, failures }
+}
 
-        apply_sorting(&mut nodes, &mut roots, &config);
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
 
-        for &root in &roots {
-            nodes[root].expanded = true;
+fn format_access_review(records: &[AccessRecord], format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => Ok(serde_json::to_string_pretty(records)? + "\n"),
+        ReportFormat::Csv => {
+            let mut out =
+                "path,kind,username,name,access_level,source,expires_at,external,flags\n"
+                    .to_string();
+            for record in records {
+                let fields = [
+                    record.path.as_str(),
+                    record.kind,
+                    record.username.as_str(),
+                    record.name.as_deref().unwrap_or_default(),
+                    record.access_level,
+                    record.source.as_str(),
+                    record.expires_at.as_deref().unwrap_or_default(),
+                    match record.external {
+                        Some(true) => "true",
+                        Some(false) => "false",
+                        None => "",
+                    },
+                    &record.flags.join(";"),
+                ];
+                let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                out.push_str(&line.join(","));
+                out.push('\n');
+            }
+            Ok(out)
         }
+    }
+}
 
-        let parent = build_parent_map(&nodes);
+/// Branch rows start without divergence; the counts are fetched one branch
+/// at a time as rows get selected.
+fn branch_rows(branches: Vec<GitLabRef>) -> Vec<BranchRow> {
+    branches
+        .into_iter()
+        .map(|branch| {
+            let (divergence, state) = if branch.default {
+                (Some((Some(0), Some(0))), DetailState::Loaded)
+            } else {
+                (None, DetailState::Partial)
+            };
+            BranchRow { branch, divergence, state }
+        })
+        .collect()
+}
 
-        Self {
-            nodes,
-            roots,
-            parent,
-            selected: 0,
-            config,
-            status: Some(status),
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        }
+fn order_jobs_by_stage(mut jobs: Vec<GitLabJob>) -> Vec<GitLabJob> {
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    for job in &jobs {
+        let entry = first_seen.entry(job.stage.clone()).or_insert(job.id);
+        *entry = (*entry).min(job.id);
     }
+    jobs.sort_by_key(|job| (first_seen[&job.stage], job.id));
+    jobs
+}
 
-    fn visible_nodes(&self) -> Vec<VisibleNode> {
-        let mut out = Vec::new();
-        for &root in &self.roots {
-            self.walk_visible(root, 0, &mut out);
+fn run_fetch(api: &dyn GitLabApi, request: &FetchRequest) -> Result<FetchPayload> {
+    match *request {
+        FetchRequest::GroupProjects { group_id, .. } => {
+            Ok(FetchPayload::Projects(api.group_projects(group_id)?))
         }
-        if let Some(query) = &self.search_query {
-            filter_visible_nodes(&out, &self.nodes, query)
-        } else {
-            out
+        FetchRequest::ProjectDetails { project_id } => {
+            let project = api.project(project_id)?;
+            let creator = project
+                .creator_id
+                .and_then(|user_id| api.user(user_id).ok())
+                .map(|user| user.username);
+            Ok(FetchPayload::ProjectDetails(Box::new(project), creator))
         }
-    }
-
-    fn walk_visible(&self, node_id: usize, depth: usize, out: &mut Vec<VisibleNode>) {
-        out.push(VisibleNode { id: node_id, depth });
-        let node = &self.nodes[node_id];
-        if node.expanded {
-            for &child in &node.children {
-                self.walk_visible(child, depth + 1, out);
+        FetchRequest::GroupDetails { group_id, ref username } => {
+            let group = api.group(group_id)?;
+            // Restricted member lists (403) and totals above GitLab's x-total
+            // limit only cost the member count, not the rest of the details.
+            let member_count = api.group_member_count(group_id).ok();
+            let access_level = username
+                .as_deref()
+                .and_then(|username| api.group_member(group_id, username).ok().flatten())
+                .map(|member| member.access_level);
+            Ok(FetchPayload::GroupDetails(Box::new(group), member_count, access_level))
+        }
+        FetchRequest::Readme { project_id, ref path, ref ref_name } => {
+            Ok(FetchPayload::File(api.file_raw(project_id, path, ref_name)?))
+        }
+        FetchRequest::RepoTree { project_id, ref path, ref ref_name, .. } => {
+            Ok(FetchPayload::RepoTree(api.repository_tree(project_id, path, ref_name)?))
+        }
+        FetchRequest::RepoFile { project_id, ref path, ref ref_name } => {
+            let content = api.file_raw(project_id, path, ref_name)?;
+            let commit = api.commit(project_id, ref_name).ok().map(|commit| commit.id);
+            Ok(FetchPayload::RepoFile(content, commit))
+        }
+        FetchRequest::MergeRequests { scope, filter, ref username } => {
+            let mut query = vec![("state", "opened".to_string())];
+            match filter {
+                MergeRequestFilter::All => query.push(("scope", "all".to_string())),
+                MergeRequestFilter::AssignedToMe => {
+                    query.push(("scope", "assigned_to_me".to_string()))
+                }
+                MergeRequestFilter::ReviewRequested => {
+                    query.push(("scope", "all".to_string()));
+                    // The token's owner is known once its info has loaded; only
+                    // ask the API when that lookup has not completed.
+                    let reviewer = match username {
+                        Some(username) => username.clone(),
+                        None => api.current_user()?.username,
+                    };
+                    query.push(("reviewer_username", reviewer));
+                }
             }
+            let rows = api
+                .merge_requests(scope, query)?
+                .into_iter()
+                .map(|merge_request| MergeRequestRow {
+                    merge_request,
+                    pipeline: None,
+                    approvals: None,
+                    state: DetailState::Partial,
+                })
+                .collect();
+            Ok(FetchPayload::MergeRequests(rows))
         }
-    }
-
-    fn ensure_selection(&mut self, visible_len: usize) {
-        if visible_len == 0 {
-            self.selected = 0;
-        } else if self.selected >= visible_len {
-            self.selected = visible_len - 1;
+        FetchRequest::MergeRequestDetail { project_id, iid } => {
+            let pipeline = api
+                .merge_request(project_id, iid)?
+                .head_pipeline
+                .map(|pipeline| pipeline.status);
+            let approvals = api.merge_request_approvals(project_id, iid)?;
+            let approvals = (approvals.approved_by.len(), approvals.approvals_required);
+            Ok(FetchPayload::MergeRequestDetail(pipeline, approvals))
         }
-    }
-
-    fn move_up(&mut self) {
-        if self.selected > 0 {
-            self.selected -= 1;
+        FetchRequest::Issues { scope, ref query } => {
+            let query = parse_issue_query(query)?;
+            Ok(FetchPayload::Issues(api.issues(scope, query)?))
         }
-    }
-
-    fn move_down(&mut self, visible_len: usize) {
-        if self.selected + 1 < visible_len {
-            self.selected += 1;
+        FetchRequest::IssueNotes { project_id, iid } => {
+            Ok(FetchPayload::IssueNotes(api.issue_notes(project_id, iid)?))
         }
-    }
-
-    fn move_top(&mut self) {
-        self.selected = 0;
-    }
-
-    fn move_bottom(&mut self, visible_len: usize) {
-        if visible_len > 0 {
-            self.selected = visible_len - 1;
+        FetchRequest::PipelineBadges { ref projects } => {
+            Ok(FetchPayload::PipelineBadges(latest_pipeline_statuses(api, projects)))
         }
-    }
-
-    fn collapse_or_parent(&mut self, visible: &[VisibleNode]) {
-        if visible.is_empty() {
-            return;
+        FetchRequest::Pipelines { project_id } => {
+            let query = vec![("per_page", PIPELINE_PAGE_SIZE.to_string())];
+            let pipelines = api.pipelines(project_id, query)?;
+            Ok(FetchPayload::Pipelines(pipelines))
         }
-        let node_id = visible[self.selected].id;
-        if self.nodes[node_id].expanded {
-            self.nodes[node_id].expanded = false;
-        } else if let Some(parent) = self.parent[node_id] {
-            self.select_node(parent, visible);
+        FetchRequest::PipelineDetail { project_id, pipeline_id } => {
+            Ok(FetchPayload::Pipeline(Box::new(api.pipeline(project_id, pipeline_id)?)))
         }
-        self.ensure_selection(self.visible_nodes().len());
-    }
-
-    fn expand_or_child(&mut self, visible: &[VisibleNode]) {
-        if visible.is_empty() {
-            return;
+        FetchRequest::PipelineJobs { project_id, pipeline_id } => {
+            let jobs = api.pipeline_jobs(project_id, pipeline_id)?;
+            Ok(FetchPayload::PipelineJobs(order_jobs_by_stage(jobs)))
         }
-        let node_id = visible[self.selected].id;
-        if self.nodes[node_id].children.is_empty() {
-            return;
+        FetchRequest::JobTrace { project_id, job_id } => {
+            let job = api.job(project_id, job_id)?;
+            let trace = api.job_trace(project_id, job_id)?;
+            Ok(FetchPayload::JobTrace(Box::new(job), trace))
         }
-        if !self.nodes[node_id].expanded {
-            self.nodes[node_id].expanded = true;
-        } else {
-            let child = self.nodes[node_id].children[0];
-            self.select_node(child, visible);
+        FetchRequest::JobAction { project_id, job_id, action } => {
+            Ok(FetchPayload::Job(Box::new(api.job_action(project_id, job_id, action)?)))
         }
-        self.ensure_selection(self.visible_nodes().len());
-    }
-
-    fn select_node(&mut self, node_id: usize, visible: &[VisibleNode]) {
-        if let Some(pos) = visible.iter().position(|item| item.id == node_id) {
-            self.selected = pos;
+        FetchRequest::RefList { project_id, tab } => match tab {
+            RefTab::Branches => {
+                Ok(FetchPayload::Branches(branch_rows(api.branches(project_id)?)))
+            }
+            RefTab::Tags => Ok(FetchPayload::Tags(api.tags(project_id)?)),
+            RefTab::Releases => Ok(FetchPayload::Releases(api.releases(project_id)?)),
+        },
+        FetchRequest::BranchDivergence { project_id, ref branch, ref default } => {
+            let ahead = api.commit_count(project_id, &format!("{default}..{branch}"))?;
+            let behind = api.commit_count(project_id, &format!("{branch}..{default}"))?;
+            Ok(FetchPayload::Divergence(ahead, behind))
         }
-    }
-
-    fn yank_selected<C: ClipboardSink + ?Sized>(
-        &mut self,
-        visible: &[VisibleNode],
-        clipboard: &mut C,
-    ) -> Result<String> {
-        if visible.is_empty() {
-            anyhow::bail!("no selection");
+        FetchRequest::Members { scope, ref ancestors } => {
+            let all = api.members(scope, true)?;
+            let direct = api.members(scope, false)?;
+            let ancestors = ancestors
+                .iter()
+                .map(|(group_id, path)| {
+                    let members = api.members(ViewScope::Group(*group_id), false)?;
+                    Ok((path.clone(), members))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(FetchPayload::Members(resolve_members(all, &direct, &ancestors)))
         }
-        let node_id = visible[self.selected].id;
-        let url = self.nodes[node_id].url.clone();
-        clipboard.set_text(url.clone())?;
-        Ok(url)
-    }
-
-    fn open_selected<B: BrowserOpener + ?Sized>(
-        &mut self,
-        visible: &[VisibleNode],
-        browser: &mut B,
-    ) -> Result<String> {
-        if visible.is_empty() {
-            anyhow::bail!("no selection");
+        FetchRequest::Refs { project_id } => {
+            let branches = api.branches(project_id)?;
+            let tags = api.tags(project_id)?;
+            Ok(FetchPayload::Refs(
+                branches.into_iter().map(|branch| branch.name).collect(),
+                tags.into_iter().map(|tag| tag.name).collect(),
+            ))
         }
-        let node_id = visible[self.selected].id;
-        let url = self.nodes[node_id].url.clone();
-        browser.open(&url)?;
-        Ok(url)
     }
+}
 
-    fn set_status(&mut self, message: String) {
-        self.status = Some(message);
-    }
+fn loading_message(tick: usize) -> String {
+    let frames = ["|", "/", "-", "\\"];
+    let frame = frames[tick % frames.len()];
+    format!("{frame} loading GitLab data...")
+}
 
-    fn set_toast(&mut self, message: String) {
-        self.toast = Some(Toast {
-            message,
-            remaining: Self::TOAST_TTL,
-        });
+#[derive(Clone)]
+struct Config {
+    gitlab_url: String,
+    gitlab_token: String,
+    filters: ApiFilters,
+    http: HttpOptions,
+    cache_path: PathBuf,
+    cache_ttl: Duration,
+    group_sort: SortOrder,
+    project_sort: SortOrder,
+    token_warn_days: u64,
+    loader: LoaderKind,
+    log_file: Option<PathBuf>,
+    log: EventLog,
+    show_archived: bool,
+    show_shared: bool,
+    show_pipelines: bool,
+    show_group_stats: bool,
+    stale_days: u64,
+    time: TimeDisplay,
+}
+
+impl Config {
+    fn from_env() -> Result<Self> {
+        let config = Self::from_env_reader(|key| env::var(key).ok())?;
+        if let Some(path) = &config.log_file {
+            config.log.open_file(path)?;
+        }
+        Ok(config)
     }
 
-    fn tick_toast(&mut self) {
-        if let Some(toast) = self.toast.as_mut() {
-            if toast.remaining > 0 {
-                toast.remaining -= 1;
-            }
-            if toast.remaining == 0 {
-                self.toast = None;
+    fn from_env_reader<F>(reader: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let gitlab_url =
+            read_env_optional(&reader, "GITLAB_URL").unwrap_or_else(|| "https://gitlab.com".to_string());
+        let gitlab_token = read_env_required(&reader, "GITLAB_TOKEN")?;
+        let filters = ApiFilters::from_env_reader(&reader)?;
+        let http = HttpOptions::from_env_reader(&reader)?;
+        let cache_ttl_seconds =
+            read_env_u64_optional(&reader, "GITLAB_CACHE_TTL_SECONDS")?.unwrap_or(300);
+        let cache_path = read_env_optional(&reader, "GITLAB_CACHE_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(default_cache_path);
+        let group_sort =
+            SortOrder::from_env(&reader, "GITLAB_GROUP_SORT")?.unwrap_or(SortOrder::Alpha);
+        let project_sort =
+            SortOrder::from_env(&reader, "GITLAB_PROJECT_SORT")?.unwrap_or(SortOrder::Alpha);
+        let token_warn_days =
+            read_env_u64_optional(&reader, "GITLAB_TOKEN_WARN_DAYS")?.unwrap_or(14);
+        let loader = LoaderKind::from_env(&reader, "GITLAB_LOADER")?.unwrap_or(LoaderKind::Rest);
+        if loader == LoaderKind::GraphQl {
+            // The GraphQL queries have no equivalent of these REST list filters.
+            let unsupported = [
+                ("GITLAB_ALL_AVAILABLE", filters.all_available),
+                ("GITLAB_OWNED", filters.owned),
+                ("GITLAB_INCLUDE_SUBGROUPS", filters.include_subgroups),
+            ];
+            if let Some((key, _)) = unsupported.iter().find(|(_, value)| value.is_some()) {
+                anyhow::bail!("{key} is not supported with GITLAB_LOADER=graphql");
             }
         }
-    }
+        let log_level = LogLevel::from_env(&reader, "GITLAB_LOG_LEVEL")?.unwrap_or(LogLevel::Info);
+        let log_file = read_env_optional(&reader, "GITLAB_LOG_FILE").map(PathBuf::from);
+        let show_archived =
+            read_env_bool_optional(&reader, "GITLAB_SHOW_ARCHIVED")?.unwrap_or(true);
+        let show_shared = read_env_bool_optional(&reader, "GITLAB_SHOW_SHARED")?.unwrap_or(false);
+        let show_pipelines =
+            read_env_bool_optional(&reader, "GITLAB_PIPELINE_BADGES")?.unwrap_or(true);
+        let show_group_stats =
+            read_env_bool_optional(&reader, "GITLAB_GROUP_STATS")?.unwrap_or(false);
+        let stale_days = read_env_u64_optional(&reader, "GITLAB_STALE_DAYS")?.unwrap_or(180);
+        let time = TimeDisplay::from_env(&reader)?;
 
-    fn set_pending_g(&mut self) {
-        self.pending_g = true;
+        Ok(Self {
+            gitlab_url,
+            gitlab_token,
+            filters,
+            http,
+            cache_path,
+            cache_ttl: Duration::from_secs(cache_ttl_seconds),
+            group_sort,
+            project_sort,
+            token_warn_days,
+            loader,
+            log_file,
+            log: EventLog::new(log_level),
+            show_archived,
+            show_shared,
+            show_pipelines,
+            show_group_stats,
+            stale_days,
+            time,
+        })
     }
+}
 
-    fn clear_pending_g(&mut self) {
-        self.pending_g = false;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortOrder {
+    Alpha,
+    Activity,
+    Stars,
+    Size,
+    Created,
+    Path,
+}
+
+impl SortOrder {
+    fn next(self) -> Self {
+        match self {
+            SortOrder::Alpha => SortOrder::Activity,
+            SortOrder::Activity => SortOrder::Stars,
+            SortOrder::Stars => SortOrder::Size,
+            SortOrder::Size => SortOrder::Created,
+            SortOrder::Created => SortOrder::Path,
+            SortOrder::Path => SortOrder::Alpha,
+        }
     }
 
-    fn consume_pending_g(&mut self) -> bool {
-        if self.pending_g {
-            self.pending_g = false;
-            true
-        } else {
-            false
+    fn label(self) -> &'static str {
+        match self {
+            SortOrder::Alpha => "name",
+            SortOrder::Activity => "last activity",
+            SortOrder::Stars => "stars",
+            SortOrder::Size => "repository size",
+            SortOrder::Created => "creation date",
+            SortOrder::Path => "path",
         }
     }
 
-    fn start_search(&mut self) {
-        self.search_mode = true;
-        self.search_query = Some(String::new());
+    fn from_env<F>(reader: &F, key: &str) -> Result<Option<Self>>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let Some(value) = read_env_optional(reader, key) else {
+            return Ok(None);
+        };
+        match value.to_lowercase().as_str() {
+            "alpha" | "alphabetical" => Ok(Some(SortOrder::Alpha)),
+            "activity" | "last_activity" | "recent" => Ok(Some(SortOrder::Activity)),
+            "stars" | "star_count" => Ok(Some(SortOrder::Stars)),
+            "size" | "repository_size" => Ok(Some(SortOrder::Size)),
+            "created" | "created_at" => Ok(Some(SortOrder::Created)),
+            "path" => Ok(Some(SortOrder::Path)),
+            _ => anyhow::bail!("invalid sort order for {key}: {value}"),
+        }
     }
+}
 
-    fn exit_search_mode(&mut self) {
-        self.search_mode = false;
-        if let Some(query) = &self.search_query {
-            if query.is_empty() {
-                self.search_query = None;
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DisplayTimezone {
+    Local,
+    Named(chrono_tz::Tz),
+}
+
+/// How absolute timestamps are shown: a timezone plus a strftime format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TimeDisplay {
+    timezone: DisplayTimezone,
+    format: String,
+}
+
+impl TimeDisplay {
+    fn from_env<F>(reader: &F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let timezone = match read_env_optional(reader, "GITLAB_TIMEZONE") {
+            None => DisplayTimezone::Local,
+            Some(value) if value.eq_ignore_ascii_case("local") => DisplayTimezone::Local,
+            Some(value) if value.eq_ignore_ascii_case("utc") => {
+                DisplayTimezone::Named(chrono_tz::UTC)
             }
+            Some(value) => match value.parse::<chrono_tz::Tz>() {
+                Ok(tz) => DisplayTimezone::Named(tz),
+                Err(_) => anyhow::bail!("invalid timezone for GITLAB_TIMEZONE: {value}"),
+            },
+        };
+        let format = read_env_optional(reader, "GITLAB_TIME_FORMAT")
+            .unwrap_or_else(|| DEFAULT_TIME_FORMAT.to_string());
+        let invalid = chrono::format::StrftimeItems::new(&format)
+            .any(|item| item == chrono::format::Item::Error);
+        if invalid {
+            anyhow::bail!("invalid format for GITLAB_TIME_FORMAT: {format}");
         }
+        Ok(Self { timezone, format })
     }
 
-    fn clear_search(&mut self) {
-        self.search_query = None;
-        self.search_mode = false;
+    fn absolute(&self, instant: DateTime<Utc>) -> String {
+        let format = self.format.as_str();
+        match self.timezone {
+            DisplayTimezone::Local => instant.with_timezone(&Local).format(format).to_string(),
+            DisplayTimezone::Named(tz) => instant.with_timezone(&tz).format(format).to_string(),
+        }
     }
 
-    fn push_search_char(&mut self, ch: char) {
-        if let Some(query) = &mut self.search_query {
-            query.push(ch);
-        } else {
-            self.search_query = Some(ch.to_string());
+    /// "3 days ago (2024-03-01 13:00 CET)"; unparseable values are shown as-is.
+    fn describe(&self, raw: &str, now: SystemTime) -> String {
+        match parse_timestamp(raw) {
+            Some(instant) => format!(
+                "{} ({})",
+                format_relative(instant, DateTime::<Utc>::from(now)),
+                self.absolute(instant)
+            ),
+            None => raw.to_string(),
         }
     }
+}
 
-    fn pop_search_char(&mut self) {
-        if let Some(query) = &mut self.search_query {
-            query.pop();
-            if query.is_empty() && !self.search_mode {
-                self.search_query = None;
-            }
-        }
+/// Parses GitLab's RFC 3339 timestamps (any offset) and plain `YYYY-MM-DD`
+/// dates, which are taken as midnight UTC.
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(raw.trim()) {
+        return Some(instant.with_timezone(&Utc));
     }
+    let date = NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
 
-    fn toggle_selected(&mut self, visible: &[VisibleNode]) {
-        if visible.is_empty() {
-            return;
+/// List-row age of a raw GitLab timestamp; "-" when it cannot be parsed.
+fn relative_age(raw: &str, now: SystemTime) -> String {
+    parse_timestamp(raw).map_or_else(
+        || "-".to_string(),
+        |instant| format_relative(instant, DateTime::<Utc>::from(now)),
+    )
+}
+
+fn format_relative(instant: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - instant).num_seconds();
+    let (value, unit) = match seconds.unsigned_abs() {
+        0..=59 => return "just now".to_string(),
+        secs @ 60..=3_599 => (secs / 60, "minute"),
+        secs @ 3_600..=86_399 => (secs / 3_600, "hour"),
+        secs @ 86_400..=2_591_999 => (secs / 86_400, "day"),
+        secs @ 2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        secs => (secs / 31_536_000, "year"),
+    };
+    let plural = if value == 1 { "" } else { "s" };
+    if seconds >= 0 {
+        format!("{value} {unit}{plural} ago")
+    } else {
+        format!("in {value} {unit}{plural}")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn from_env<F>(reader: &F, key: &str) -> Result<Option<Self>>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let Some(value) = read_env_optional(reader, key) else {
+            return Ok(None);
+        };
+        match value.to_lowercase().as_str() {
+            "debug" => Ok(Some(LogLevel::Debug)),
+            "info" => Ok(Some(LogLevel::Info)),
+            "warn" | "warning" => Ok(Some(LogLevel::Warn)),
+            "error" => Ok(Some(LogLevel::Error)),
+            _ => anyhow::bail!("invalid log level for {key}: {value}"),
         }
-        let node_id = visible[self.selected].id;
-        if self.nodes[node_id].children.is_empty() {
-            return;
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
         }
-        self.nodes[node_id].expanded = !self.nodes[node_id].expanded;
     }
+}
 
-    fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+#[derive(Clone, Debug)]
+struct LogEntry {
+    timestamp: u64,
+    level: LogLevel,
+    category: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct LogRecord<'a> {
+    ts: String,
+    level: &'static str,
+    category: &'static str,
+    message: &'a str,
+}
+
+struct EventLogState {
+    level: LogLevel,
+    entries: std::collections::VecDeque<LogEntry>,
+    file: Option<std::fs::File>,
+}
+
+#[derive(Clone)]
+struct EventLog {
+    state: Arc<Mutex<EventLogState>>,
+}
+
+impl EventLog {
+    const CAPACITY: usize = 500;
+
+    fn new(level: LogLevel) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(EventLogState {
+                level,
+                entries: std::collections::VecDeque::new(),
+                file: None,
+            })),
+        }
     }
 
-    fn page_up(&mut self, visible_len: usize, page_size: usize) {
-        if visible_len == 0 {
-            self.selected = 0;
-            return;
+    fn open_file(&self, path: &std::path::Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
-        let step = page_size.min(visible_len.saturating_sub(1)).max(1);
-        self.selected = self.selected.saturating_sub(step);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| anyhow::anyhow!("failed to open log file {}: {err}", path.display()))?;
+        if let Ok(mut state) = self.state.lock() {
+            state.file = Some(file);
+        }
+        Ok(())
     }
 
-    fn page_down(&mut self, visible_len: usize, page_size: usize) {
-        if visible_len == 0 {
-            self.selected = 0;
+    fn record(&self, level: LogLevel, category: &'static str, message: String) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if level < state.level {
             return;
         }
-        let step = page_size.min(visible_len.saturating_sub(1)).max(1);
-        self.selected = (self.selected + step).min(visible_len - 1);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if let Some(file) = state.file.as_mut() {
+            let record = LogRecord {
+                ts: format_utc_timestamp(timestamp),
+                level: level.label(),
+                category,
+                message: &message,
+            };
+            if let Ok(line) = serde_json::to_string(&record) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+        if state.entries.len() == Self::CAPACITY {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(LogEntry {
+            timestamp,
+            level,
+            category,
+            message,
+        });
     }
 
-    fn handle_key(
-        &mut self,
-        key: KeyEvent,
-        visible: &[VisibleNode],
-        clipboard: Option<&mut dyn ClipboardSink>,
-        browser: &mut dyn BrowserOpener,
-    ) -> Result<KeyAction> {
-        if self.search_mode {
-            match key.code {
-                KeyCode::Esc => self.clear_search(),
-                KeyCode::Enter => self.exit_search_mode(),
-                KeyCode::Backspace => self.pop_search_char(),
-                KeyCode::Char(ch) => self.push_search_char(ch),
-                _ => {}
-            }
-            return Ok(KeyAction::None);
+    fn entries(&self) -> Vec<LogEntry> {
+        self.state
+            .lock()
+            .map(|state| state.entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+fn format_utc_timestamp(secs: u64) -> String {
+    DateTime::<Utc>::from(UNIX_EPOCH + Duration::from_secs(secs))
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LoaderKind {
+    Rest,
+    GraphQl,
+}
+
+impl LoaderKind {
+    fn from_env<F>(reader: &F, key: &str) -> Result<Option<Self>>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let Some(value) = read_env_optional(reader, key) else {
+            return Ok(None);
+        };
+        match value.to_lowercase().as_str() {
+            "rest" => Ok(Some(LoaderKind::Rest)),
+            "graphql" => Ok(Some(LoaderKind::GraphQl)),
+            _ => anyhow::bail!("invalid loader for {key}: {value}"),
         }
+    }
+}
 
-        let action = match (key.code, key.modifiers) {
-            (KeyCode::Char('c'), modifiers) if modifiers.contains(KeyModifiers::CONTROL) => {
-                KeyAction::Quit
-            }
-            (KeyCode::Char('q'), _) => KeyAction::Quit,
-            (KeyCode::Char('r'), _) => KeyAction::Reload,
-            (KeyCode::Char('?'), _) => {
-                self.toggle_help();
-                KeyAction::None
-            }
-            (KeyCode::Enter, _) => {
-                self.toggle_selected(visible);
-                KeyAction::None
-            }
-            (KeyCode::PageUp, _) => {
-                self.page_up(visible.len(), 10);
-                KeyAction::None
-            }
-            (KeyCode::PageDown, _) => {
-                self.page_down(visible.len(), 10);
-                KeyAction::None
-            }
-            (KeyCode::Up, _) => {
-                self.move_up();
-                KeyAction::None
-            }
-            (KeyCode::Down, _) => {
-                self.move_down(visible.len());
-                KeyAction::None
-            }
-            (KeyCode::Left, _) => {
-                self.collapse_or_parent(visible);
-                KeyAction::None
-            }
-            (KeyCode::Right, _) => {
-                self.expand_or_child(visible);
-                KeyAction::None
-            }
-            (KeyCode::Char('k'), _) => {
-                self.move_up();
-                KeyAction::None
-            }
-            (KeyCode::Char('j'), _) => {
-                self.move_down(visible.len());
-                KeyAction::None
-            }
-            (KeyCode::Char('h'), _) => {
-                self.collapse_or_parent(visible);
-                KeyAction::None
-            }
-            (KeyCode::Char('l'), _) => {
-                self.expand_or_child(visible);
-                KeyAction::None
-            }
-            (KeyCode::Char('g'), _) => {
-                if self.consume_pending_g() {
-                    self.move_top();
-                } else {
-                    self.set_pending_g();
-                }
-                KeyAction::None
-            }
-            (KeyCode::Char('G'), _) => {
-                self.move_bottom(visible.len());
-                KeyAction::None
-            }
-            (KeyCode::Char('y'), _) => {
-                if let Some(clipboard) = clipboard {
-                    match self.yank_selected(visible, clipboard) {
-                        Ok(url) => {
-                            self.set_status(format!("copied {url}"));
-                            self.set_toast("Copied URL".to_string());
-                        }
-                        Err(err) => self.set_status(format!("copy failed: {err}")),
-                    }
-                } else {
-                    self.set_status("clipboard unavailable".to_string());
-                }
-                KeyAction::None
-            }
-            (KeyCode::Char('o'), _) => {
-                match self.open_selected(visible, browser) {
-                    Ok(url) => self.set_status(format!("opened {url}")),
-                    Err(err) => self.set_status(format!("open failed: {err}")),
-                }
-                KeyAction::None
-            }
-            (KeyCode::Char('/'), _) => {
-                self.start_search();
-                KeyAction::None
-            }
-            (KeyCode::Esc, _) => {
-                self.clear_search();
-                KeyAction::None
-            }
-            _ => KeyAction::None,
-        };
-
-        if key.code != KeyCode::Char('g') {
-            self.clear_pending_g();
-        }
+#[derive(Clone, Debug, Default)]
+struct ApiFilters {
+    all_available: Option<bool>,
+    owned: Option<bool>,
+    top_level_only: Option<bool>,
+    include_subgroups: Option<bool>,
+    visibility: Option<String>,
+    per_page: u16,
+    keyset_pagination: bool,
+    page_concurrency: usize,
+}
 
-        Ok(action)
+impl ApiFilters {
+    fn from_env_reader<F>(reader: &F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let per_page = read_env_u16_optional(reader, "GITLAB_PER_PAGE")?.unwrap_or(100);
+        let page_concurrency =
+            read_env_u16_optional(reader, "GITLAB_PAGE_CONCURRENCY")?.unwrap_or(4);
+        Ok(Self {
+            all_available: read_env_bool_optional(reader, "GITLAB_ALL_AVAILABLE")?,
+            owned: read_env_bool_optional(reader, "GITLAB_OWNED")?,
+            top_level_only: read_env_bool_optional(reader, "GITLAB_TOP_LEVEL_ONLY")?,
+            include_subgroups: read_env_bool_optional(reader, "GITLAB_INCLUDE_SUBGROUPS")?,
+            visibility: read_env_optional(reader, "GITLAB_VISIBILITY"),
+            per_page,
+            keyset_pagination: read_env_bool_optional(reader, "GITLAB_KEYSET_PAGINATION")?
+                .unwrap_or(true),
+            page_concurrency: usize::from(page_concurrency),
+        })
     }
-
 }
 
-#[derive(Clone, Copy)]
-struct VisibleNode {
-    id: usize,
-    depth: usize,
+#[derive(Clone, Debug, Default)]
+struct HttpOptions {
+    ca_bundle: Option<PathBuf>,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
+    http_proxy: Option<String>,
+    https_proxy: Option<String>,
+    no_proxy: Option<String>,
+    timeout: Option<Duration>,
 }
 
-fn push_node(
-    nodes: &mut Vec<Node>,
-    name: &str,
-    kind: NodeKind,
-    url: &str,
-    path: &str,
-    visibility: &str,
-    last_activity: Option<String>,
-) -> usize {
-    let id = nodes.len();
-    nodes.push(Node {
-        name: name.to_string(),
-        kind,
-        children: Vec::new(),
-        expanded: false,
-        url: url.to_string(),
-        path: path.to_string(),
-        visibility: visibility.to_string(),
-        last_activity,
-    });
-    id
+impl HttpOptions {
+    fn from_env_reader<F>(reader: &F) -> Result<Self>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let client_cert = read_env_optional(reader, "GITLAB_CLIENT_CERT").map(PathBuf::from);
+        let client_key = read_env_optional(reader, "GITLAB_CLIENT_KEY").map(PathBuf::from);
+        if client_key.is_some() && client_cert.is_none() {
+            anyhow::bail!("GITLAB_CLIENT_KEY requires GITLAB_CLIENT_CERT");
+        }
+        let http_proxy = read_env_optional(reader, "GITLAB_HTTP_PROXY");
+        let https_proxy = read_env_optional(reader, "GITLAB_HTTPS_PROXY");
+        let no_proxy = read_env_optional(reader, "GITLAB_NO_PROXY");
+        // The list is attached to the configured proxies only; a proxy picked up
+        // from the environment follows the standard NO_PROXY variable instead.
+        if no_proxy.is_some() && http_proxy.is_none() && https_proxy.is_none() {
+            anyhow::bail!("GITLAB_NO_PROXY requires GITLAB_HTTP_PROXY or GITLAB_HTTPS_PROXY");
+        }
+        let timeout_seconds =
+            read_env_u64_optional(reader, "GITLAB_TIMEOUT_SECONDS")?.unwrap_or(30);
+        Ok(Self {
+            ca_bundle: read_env_optional(reader, "GITLAB_CA_BUNDLE").map(PathBuf::from),
+            client_cert,
+            client_key,
+            http_proxy,
+            https_proxy,
+            no_proxy,
+            timeout: (timeout_seconds > 0).then(|| Duration::from_secs(timeout_seconds)),
+        })
+    }
 }
 
-fn build_parent_map(nodes: &[Node]) -> Vec<Option<usize>> {
-    let mut parent = vec![None; nodes.len()];
-    for (idx, node) in nodes.iter().enumerate() {
-        for &child in &node.children {
-            parent[child] = Some(idx);
+fn build_http_client(options: &HttpOptions) -> Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(path) = &options.ca_bundle {
+        let pem = std::fs::read(path)
+            .map_err(|err| anyhow::anyhow!("failed to read CA bundle {}: {err}", path.display()))?;
+        for certificate in reqwest::Certificate::from_pem_bundle(&pem)? {
+            builder = builder.add_root_certificate(certificate);
         }
     }
-    parent
+    if let Some(cert_path) = &options.client_cert {
+        let mut pem = std::fs::read(cert_path).map_err(|err| {
+            anyhow::anyhow!("failed to read client cert {}: {err}", cert_path.display())
+        })?;
+        if let Some(key_path) = &options.client_key {
+            let key = std::fs::read(key_path).map_err(|err| {
+                anyhow::anyhow!("failed to read client key {}: {err}", key_path.display())
+            })?;
+            pem.push(b'\n');
+            pem.extend(key);
+        }
+        builder = builder.identity(reqwest::Identity::from_pem(&pem)?);
+    }
+    let no_proxy = options.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+    if let Some(proxy) = &options.http_proxy {
+        builder = builder.proxy(reqwest::Proxy::http(proxy)?.no_proxy(no_proxy.clone()));
+    }
+    if let Some(proxy) = &options.https_proxy {
+        builder = builder.proxy(reqwest::Proxy::https(proxy)?.no_proxy(no_proxy.clone()));
+    }
+    Ok(builder.build()?)
 }
 
-fn apply_sorting(nodes: &mut [Node], roots: &mut Vec<usize>, config: &Config) {
-    let names: Vec<String> = nodes.iter().map(|node| node.name.to_lowercase()).collect();
-    let activities: Vec<Option<String>> = nodes.iter().map(|node| node.last_activity.clone()).collect();
+trait ClipboardSink {
+    fn set_text(&mut self, text: String) -> Result<()>;
+}
 
-    roots.sort_by(|a, b| compare_nodes(*a, *b, &names, &activities, config.group_sort));
-    for node in nodes.iter_mut() {
-        node.children
-            .sort_by(|a, b| compare_nodes(*a, *b, &names, &activities, config.project_sort));
-    }
+#[derive(Debug, PartialEq, Eq)]
+enum ClipboardBackend {
+    Arboard,
+    WlCopy,
+    Xclip,
+    Osc52,
+    None,
 }
 
-fn compare_nodes(
-    a: usize,
-    b: usize,
-    names: &[String],
-    activities: &[Option<String>],
-    order: SortOrder,
-) -> std::cmp::Ordering {
-    match order {
-        SortOrder::Alpha => names[a].cmp(&names[b]),
-        SortOrder::Activity => activity_key(activities[a].as_deref())
-            .cmp(&activity_key(activities[b].as_deref()))
-            .then_with(|| names[a].cmp(&names[b])),
-    }
+trait ClipboardProbe {
+    fn arboard_ok(&self) -> bool;
+    fn has_wayland(&self) -> bool;
+    fn has_display(&self) -> bool;
+    fn has_tmux(&self) -> bool;
+    fn command_exists(&self, command: &str) -> bool;
 }
 
-fn activity_key(timestamp: Option<&str>) -> std::cmp::Reverse<(bool, &str)> {
-    std::cmp::Reverse((timestamp.is_none(), timestamp.unwrap_or("")))
+trait BrowserOpener {
+    fn open(&mut self, url: &str) -> Result<()>;
 }
 
-fn filter_visible_nodes(
-    visible: &[VisibleNode],
-    nodes: &[Node],
-    query: &str,
-) -> Vec<VisibleNode> {
-    let needle = query.trim().to_lowercase();
-    if needle.is_empty() {
-        return visible.to_vec();
+struct CommandClipboard {
+    command: String,
+    args: Vec<String>,
+}
+
+impl CommandClipboard {
+    fn new(command: &str, args: &[&str]) -> Self {
+        Self {
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
     }
-    visible
-        .iter()
-        .copied()
-        .filter(|node| fuzzy_match(&needle, &nodes[node.id].name.to_lowercase()))
-        .collect()
 }
 
-fn fuzzy_match(needle: &str, haystack: &str) -> bool {
-    let mut needle_chars = needle.chars();
-    let mut current = needle_chars.next();
-    for ch in haystack.chars() {
-        match current {
-            Some(target) if ch == target => {
-                current = needle_chars.next();
-                if current.is_none() {
-                    return true;
-                }
-            }
-            None => return true,
-            _ => {}
+impl ClipboardSink for CommandClipboard {
+    fn set_text(&mut self, text: String) -> Result<()> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow::anyhow!("clipboard command failed: {err}"))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("clipboard command exited with {status}");
         }
+        Ok(())
     }
-    current.is_none()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
-    use std::time::{Duration, UNIX_EPOCH};
+struct SystemClipboard {
+    inner: SystemClipboardHandle,
+}
 
-    fn test_config() -> Config {
-        Config {
-            gitlab_url: "https://gitlab.com".to_string(),
-            gitlab_token: "token".to_string(),
-            filters: ApiFilters::default(),
-            cache_path: default_cache_path(),
-            cache_ttl: Duration::from_secs(300),
-            group_sort: SortOrder::Alpha,
-            project_sort: SortOrder::Alpha,
-        }
+impl SystemClipboard {
+    fn new() -> Result<Self> {
+        let inner = SystemClipboardHandle::new()
+            .map_err(|err| anyhow::anyhow!("clipboard init failed: {err}"))?;
+        Ok(Self { inner })
     }
+}
 
-    fn key_event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
-        KeyEvent {
-            code,
-            modifiers,
-            kind: KeyEventKind::Press,
-            state: KeyEventState::NONE,
-        }
+impl ClipboardSink for SystemClipboard {
+    fn set_text(&mut self, text: String) -> Result<()> {
+        self.inner
+            .set_text(text)
+            .map_err(|err| anyhow::anyhow!("clipboard write failed: {err}"))?;
+        Ok(())
     }
+}
 
-    #[test]
-    fn visible_nodes_respects_expansion() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
+struct Osc52Clipboard {
+    tmux: bool,
+}
+
+impl ClipboardSink for Osc52Clipboard {
+    fn set_text(&mut self, text: String) -> Result<()> {
+        let sequence = osc52_sequence(&text, self.tmux);
+        let mut stdout = io::stdout();
+        stdout.write_all(sequence.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+struct SystemClipboardProbe;
+
+impl ClipboardProbe for SystemClipboardProbe {
+    fn arboard_ok(&self) -> bool {
+        SystemClipboardHandle::new().is_ok()
+    }
+
+    fn has_wayland(&self) -> bool {
+        env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    fn has_display(&self) -> bool {
+        env::var_os("DISPLAY").is_some()
+    }
+
+    fn has_tmux(&self) -> bool {
+        env::var_os("TMUX").is_some()
+    }
+
+    fn command_exists(&self, command: &str) -> bool {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("command -v {command} >/dev/null 2>&1"))
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+fn select_clipboard_backend(probe: &dyn ClipboardProbe) -> ClipboardBackend {
+    if probe.arboard_ok() {
+        ClipboardBackend::Arboard
+    } else if probe.has_wayland() && probe.command_exists("wl-copy") {
+        ClipboardBackend::WlCopy
+    } else if probe.has_display() && probe.command_exists("xclip") {
+        ClipboardBackend::Xclip
+    } else if probe.has_tmux() {
+        ClipboardBackend::Osc52
+    } else {
+        ClipboardBackend::None
+    }
+}
+
+fn build_clipboard() -> Option<Box<dyn ClipboardSink>> {
+    let probe = SystemClipboardProbe;
+    match select_clipboard_backend(&probe) {
+        ClipboardBackend::Arboard => SystemClipboard::new()
+            .ok()
+            .map(|clipboard| Box::new(clipboard) as Box<dyn ClipboardSink>),
+        ClipboardBackend::WlCopy => Some(Box::new(CommandClipboard::new("wl-copy", &[]))),
+        ClipboardBackend::Xclip => Some(Box::new(CommandClipboard::new(
+            "xclip",
+            &["-selection", "clipboard"],
+        ))),
+        ClipboardBackend::Osc52 => Some(Box::new(Osc52Clipboard { tmux: true })),
+        ClipboardBackend::None => None,
+    }
+}
+
+fn osc52_sequence(text: &str, tmux: bool) -> String {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let osc = format!("\x1b]52;c;{}\x07", encoded);
+    if tmux {
+        format!("\x1bPtmux;\x1b{}\x1b\\", osc)
+    } else {
+        osc
+    }
+}
+
+struct SystemBrowser;
+
+impl BrowserOpener for SystemBrowser {
+    fn open(&mut self, url: &str) -> Result<()> {
+        open::that(url).map_err(|err| anyhow::anyhow!("open failed: {err}"))?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct GitLabGroup {
+    id: usize,
+    name: String,
+    web_url: String,
+    full_path: String,
+    visibility: String,
+    #[serde(default)]
+    parent_id: Option<usize>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    full_name: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabTreeEntry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct GitLabCommit {
+    id: String,
+    #[serde(default)]
+    short_id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    author_name: Option<String>,
+    #[serde(default)]
+    committed_date: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabRef {
+    name: String,
+    #[serde(default)]
+    protected: bool,
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    commit: Option<GitLabCommit>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabRelease {
+    #[serde(default)]
+    name: Option<String>,
+    tag_name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    released_at: Option<String>,
+    #[serde(rename = "_links", default)]
+    links: Option<GitLabReleaseLinks>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabReleaseLinks {
+    #[serde(rename = "self")]
+    self_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabMergeRequest {
+    iid: usize,
+    project_id: usize,
+    title: String,
+    web_url: String,
+    target_branch: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    author: Option<GitLabUser>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    references: Option<GitLabReferences>,
+    #[serde(default)]
+    head_pipeline: Option<GitLabPipelineStatus>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabIssue {
+    iid: usize,
+    project_id: usize,
+    title: String,
+    state: String,
+    web_url: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    assignees: Vec<GitLabUser>,
+    #[serde(default)]
+    author: Option<GitLabUser>,
+    #[serde(default)]
+    milestone: Option<GitLabMilestone>,
+    #[serde(default)]
+    updated_at: Option<String>,
+    #[serde(default)]
+    references: Option<GitLabReferences>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabMilestone {
+    title: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabNote {
+    body: String,
+    #[serde(default)]
+    author: Option<GitLabUser>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    system: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabReferences {
+    full: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabPipelineStatus {
+    status: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabJob {
+    id: usize,
+    name: String,
+    stage: String,
+    status: String,
+    web_url: String,
+    #[serde(default)]
+    duration: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabPipeline {
+    id: usize,
+    #[serde(rename = "ref")]
+    ref_name: String,
+    status: String,
+    web_url: String,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    duration: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabApprovals {
+    #[serde(default)]
+    approved_by: Vec<serde_json::Value>,
+    #[serde(default)]
+    approvals_required: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GitLabMember {
+    id: usize,
+    username: String,
+    #[serde(default)]
+    name: Option<String>,
+    access_level: u64,
+    #[serde(default)]
+    expires_at: Option<String>,
+    #[serde(default)]
+    two_factor_enabled: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct GitLabProject {
+    #[serde(default)]
+    id: usize,
+    name: String,
+    web_url: String,
+    path_with_namespace: String,
+    visibility: String,
+    last_activity_at: Option<String>,
+    #[serde(default)]
+    namespace: Option<GitLabNamespace>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    star_count: Option<u64>,
+    #[serde(default)]
+    forked_from_project: Option<GitLabForkParent>,
+    #[serde(default)]
+    mirror: bool,
+    #[serde(default)]
+    default_branch: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default)]
+    forks_count: Option<u64>,
+    #[serde(default)]
+    open_issues_count: Option<u64>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    creator_id: Option<usize>,
+    #[serde(default)]
+    ssh_url_to_repo: Option<String>,
+    #[serde(default)]
+    http_url_to_repo: Option<String>,
+    #[serde(default)]
+    statistics: Option<GitLabProjectStatistics>,
+    #[serde(default)]
+    readme_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct GitLabProjectStatistics {
+    #[serde(default)]
+    commit_count: u64,
+    #[serde(default)]
+    storage_size: u64,
+    #[serde(default)]
+    repository_size: u64,
+    #[serde(default)]
+    wiki_size: u64,
+    #[serde(default)]
+    lfs_objects_size: u64,
+    #[serde(default)]
+    job_artifacts_size: u64,
+    #[serde(default)]
+    packages_size: u64,
+    #[serde(default)]
+    snippets_size: u64,
+    #[serde(default)]
+    uploads_size: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct GitLabForkParent {
+    #[serde(default)]
+    id: usize,
+    path_with_namespace: String,
+    web_url: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct GroupProjects {
+    group_id: usize,
+    projects: Vec<GitLabProject>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    shared_projects: Vec<GitLabProject>,
+    #[serde(default)]
+    shared_groups: Vec<GitLabGroup>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct GitLabUser {
+    username: String,
+    #[serde(default)]
+    name: Option<String>,
+    /// Only present in responses to admin tokens.
+    #[serde(default)]
+    external: Option<bool>,
+    #[serde(default)]
+    is_admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabPersonalAccessToken {
+    name: String,
+    #[serde(default)]
+    scopes: Vec<String>,
+    #[serde(default)]
+    expires_at: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct TokenInfo {
+    username: String,
+    display_name: Option<String>,
+    token_name: String,
+    scopes: Vec<String>,
+    expires_at: Option<String>,
+    days_until_expiry: Option<i64>,
+}
+
+impl TokenInfo {
+    fn has_any_scope(&self, scopes: &[&str]) -> bool {
+        scopes
+            .iter()
+            .any(|scope| self.scopes.iter().any(|owned| owned == scope))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenAction {
+    Browse,
+    CloneHttps,
+    ManageJobs,
+}
+
+impl TokenAction {
+    fn label(self) -> &'static str {
+        match self {
+            TokenAction::Browse => "browse",
+            TokenAction::CloneHttps => "clone over HTTPS",
+            TokenAction::ManageJobs => "retry/cancel jobs",
+        }
+    }
+
+    fn required_scopes(self) -> &'static [&'static str] {
+        match self {
+            TokenAction::Browse => &["api", "read_api"],
+            TokenAction::CloneHttps => &["read_repository", "write_repository", "api"],
+            TokenAction::ManageJobs => &["api"],
+        }
+    }
+
+    fn missing_scopes(self) -> String {
+        format!("{} needs {}", self.label(), self.required_scopes().join(" or "))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct GitLabNamespace {
+    kind: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct PersonalProjects {
+    username: String,
+    web_url: String,
+    projects: Vec<GitLabProject>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheData {
+    created_at: u64,
+    groups: Vec<GitLabGroup>,
+    projects_by_group: Vec<GroupProjects>,
+    personal: Option<PersonalProjects>,
+}
+
+struct CacheStore {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+impl CacheStore {
+    fn new(path: PathBuf, ttl: Duration) -> Self {
+        Self { path, ttl }
+    }
+
+    fn load(&self) -> Result<Option<CacheData>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        let cache: CacheData = match serde_json::from_slice(&data) {
+            Ok(cache) => cache,
+            Err(_) => return Ok(None),
+        };
+        if cache_is_valid(cache.created_at, self.ttl, SystemTime::now()) {
+            Ok(Some(cache))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn store(&self, cache: &CacheData) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = serde_json::to_vec_pretty(cache)?;
+        std::fs::write(&self.path, data)?;
+        Ok(())
+    }
+}
+
+fn cache_is_valid(created_at: u64, ttl: Duration, now: SystemTime) -> bool {
+    let Ok(now) = now.duration_since(UNIX_EPOCH) else {
+        return false;
+    };
+    let now = now.as_secs();
+    let ttl = ttl.as_secs();
+    now.saturating_sub(created_at) <= ttl
+}
+
+struct Paginator<'a> {
+    client: &'a reqwest::blocking::Client,
+    config: &'a Config,
+    url: String,
+    query: Vec<(&'a str, String)>,
+    keyset_order: Option<&'static str>,
+}
+
+impl<'a> Paginator<'a> {
+    fn new(
+        client: &'a reqwest::blocking::Client,
+        config: &'a Config,
+        url: String,
+        query: Vec<(&'a str, String)>,
+    ) -> Self {
+        Self {
+            client,
+            config,
+            url,
+            query,
+            keyset_order: None,
+        }
+    }
+
+    fn keyset(mut self, order_by: &'static str) -> Self {
+        self.keyset_order = Some(order_by);
+        self
+    }
+
+    fn fetch_all<T>(&self) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send,
+    {
+        if self.config.filters.keyset_pagination
+            && let Some(order_by) = self.keyset_order
+            && let Some(items) = self.fetch_keyset(order_by)?
+        {
+            return Ok(items);
+        }
+        self.fetch_offset()
+    }
+
+    fn fetch_keyset<T: DeserializeOwned>(&self, order_by: &str) -> Result<Option<Vec<T>>> {
+        let mut query = self.query.clone();
+        query.extend([
+            ("pagination", "keyset".to_string()),
+            ("order_by", order_by.to_string()),
+            ("sort", "asc".to_string()),
+            ("per_page", self.config.filters.per_page.to_string()),
+        ]);
+        let request = self
+            .client
+            .get(&self.url)
+            .header("PRIVATE-TOKEN", &self.config.gitlab_token)
+            .query(&query);
+        let resp = send_logged(self.config, request)?;
+        if matches!(
+            resp.status(),
+            reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::METHOD_NOT_ALLOWED
+        ) {
+            return Ok(None);
+        }
+        let resp = resp.error_for_status()?;
+        let mut next = next_link(resp.headers());
+        if next.is_none() && !header_text(resp.headers(), "x-next-page").is_empty() {
+            return Ok(None);
+        }
+        let mut all: Vec<T> = resp.json()?;
+        while let Some(url) = next {
+            let request = self
+                .client
+                .get(&url)
+                .header("PRIVATE-TOKEN", &self.config.gitlab_token);
+            let resp = send_logged(self.config, request)?.error_for_status()?;
+            next = next_link(resp.headers());
+            let mut page: Vec<T> = resp.json()?;
+            all.append(&mut page);
+        }
+        Ok(Some(all))
+    }
+
+    fn fetch_offset<T>(&self) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let resp = self.get_page(1)?;
+        let total_pages = header_text(resp.headers(), "x-total-pages").parse::<usize>().ok();
+        let mut next_page = header_text(resp.headers(), "x-next-page");
+        let mut all: Vec<T> = resp.json()?;
+
+        if let Some(total_pages) = total_pages {
+            let remaining: Vec<usize> = (2..=total_pages).collect();
+            let workers = self.config.filters.page_concurrency.max(1);
+            for batch in remaining.chunks(workers) {
+                let pages: Vec<Result<Vec<T>>> = thread::scope(|scope| {
+                    let handles: Vec<_> = batch
+                        .iter()
+                        .map(|&page| {
+                            scope.spawn(move || -> Result<Vec<T>> { Ok(self.get_page(page)?.json()?) })
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| {
+                            handle
+                                .join()
+                                .unwrap_or_else(|_| Err(anyhow::anyhow!("page fetch panicked")))
+                        })
+                        .collect()
+                });
+                for page in pages {
+                    all.append(&mut page?);
+                }
+            }
+            return Ok(all);
+        }
+
+        while !next_page.is_empty() {
+            let page: usize = next_page
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid x-next-page header: {next_page}"))?;
+            let resp = self.get_page(page)?;
+            next_page = header_text(resp.headers(), "x-next-page");
+            let mut page_items: Vec<T> = resp.json()?;
+            all.append(&mut page_items);
+        }
+        Ok(all)
+    }
+
+    fn get_page(&self, page: usize) -> Result<reqwest::blocking::Response> {
+        let mut query = self.query.clone();
+        query.push(("per_page", self.config.filters.per_page.to_string()));
+        query.push(("page", page.to_string()));
+        let request = self
+            .client
+            .get(&self.url)
+            .header("PRIVATE-TOKEN", &self.config.gitlab_token)
+            .query(&query);
+        Ok(send_logged(self.config, request)?.error_for_status()?)
+    }
+}
+
+fn send_logged(
+    config: &Config,
+    request: reqwest::blocking::RequestBuilder,
+) -> Result<reqwest::blocking::Response> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let method = request.method().clone();
+    let target = match request.url().query() {
+        Some(query) => format!("{}?{query}", request.url().path()),
+        None => request.url().path().to_string(),
+    };
+    let started = Instant::now();
+    let result = client.execute(request);
+    let elapsed = started.elapsed().as_millis();
+    match &result {
+        Ok(resp) => {
+            let level = if resp.status().is_success() {
+                LogLevel::Info
+            } else {
+                LogLevel::Warn
+            };
+            config.log.record(
+                level,
+                "http",
+                format!("{method} {target} -> {} in {elapsed}ms", resp.status().as_u16()),
+            );
+        }
+        Err(err) => config.log.record(
+            LogLevel::Error,
+            "http",
+            format!("{method} {target} failed after {elapsed}ms: {err}"),
+        ),
+    }
+    Ok(result?)
+}
+
+fn header_text(headers: &reqwest::header::HeaderMap, name: &str) -> String {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+fn next_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    parse_next_link(&header_text(headers, "link"))
+}
+
+fn parse_next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim().trim_start_matches("rel=").trim_matches('"') == "next");
+        if !is_next {
+            return None;
+        }
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        Some(target.to_string())
+    })
+}
+
+trait GitLabApi: Sync {
+    fn current_user(&self) -> Result<GitLabUser>;
+    fn personal_access_token(&self) -> Result<GitLabPersonalAccessToken>;
+    fn groups(&self) -> Result<Vec<GitLabGroup>>;
+    fn group_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>>;
+    fn shared_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>>;
+    fn shared_groups(&self, group_id: usize) -> Result<Vec<GitLabGroup>>;
+    fn owned_projects(&self) -> Result<Vec<GitLabProject>>;
+    fn project(&self, project_id: usize) -> Result<GitLabProject>;
+    fn user(&self, user_id: usize) -> Result<GitLabUser>;
+    fn group(&self, group_id: usize) -> Result<GitLabGroup>;
+    fn group_member_count(&self, group_id: usize) -> Result<usize>;
+    fn group_member(&self, group_id: usize, username: &str) -> Result<Option<GitLabMember>>;
+    fn members(&self, scope: ViewScope, inherited: bool) -> Result<Vec<GitLabMember>>;
+    fn file_raw(&self, project_id: usize, path: &str, ref_name: &str) -> Result<String>;
+    fn repository_tree(
+        &self,
+        project_id: usize,
+        path: &str,
+        ref_name: &str,
+    ) -> Result<Vec<GitLabTreeEntry>>;
+    fn commit(&self, project_id: usize, ref_name: &str) -> Result<GitLabCommit>;
+    fn branches(&self, project_id: usize) -> Result<Vec<GitLabRef>>;
+    fn tags(&self, project_id: usize) -> Result<Vec<GitLabRef>>;
+    fn releases(&self, project_id: usize) -> Result<Vec<GitLabRelease>>;
+    fn commit_count(&self, project_id: usize, range: &str) -> Result<Option<usize>>;
+    fn merge_requests(
+        &self,
+        scope: ViewScope,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabMergeRequest>>;
+    fn merge_request(&self, project_id: usize, iid: usize) -> Result<GitLabMergeRequest>;
+    fn merge_request_approvals(&self, project_id: usize, iid: usize) -> Result<GitLabApprovals>;
+    fn issues(
+        &self,
+        scope: ViewScope,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabIssue>>;
+    fn issue_notes(&self, project_id: usize, iid: usize) -> Result<Vec<GitLabNote>>;
+    fn pipelines(
+        &self,
+        project_id: usize,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabPipeline>>;
+    fn pipeline(&self, project_id: usize, pipeline_id: usize) -> Result<GitLabPipeline>;
+    fn pipeline_jobs(&self, project_id: usize, pipeline_id: usize) -> Result<Vec<GitLabJob>>;
+    fn job(&self, project_id: usize, job_id: usize) -> Result<GitLabJob>;
+    fn job_trace(&self, project_id: usize, job_id: usize) -> Result<String>;
+    fn job_action(&self, project_id: usize, job_id: usize, action: JobAction) -> Result<GitLabJob>;
+    fn graphql(&self, query: &str, variables: serde_json::Value) -> Result<serde_json::Value>;
+}
+
+struct HttpGitLabApi {
+    client: reqwest::blocking::Client,
+    config: Config,
+}
+
+impl HttpGitLabApi {
+    fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            client: build_http_client(&config.http)?,
+            config: config.clone(),
+        })
+    }
+}
+
+impl GitLabApi for HttpGitLabApi {
+    fn current_user(&self) -> Result<GitLabUser> {
+        fetch_current_user(&self.client, &self.config)
+    }
+
+    fn personal_access_token(&self) -> Result<GitLabPersonalAccessToken> {
+        fetch_personal_access_token(&self.client, &self.config)
+    }
+
+    fn groups(&self) -> Result<Vec<GitLabGroup>> {
+        fetch_groups(&self.client, &self.config)
+    }
+
+    fn group_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>> {
+        fetch_group_projects(&self.client, &self.config, group_id)
+    }
+
+    fn shared_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>> {
+        fetch_shared_projects(&self.client, &self.config, group_id)
+    }
+
+    fn shared_groups(&self, group_id: usize) -> Result<Vec<GitLabGroup>> {
+        fetch_shared_groups(&self.client, &self.config, group_id)
+    }
+
+    fn owned_projects(&self) -> Result<Vec<GitLabProject>> {
+        fetch_owned_projects(&self.client, &self.config)
+    }
+
+    fn project(&self, project_id: usize) -> Result<GitLabProject> {
+        fetch_project(&self.client, &self.config, project_id)
+    }
+
+    fn user(&self, user_id: usize) -> Result<GitLabUser> {
+        fetch_user(&self.client, &self.config, user_id)
+    }
+
+    fn group(&self, group_id: usize) -> Result<GitLabGroup> {
+        fetch_group(&self.client, &self.config, group_id)
+    }
+
+    fn group_member_count(&self, group_id: usize) -> Result<usize> {
+        let path = format!("groups/{group_id}/members/all");
+        fetch_total(&self.client, &self.config, &path, &[])?
+            .ok_or_else(|| anyhow::anyhow!("GitLab did not report a total for {path}"))
+    }
+
+    /// Looks up one effective member by username; `query` also matches names,
+    /// so the result is filtered to the exact username.
+    fn group_member(&self, group_id: usize, username: &str) -> Result<Option<GitLabMember>> {
+        let path = format!("groups/{group_id}/members/all");
+        let query = [("query", username.to_string())];
+        let members: Vec<GitLabMember> =
+            fetch_json_query(&self.client, &self.config, &path, &query)?;
+        Ok(members.into_iter().find(|member| member.username == username))
+    }
+
+    fn members(&self, scope: ViewScope, inherited: bool) -> Result<Vec<GitLabMember>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let suffix = if inherited { "/all" } else { "" };
+        let url = format!("{base}/api/v4/{}/members{suffix}", scope.api_path());
+        Paginator::new(&self.client, &self.config, url, Vec::new()).fetch_all()
+    }
+
+    fn file_raw(&self, project_id: usize, path: &str, ref_name: &str) -> Result<String> {
+        fetch_file_raw(&self.client, &self.config, project_id, path, ref_name)
+    }
+
+    fn repository_tree(
+        &self,
+        project_id: usize,
+        path: &str,
+        ref_name: &str,
+    ) -> Result<Vec<GitLabTreeEntry>> {
+        fetch_repository_tree(&self.client, &self.config, project_id, path, ref_name)
+    }
+
+    fn commit(&self, project_id: usize, ref_name: &str) -> Result<GitLabCommit> {
+        fetch_commit(&self.client, &self.config, project_id, ref_name)
+    }
+
+    fn branches(&self, project_id: usize) -> Result<Vec<GitLabRef>> {
+        fetch_refs(&self.client, &self.config, project_id, "branches")
+    }
+
+    fn tags(&self, project_id: usize) -> Result<Vec<GitLabRef>> {
+        fetch_refs(&self.client, &self.config, project_id, "tags")
+    }
+
+    fn releases(&self, project_id: usize) -> Result<Vec<GitLabRelease>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/projects/{project_id}/releases");
+        Paginator::new(&self.client, &self.config, url, Vec::new()).fetch_all()
+    }
+
+    /// Counts commits in a `from..to` range from the list's `x-total` header,
+    /// without downloading the commits or their diffs. `None` means the range
+    /// holds more than [`COMMIT_COUNT_CAP`] commits.
+    fn commit_count(&self, project_id: usize, range: &str) -> Result<Option<usize>> {
+        let path = format!("projects/{project_id}/repository/commits");
+        fetch_total(&self.client, &self.config, &path, &[("ref_name", range.to_string())])
+    }
+
+    fn merge_requests(
+        &self,
+        scope: ViewScope,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabMergeRequest>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/{}/merge_requests", scope.api_path());
+        Paginator::new(&self.client, &self.config, url, query).fetch_all()
+    }
+
+    fn merge_request(&self, project_id: usize, iid: usize) -> Result<GitLabMergeRequest> {
+        let path = format!("projects/{project_id}/merge_requests/{iid}");
+        fetch_json(&self.client, &self.config, &path)
+    }
+
+    fn merge_request_approvals(&self, project_id: usize, iid: usize) -> Result<GitLabApprovals> {
+        let path = format!("projects/{project_id}/merge_requests/{iid}/approvals");
+        fetch_json(&self.client, &self.config, &path)
+    }
+
+    fn issues(
+        &self,
+        scope: ViewScope,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabIssue>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/{}/issues", scope.api_path());
+        Paginator::new(&self.client, &self.config, url, query).fetch_all()
+    }
+
+    fn pipelines(
+        &self,
+        project_id: usize,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<GitLabPipeline>> {
+        let path = format!("projects/{project_id}/pipelines");
+        fetch_json_query(&self.client, &self.config, &path, &query)
+    }
+
+    fn pipeline(&self, project_id: usize, pipeline_id: usize) -> Result<GitLabPipeline> {
+        let path = format!("projects/{project_id}/pipelines/{pipeline_id}");
+        fetch_json(&self.client, &self.config, &path)
+    }
+
+    fn pipeline_jobs(&self, project_id: usize, pipeline_id: usize) -> Result<Vec<GitLabJob>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/projects/{project_id}/pipelines/{pipeline_id}/jobs");
+        Paginator::new(&self.client, &self.config, url, Vec::new()).fetch_all()
+    }
+
+    fn job(&self, project_id: usize, job_id: usize) -> Result<GitLabJob> {
+        let path = format!("projects/{project_id}/jobs/{job_id}");
+        fetch_json(&self.client, &self.config, &path)
+    }
+
+    fn job_trace(&self, project_id: usize, job_id: usize) -> Result<String> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/projects/{project_id}/jobs/{job_id}/trace");
+        let request = self.client.get(&url).header("PRIVATE-TOKEN", &self.config.gitlab_token);
+        Ok(send_logged(&self.config, request)?.error_for_status()?.text()?)
+    }
+
+    fn job_action(&self, project_id: usize, job_id: usize, action: JobAction) -> Result<GitLabJob> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/projects/{project_id}/jobs/{job_id}/{}", action.path());
+        let request = self.client.post(&url).header("PRIVATE-TOKEN", &self.config.gitlab_token);
+        Ok(send_logged(&self.config, request)?.error_for_status()?.json::<GitLabJob>()?)
+    }
+
+    fn issue_notes(&self, project_id: usize, iid: usize) -> Result<Vec<GitLabNote>> {
+        let base = self.config.gitlab_url.trim_end_matches('/');
+        let url = format!("{base}/api/v4/projects/{project_id}/issues/{iid}/notes");
+        let query = vec![("sort", "asc".to_string()), ("order_by", "created_at".to_string())];
+        Paginator::new(&self.client, &self.config, url, query).fetch_all()
+    }
+
+    fn graphql(&self, query: &str, variables: serde_json::Value) -> Result<serde_json::Value> {
+        fetch_graphql(&self.client, &self.config, query, variables)
+    }
+}
+
+fn fetch_groups(client: &reqwest::blocking::Client, config: &Config) -> Result<Vec<GitLabGroup>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/groups");
+    let mut query: Vec<(&str, String)> = vec![("membership", "true".to_string())];
+    if let Some(value) = config.filters.all_available {
+        query.push(("all_available", value.to_string()));
+    }
+    if let Some(value) = config.filters.owned {
+        query.push(("owned", value.to_string()));
+    }
+    if let Some(value) = config.filters.top_level_only {
+        query.push(("top_level_only", value.to_string()));
+    }
+    if let Some(value) = &config.filters.visibility {
+        query.push(("visibility", value.to_string()));
+    }
+    Paginator::new(client, config, url, query)
+        .keyset("name")
+        .fetch_all()
+}
+
+fn fetch_group_projects(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    group_id: usize,
+) -> Result<Vec<GitLabProject>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/groups/{group_id}/projects");
+    let mut query: Vec<(&str, String)> = vec![("statistics", "true".to_string())];
+    if let Some(value) = config.filters.include_subgroups {
+        query.push(("include_subgroups", value.to_string()));
+    }
+    if let Some(value) = &config.filters.visibility {
+        query.push(("visibility", value.to_string()));
+    }
+    Paginator::new(client, config, url, query).fetch_all()
+}
+
+fn fetch_shared_projects(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    group_id: usize,
+) -> Result<Vec<GitLabProject>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/groups/{group_id}/projects/shared");
+    let mut query: Vec<(&str, String)> = Vec::new();
+    if let Some(value) = &config.filters.visibility {
+        query.push(("visibility", value.to_string()));
+    }
+    Paginator::new(client, config, url, query).fetch_all()
+}
+
+fn fetch_shared_groups(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    group_id: usize,
+) -> Result<Vec<GitLabGroup>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/groups/{group_id}/groups/shared");
+    let mut query: Vec<(&str, String)> = Vec::new();
+    if let Some(value) = &config.filters.visibility {
+        query.push(("visibility", value.to_string()));
+    }
+    Paginator::new(client, config, url, query).fetch_all()
+}
+
+fn fetch_current_user(client: &reqwest::blocking::Client, config: &Config) -> Result<GitLabUser> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/user");
+    let request = client.get(&url).header("PRIVATE-TOKEN", &config.gitlab_token);
+    let user = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabUser>()?;
+    Ok(user)
+}
+
+fn fetch_project(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    project_id: usize,
+) -> Result<GitLabProject> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/projects/{project_id}");
+    let request = client
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .query(&[("statistics", "true")]);
+    let project = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabProject>()?;
+    Ok(project)
+}
+
+fn fetch_user(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    user_id: usize,
+) -> Result<GitLabUser> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/users/{user_id}");
+    let request = client.get(&url).header("PRIVATE-TOKEN", &config.gitlab_token);
+    let user = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabUser>()?;
+    Ok(user)
+}
+
+fn fetch_group(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    group_id: usize,
+) -> Result<GitLabGroup> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/groups/{group_id}");
+    let request = client
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .query(&[("with_projects", "false")]);
+    let group = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabGroup>()?;
+    Ok(group)
+}
+
+fn fetch_file_raw(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    project_id: usize,
+    path: &str,
+    ref_name: &str,
+) -> Result<String> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let path = encode_path_segment(path);
+    let url = format!("{base}/api/v4/projects/{project_id}/repository/files/{path}/raw");
+    let request = client
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .query(&[("ref", ref_name)]);
+    let body = send_logged(config, request)?.error_for_status()?.text()?;
+    Ok(body)
+}
+
+fn fetch_repository_tree(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    project_id: usize,
+    path: &str,
+    ref_name: &str,
+) -> Result<Vec<GitLabTreeEntry>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/projects/{project_id}/repository/tree");
+    let mut query: Vec<(&str, String)> = vec![("ref", ref_name.to_string())];
+    if !path.is_empty() {
+        query.push(("path", path.to_string()));
+    }
+    Paginator::new(client, config, url, query).fetch_all()
+}
+
+fn fetch_commit(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    project_id: usize,
+    ref_name: &str,
+) -> Result<GitLabCommit> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let ref_name = encode_path_segment(ref_name);
+    let url = format!("{base}/api/v4/projects/{project_id}/repository/commits/{ref_name}");
+    let request = client.get(&url).header("PRIVATE-TOKEN", &config.gitlab_token);
+    let commit = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabCommit>()?;
+    Ok(commit)
+}
+
+fn fetch_refs(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    project_id: usize,
+    kind: &str,
+) -> Result<Vec<GitLabRef>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/projects/{project_id}/repository/{kind}");
+    Paginator::new(client, config, url, Vec::new()).fetch_all()
+}
+
+fn fetch_json<T: DeserializeOwned>(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    path: &str,
+) -> Result<T> {
+    fetch_json_query(client, config, path, &[])
+}
+
+fn fetch_json_query<T: DeserializeOwned>(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    path: &str,
+    query: &[(&str, String)],
+) -> Result<T> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/{path}");
+    let request = client
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .query(query);
+    Ok(send_logged(config, request)?.error_for_status()?.json::<T>()?)
+}
+
+/// Reads a list's `x-total` header from a single-item page instead of paging
+/// through every record.
+fn fetch_total(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    path: &str,
+    query: &[(&str, String)],
+) -> Result<Option<usize>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/{path}");
+    let request = client
+        .get(&url)
+        .header("PRIVATE-TOKEN", &config.gitlab_token)
+        .query(query)
+        .query(&[("per_page", "1")]);
+    let response = send_logged(config, request)?.error_for_status()?;
+    // GitLab leaves x-total out once a list passes COMMIT_COUNT_CAP rows.
+    let total = header_text(response.headers(), "x-total");
+    if total.is_empty() {
+        return Ok(None);
+    }
+    let total = total
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid x-total header for {path}: {total:?}"))?;
+    Ok(Some(total))
+}
+
+fn encode_path_segment(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn fetch_personal_access_token(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+) -> Result<GitLabPersonalAccessToken> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/personal_access_tokens/self");
+    let request = client.get(&url).header("PRIVATE-TOKEN", &config.gitlab_token);
+    let token = send_logged(config, request)?
+        .error_for_status()?
+        .json::<GitLabPersonalAccessToken>()?;
+    Ok(token)
+}
+
+fn fetch_token_info(api: &dyn GitLabApi) -> Result<TokenInfo> {
+    let user = api.current_user()?;
+    let token = api.personal_access_token()?;
+    let days_until_expiry = token
+        .expires_at
+        .as_deref()
+        .and_then(|expires_at| days_until(expires_at, SystemTime::now()));
+    Ok(TokenInfo {
+        username: user.username,
+        display_name: user.name,
+        token_name: token.name,
+        scopes: token.scopes,
+        expires_at: token.expires_at,
+        days_until_expiry,
+    })
+}
+
+fn format_token_summary(token: &TokenInfo) -> String {
+    let scopes = if token.scopes.is_empty() {
+        "none".to_string()
+    } else {
+        token.scopes.join(",")
+    };
+    let expiry = match token.days_until_expiry {
+        Some(days) if days < 0 => "expired".to_string(),
+        Some(days) => format!("expires in {days}d"),
+        None => "no expiry".to_string(),
+    };
+    format!("user: {} | scopes: {scopes} | {expiry}", token.username)
+}
+
+fn format_token_details(token: &TokenInfo) -> Vec<String> {
+    let user = match &token.display_name {
+        Some(name) => format!("Logged in as: {name} (@{})", token.username),
+        None => format!("Logged in as: @{}", token.username),
+    };
+    let expiry = match (&token.expires_at, token.days_until_expiry) {
+        (Some(date), Some(days)) => format!("Token expires: {date} ({days} days)"),
+        (Some(date), None) => format!("Token expires: {date}"),
+        (None, _) => "Token expires: never".to_string(),
+    };
+    vec![
+        user,
+        format!("Token: {} [{}]", token.token_name, token.scopes.join(", ")),
+        expiry,
+    ]
+}
+
+fn token_warnings(token: &TokenInfo, warn_days: u64) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(days) = token.days_until_expiry {
+        if days < 0 {
+            warnings.push("token expired".to_string());
+        } else if days <= warn_days as i64 {
+            warnings.push(format!("token expires in {days}d"));
+        }
+    }
+    // Other actions warn when attempted; see `App::scope_warning`.
+    if !token.has_any_scope(TokenAction::Browse.required_scopes()) {
+        warnings.push(TokenAction::Browse.missing_scopes());
+    }
+    warnings
+}
+
+/// Calendar days from `now` to the timestamp, both taken as UTC dates after
+/// normalising the timestamp's offset.
+fn days_until(timestamp: &str, now: SystemTime) -> Option<i64> {
+    let target = parse_timestamp(timestamp)?.date_naive();
+    Some((target - DateTime::<Utc>::from(now).date_naive()).num_days())
+}
+
+fn fetch_owned_projects(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+) -> Result<Vec<GitLabProject>> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/v4/projects");
+    let mut query: Vec<(&str, String)> = vec![("owned", "true".to_string())];
+    if let Some(value) = &config.filters.visibility {
+        query.push(("visibility", value.to_string()));
+    }
+    let projects = Paginator::new(client, config, url, query)
+        .keyset("id")
+        .fetch_all()?;
+    Ok(filter_personal_projects(projects))
+}
+
+fn fetch_personal_projects(api: &dyn GitLabApi, config: &Config) -> Result<PersonalProjects> {
+    let user = api.current_user()?;
+    let projects = api.owned_projects()?;
+    let base = config.gitlab_url.trim_end_matches('/');
+    let web_url = format!("{base}/{}", user.username);
+    Ok(PersonalProjects {
+        username: user.username,
+        web_url,
+        projects,
+    })
+}
+
+fn filter_personal_projects(projects: Vec<GitLabProject>) -> Vec<GitLabProject> {
+    projects
+        .into_iter()
+        .filter(|project| project.namespace.as_ref().map(|ns| ns.kind.as_str()) == Some("user"))
+        .collect()
+}
+
+fn fetch_projects_by_group(
+    api: &dyn GitLabApi,
+    config: &Config,
+    groups: &[GitLabGroup],
+) -> Vec<GroupProjects> {
+    let mut projects = Vec::with_capacity(groups.len());
+    for group in groups {
+        let mut entry = match api.group_projects(group.id) {
+            Ok(group_projects) => GroupProjects {
+                group_id: group.id,
+                projects: group_projects,
+                ..GroupProjects::default()
+            },
+            Err(err) => GroupProjects {
+                group_id: group.id,
+                error: Some(err.to_string()),
+                ..GroupProjects::default()
+            },
+        };
+        if config.show_shared {
+            match api.shared_projects(group.id) {
+                Ok(shared) => entry.shared_projects = shared,
+                Err(err) => config.log.record(
+                    LogLevel::Warn,
+                    "load",
+                    format!("group {} shared projects failed: {err}", group.id),
+                ),
+            }
+            match api.shared_groups(group.id) {
+                Ok(shared) => entry.shared_groups = shared,
+                Err(err) => config.log.record(
+                    LogLevel::Warn,
+                    "load",
+                    format!("group {} shared groups failed: {err}", group.id),
+                ),
+            }
+        }
+        projects.push(entry);
+    }
+    projects
+}
+
+const GRAPHQL_UNSUPPORTED: &str =
+    "graphql loader: forks, mirrors and shared projects/groups are not loaded";
+
+const GRAPHQL_GROUPS_QUERY: &str = r#"
+query($first: Int!, $after: String) {
+  currentUser {
+    username
+    webUrl
+    groups(first: $first, after: $after) {
+      pageInfo { hasNextPage endCursor }
+      nodes { id name fullPath webUrl visibility parent { id } }
+    }
+  }
+}
+"#;
+
+const GRAPHQL_PROJECTS_QUERY: &str = r#"
+query($first: Int!, $after: String) {
+  projects(membership: true, first: $first, after: $after) {
+    pageInfo { hasNextPage endCursor }
+    nodes {
+      id name fullPath webUrl visibility description archived starCount lastActivityAt
+      group { id }
+      repository { rootRef }
+    }
+  }
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlConnection<T> {
+    page_info: GraphQlPageInfo,
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlPageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlRef {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlCurrentUser {
+    username: String,
+    web_url: String,
+    groups: GraphQlConnection<GraphQlGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlGroup {
+    id: String,
+    name: String,
+    full_path: String,
+    web_url: String,
+    visibility: String,
+    parent: Option<GraphQlRef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlProject {
+    id: String,
+    name: String,
+    full_path: String,
+    web_url: String,
+    visibility: String,
+    description: Option<String>,
+    #[serde(default)]
+    archived: bool,
+    star_count: Option<u64>,
+    last_activity_at: Option<String>,
+    group: Option<GraphQlRef>,
+    #[serde(default)]
+    repository: Option<GraphQlRepository>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRepository {
+    root_ref: Option<String>,
+}
+
+fn fetch_graphql(
+    client: &reqwest::blocking::Client,
+    config: &Config,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let base = config.gitlab_url.trim_end_matches('/');
+    let url = format!("{base}/api/graphql");
+    let request = client
+        .post(&url)
+        .bearer_auth(&config.gitlab_token)
+        .json(&serde_json::json!({ "query": query, "variables": variables }));
+    let mut body: serde_json::Value = send_logged(config, request)?.error_for_status()?.json()?;
+    if let Some(errors) = body.get("errors").and_then(|errors| errors.as_array()) {
+        let messages: Vec<&str> = errors
+            .iter()
+            .filter_map(|error| error.get("message").and_then(|message| message.as_str()))
+            .collect();
+        anyhow::bail!("graphql error: {}", messages.join("; "));
+    }
+    Ok(body
+        .get_mut("data")
+        .map(serde_json::Value::take)
+        .unwrap_or(serde_json::Value::Null))
+}
+
+/// Loads groups and projects through GraphQL. Fork parents, mirror flags and
+/// shared projects/groups are not part of these queries, so the tree shows
+/// no relationship markers or shared subheadings; the REST loader has them.
+fn load_graphql(
+    api: &dyn GitLabApi,
+    config: &Config,
+) -> Result<(Vec<GitLabGroup>, Vec<GroupProjects>, Option<PersonalProjects>)> {
+    config.log.record(LogLevel::Warn, "load", GRAPHQL_UNSUPPORTED.to_string());
+    let first = config.filters.per_page.min(100);
+    let mut groups = Vec::new();
+    let mut after: Option<String> = None;
+    let (username, web_url) = loop {
+        let mut data = api.graphql(
+            GRAPHQL_GROUPS_QUERY,
+            serde_json::json!({ "first": first, "after": after }),
+        )?;
+        let current: GraphQlCurrentUser = serde_json::from_value(data["currentUser"].take())
+            .map_err(|err| anyhow::anyhow!("invalid graphql groups response: {err}"))?;
+        groups.extend(current.groups.nodes.into_iter().filter_map(graphql_group));
+        let page_info = current.groups.page_info;
+        if !page_info.has_next_page || page_info.end_cursor.is_none() {
+            break (current.username, current.web_url);
+        }
+        after = page_info.end_cursor;
+    };
+    if config.filters.top_level_only == Some(true) {
+        groups.retain(|group| group.parent_id.is_none());
+    }
+    if let Some(visibility) = &config.filters.visibility {
+        groups.retain(|group| &group.visibility == visibility);
+    }
+
+    let mut by_group: HashMap<usize, Vec<GitLabProject>> = HashMap::new();
+    let mut personal_projects = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let mut data = api.graphql(
+            GRAPHQL_PROJECTS_QUERY,
+            serde_json::json!({ "first": first, "after": after }),
+        )?;
+        let connection: GraphQlConnection<GraphQlProject> =
+            serde_json::from_value(data["projects"].take())
+                .map_err(|err| anyhow::anyhow!("invalid graphql projects response: {err}"))?;
+        for project in connection.nodes {
+            let group_id = project.group.as_ref().and_then(|group| parse_global_id(&group.id));
+            let Some(project) = graphql_project(project) else {
+                continue;
+            };
+            if let Some(visibility) = &config.filters.visibility
+                && &project.visibility != visibility
+            {
+                continue;
+            }
+            match group_id {
+                Some(group_id) => by_group.entry(group_id).or_default().push(project),
+                None => personal_projects.push(project),
+            }
+        }
+        if !connection.page_info.has_next_page || connection.page_info.end_cursor.is_none() {
+            break;
+        }
+        after = connection.page_info.end_cursor;
+    }
+
+    let projects = groups
+        .iter()
+        .map(|group| GroupProjects {
+            group_id: group.id,
+            projects: by_group.remove(&group.id).unwrap_or_default(),
+            ..GroupProjects::default()
+        })
+        .collect();
+    let prefix = format!("{username}/");
+    let personal = PersonalProjects {
+        projects: personal_projects
+            .into_iter()
+            .filter(|project| project.path_with_namespace.starts_with(&prefix))
+            .collect(),
+        username,
+        web_url,
+    };
+    Ok((groups, projects, Some(personal)))
+}
+
+fn graphql_group(group: GraphQlGroup) -> Option<GitLabGroup> {
+    Some(GitLabGroup {
+        id: parse_global_id(&group.id)?,
+        name: group.name,
+        web_url: group.web_url,
+        full_path: group.full_path,
+        visibility: group.visibility,
+        parent_id: group.parent.and_then(|parent| parse_global_id(&parent.id)),
+        ..GitLabGroup::default()
+    })
+}
+
+fn graphql_project(project: GraphQlProject) -> Option<GitLabProject> {
+    let namespace_kind = if project.group.is_some() { "group" } else { "user" };
+    Some(GitLabProject {
+        id: parse_global_id(&project.id)?,
+        name: project.name,
+        web_url: project.web_url,
+        path_with_namespace: project.full_path,
+        visibility: project.visibility,
+        last_activity_at: project.last_activity_at,
+        namespace: Some(GitLabNamespace {
+            kind: namespace_kind.to_string(),
+        }),
+        description: project.description,
+        archived: project.archived,
+        star_count: project.star_count,
+        default_branch: project.repository.and_then(|repository| repository.root_ref),
+        ..GitLabProject::default()
+    })
+}
+
+fn parse_global_id(gid: &str) -> Option<usize> {
+    gid.rsplit('/').next()?.parse().ok()
+}
+
+fn read_env_optional<F>(reader: &F, key: &str) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    reader(key).filter(|value| !value.trim().is_empty())
+}
+
+fn read_env_required<F>(reader: &F, key: &str) -> Result<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match read_env_optional(reader, key) {
+        Some(value) => Ok(value),
+        None => anyhow::bail!("missing required environment variable: {key}"),
+    }
+}
+
+fn read_env_bool_optional<F>(reader: &F, key: &str) -> Result<Option<bool>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let Some(value) = read_env_optional(reader, key) else {
+        return Ok(None);
+    };
+    let normalized = value.to_lowercase();
+    match normalized.as_str() {
+        "1" | "true" | "yes" | "on" => Ok(Some(true)),
+        "0" | "false" | "no" | "off" => Ok(Some(false)),
+        _ => anyhow::bail!("invalid boolean for {key}: {value}"),
+    }
+}
+
+fn read_env_u16_optional<F>(reader: &F, key: &str) -> Result<Option<u16>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let Some(value) = read_env_optional(reader, key) else {
+        return Ok(None);
+    };
+    let parsed = value
+        .parse::<u16>()
+        .map_err(|_| anyhow::anyhow!("invalid integer for {key}: {value}"))?;
+    Ok(Some(parsed))
+}
+
+fn read_env_u64_optional<F>(reader: &F, key: &str) -> Result<Option<u64>>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let Some(value) = read_env_optional(reader, key) else {
+        return Ok(None);
+    };
+    let parsed = value
+        .parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("invalid integer for {key}: {value}"))?;
+    Ok(Some(parsed))
+}
+
+fn default_cache_path() -> PathBuf {
+    let base = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join("gitlab-tree").join("cache.json")
+}
+
+#[derive(Clone)]
+struct Node {
+    name: String,
+    kind: NodeKind,
+    children: Vec<usize>,
+    expanded: bool,
+    url: String,
+    path: String,
+    visibility: String,
+    last_activity: Option<String>,
+    gitlab_id: Option<usize>,
+    error: Option<String>,
+    loading: bool,
+    archived: bool,
+    relation: NodeRelation,
+    repo: Option<RepoEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RepoEntry {
+    project_id: usize,
+    path: String,
+}
+
+#[derive(Clone, Debug, Default)]
+struct NodeRelation {
+    forked_from: Option<GitLabForkParent>,
+    mirror: bool,
+    shared: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Group,
+    Project,
+    Shared,
+    Directory,
+    File,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum DetailState {
+    Partial,
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+struct ProjectDetails {
+    project: Option<GitLabProject>,
+    creator: Option<String>,
+    state: DetailState,
+}
+
+#[derive(Clone, Debug)]
+struct ProjectReadme {
+    content: Option<String>,
+    state: DetailState,
+    scroll: u16,
+}
+
+#[derive(Clone, Debug)]
+struct FilePager {
+    project_id: usize,
+    path: String,
+    ref_name: String,
+    project_url: String,
+    content: Option<String>,
+    commit: Option<String>,
+    state: DetailState,
+    scroll: u16,
+}
+
+impl FilePager {
+    fn permalink(&self) -> String {
+        let revision = self.commit.as_deref().unwrap_or(&self.ref_name);
+        format!("{}/-/blob/{revision}/{}", self.project_url, self.path)
+    }
+
+    fn line_count(&self) -> usize {
+        self.content.as_deref().map_or(0, |text| text.lines().count())
+    }
+
+    fn scroll_by(&mut self, delta: i32) {
+        let max = u16::try_from(self.line_count().saturating_sub(1)).unwrap_or(u16::MAX);
+        let next = i32::from(self.scroll) + delta;
+        self.scroll = u16::try_from(next.max(0)).unwrap_or(u16::MAX).min(max);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RefPicker {
+    project_id: usize,
+    node_id: usize,
+    refs: Vec<(&'static str, String)>,
+    selected: usize,
+    state: DetailState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ViewScope {
+    Project(usize),
+    Group(usize),
+}
+
+impl ViewScope {
+    fn api_path(self) -> String {
+        match self {
+            ViewScope::Project(id) => format!("projects/{id}"),
+            ViewScope::Group(id) => format!("groups/{id}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MergeRequestFilter {
+    All,
+    AssignedToMe,
+    ReviewRequested,
+}
+
+impl MergeRequestFilter {
+    fn next(self) -> Self {
+        match self {
+            MergeRequestFilter::All => MergeRequestFilter::AssignedToMe,
+            MergeRequestFilter::AssignedToMe => MergeRequestFilter::ReviewRequested,
+            MergeRequestFilter::ReviewRequested => MergeRequestFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MergeRequestFilter::All => "all open",
+            MergeRequestFilter::AssignedToMe => "assigned to me",
+            MergeRequestFilter::ReviewRequested => "review requested",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MergeRequestRow {
+    merge_request: GitLabMergeRequest,
+    pipeline: Option<String>,
+    approvals: Option<(usize, usize)>,
+    state: DetailState,
+}
+
+#[derive(Clone, Debug)]
+struct MergeRequestView {
+    scope: ViewScope,
+    path: String,
+    filter: MergeRequestFilter,
+    rows: Vec<MergeRequestRow>,
+    selected: usize,
+    state: DetailState,
+}
+
+impl MergeRequestView {
+    /// The list endpoint lacks the head pipeline and approvals; they are
+    /// fetched for the selected row only.
+    fn detail_fetch(&mut self) -> Option<FetchRequest> {
+        let row = self.rows.get_mut(self.selected)?;
+        if row.state != DetailState::Partial {
+            return None;
+        }
+        row.state = DetailState::Loading;
+        let (project_id, iid) = (row.merge_request.project_id, row.merge_request.iid);
+        Some(FetchRequest::MergeRequestDetail { project_id, iid })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct IssueView {
+    scope: ViewScope,
+    path: String,
+    query: String,
+    input: Option<String>,
+    rows: Vec<GitLabIssue>,
+    selected: usize,
+    state: DetailState,
+    detail: Option<Box<IssueDetail>>,
+}
+
+#[derive(Clone, Debug)]
+struct IssueDetail {
+    issue: GitLabIssue,
+    notes: Vec<GitLabNote>,
+    state: DetailState,
+    scroll: u16,
+}
+
+#[derive(Clone, Debug)]
+struct PipelineView {
+    project_id: usize,
+    path: String,
+    rows: Vec<GitLabPipeline>,
+    selected: usize,
+    state: DetailState,
+    jobs: Option<Box<JobsPanel>>,
+    detailed: HashSet<usize>,
+}
+
+impl PipelineView {
+    /// The list endpoint has no durations; fetch the selected pipeline once.
+    fn detail_fetch(&mut self) -> Option<FetchRequest> {
+        let pipeline = self.rows.get(self.selected)?;
+        if pipeline.duration.is_some() || !self.detailed.insert(pipeline.id) {
+            return None;
+        }
+        Some(FetchRequest::PipelineDetail { project_id: self.project_id, pipeline_id: pipeline.id })
+    }
+}
+
+/// Tree-row pipeline badge for a project's latest default-branch pipeline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PipelineBadge {
+    Pending,
+    Status(String),
+    /// No pipeline was found or the lookup failed; retried after
+    /// `PIPELINE_BADGE_RETRY`.
+    Missing(Instant),
+}
+
+impl PipelineBadge {
+    fn status(&self) -> Option<&str> {
+        match self {
+            PipelineBadge::Status(status) => Some(status),
+            PipelineBadge::Pending | PipelineBadge::Missing(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct JobsPanel {
+    pipeline_id: usize,
+    rows: Vec<GitLabJob>,
+    selected: usize,
+    state: DetailState,
+    log: Option<JobLog>,
+}
+
+#[derive(Clone, Debug)]
+struct JobLog {
+    job: GitLabJob,
+    lines: Vec<TraceLine>,
+    sections: Vec<TraceSection>,
+    folded: HashMap<String, bool>,
+    cursor: usize,
+    follow: bool,
+    state: DetailState,
+    fetched_at: Option<Instant>,
+}
+
+impl JobLog {
+    fn is_live(&self) -> bool {
+        matches!(self.job.status.as_str(), "running" | "pending" | "created" | "preparing")
+    }
+
+    fn is_folded(&self, section: usize) -> bool {
+        let section = &self.sections[section];
+        self.folded.get(&section.name).copied().unwrap_or(section.collapsed)
+    }
+
+    fn visible_lines(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.sections
+                    .iter()
+                    .all(|section| Some(*section) == line.header || !self.is_folded(*section))
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn move_cursor(&mut self, delta: i64) {
+        let len = self.visible_lines().len();
+        let next = (self.cursor as i64 + delta).clamp(0, len.saturating_sub(1) as i64);
+        self.cursor = next as usize;
+        self.follow = self.cursor + 1 >= len;
+    }
+
+    fn toggle_fold(&mut self) {
+        let visible = self.visible_lines();
+        let Some(section) = visible.get(self.cursor).and_then(|idx| self.lines[*idx].header) else {
+            return;
+        };
+        let folded = self.is_folded(section);
+        self.folded.insert(self.sections[section].name.clone(), !folded);
+    }
+
+    fn toggle_all_folds(&mut self) {
+        let fold = !(0..self.sections.len()).all(|section| self.is_folded(section));
+        for section in &self.sections {
+            self.folded.insert(section.name.clone(), fold);
+        }
+        self.cursor = self.cursor.min(self.visible_lines().len().saturating_sub(1));
+    }
+
+    fn set_trace(&mut self, trace: &str) {
+        let (lines, sections) = parse_job_trace(trace);
+        self.lines = lines;
+        self.sections = sections;
+        let len = self.visible_lines().len();
+        if self.follow {
+            self.cursor = len.saturating_sub(1);
+        } else {
+            self.cursor = self.cursor.min(len.saturating_sub(1));
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TraceLine {
+    text: String,
+    sections: Vec<usize>,
+    header: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TraceSection {
+    name: String,
+    collapsed: bool,
+    duration: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JobAction {
+    Retry,
+    Cancel,
+}
+
+impl JobAction {
+    fn path(self) -> &'static str {
+        match self {
+            JobAction::Retry => "retry",
+            JobAction::Cancel => "cancel",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum View {
+    MergeRequests(MergeRequestView),
+    Issues(IssueView),
+    Pipelines(PipelineView),
+    Refs(RefView),
+    Members(MemberView),
+    Visibility(VisibilityView),
+}
+
+#[derive(Clone, Debug)]
+struct VisibilityView {
+    rows: Vec<VisibilityFinding>,
+    flagged_only: bool,
+    selected: usize,
+}
+
+impl VisibilityView {
+    fn visible_rows(&self) -> Vec<&VisibilityFinding> {
+        self.rows
+            .iter()
+            .filter(|row| !self.flagged_only || !row.flags.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum MemberSource {
+    Direct,
+    Inherited(String),
+    Shared,
+}
+
+impl MemberSource {
+    fn label(&self) -> String {
+        match self {
+            MemberSource::Direct => "direct".to_string(),
+            MemberSource::Inherited(path) => format!("inherited from {path}"),
+            MemberSource::Shared => "shared/invited".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MemberRow {
+    member: GitLabMember,
+    source: MemberSource,
+}
+
+#[derive(Clone, Debug)]
+struct MemberView {
+    scope: ViewScope,
+    path: String,
+    ancestors: Vec<(usize, String)>,
+    filter: String,
+    input: Option<String>,
+    rows: Vec<MemberRow>,
+    selected: usize,
+    state: DetailState,
+}
+
+impl MemberView {
+    fn matches(&self, row: &MemberRow) -> bool {
+        let filter = self.filter.to_lowercase();
+        filter.is_empty()
+            || row.member.username.to_lowercase().contains(&filter)
+            || row.member.name.as_deref().is_some_and(|name| name.to_lowercase().contains(&filter))
+    }
+
+    fn visible_rows(&self) -> Vec<&MemberRow> {
+        self.rows.iter().filter(|row| self.matches(row)).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RefTab {
+    Branches,
+    Tags,
+    Releases,
+}
+
+impl RefTab {
+    fn label(self) -> &'static str {
+        match self {
+            RefTab::Branches => "branches",
+            RefTab::Tags => "tags",
+            RefTab::Releases => "releases",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            RefTab::Branches => RefTab::Tags,
+            RefTab::Tags => RefTab::Releases,
+            RefTab::Releases => RefTab::Branches,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BranchRow {
+    branch: GitLabRef,
+    divergence: Option<(Option<usize>, Option<usize>)>,
+    state: DetailState,
+}
+
+#[derive(Clone, Debug)]
+struct RefView {
+    project_id: usize,
+    path: String,
+    url: String,
+    tab: RefTab,
+    branches: Vec<BranchRow>,
+    tags: Vec<GitLabRef>,
+    releases: Vec<GitLabRelease>,
+    selected: usize,
+    state: DetailState,
+    notes: Option<RefNotes>,
+}
+
+#[derive(Clone, Debug)]
+struct RefNotes {
+    title: String,
+    body: Option<String>,
+    markdown: bool,
+    url: String,
+    scroll: u16,
+}
+
+impl RefView {
+    fn len(&self) -> usize {
+        match self.tab {
+            RefTab::Branches => self.branches.len(),
+            RefTab::Tags => self.tags.len(),
+            RefTab::Releases => self.releases.len(),
+        }
+    }
+
+    /// Requests ahead/behind counts for the selected branch if it has none yet.
+    fn divergence_fetch(&mut self) -> Option<FetchRequest> {
+        if self.tab != RefTab::Branches {
+            return None;
+        }
+        let (default, branch) = self.compare_refs()?;
+        let row = self.branches.get_mut(self.selected)?;
+        if row.state != DetailState::Partial {
+            return None;
+        }
+        row.state = DetailState::Loading;
+        Some(FetchRequest::BranchDivergence { project_id: self.project_id, branch, default })
+    }
+
+    /// Branches compare against the default branch; tags and releases
+    /// against the next older entry in the list.
+    fn compare_refs(&self) -> Option<(String, String)> {
+        match self.tab {
+            RefTab::Branches => {
+                let default = self.branches.iter().find(|row| row.branch.default)?;
+                let branch = &self.branches.get(self.selected)?.branch;
+                (!branch.default).then(|| (default.branch.name.clone(), branch.name.clone()))
+            }
+            RefTab::Tags => {
+                let tag = self.tags.get(self.selected)?;
+                let previous = self.tags.get(self.selected + 1)?;
+                Some((previous.name.clone(), tag.name.clone()))
+            }
+            RefTab::Releases => {
+                let release = self.releases.get(self.selected)?;
+                let previous = self.releases.get(self.selected + 1)?;
+                Some((previous.tag_name.clone(), release.tag_name.clone()))
+            }
+        }
+    }
+
+    fn compare_url(&self) -> Option<String> {
+        let (from, to) = self.compare_refs()?;
+        Some(format!("{}/-/compare/{from}...{to}", self.url.trim_end_matches('/')))
+    }
+
+    fn selected_notes(&self) -> Option<RefNotes> {
+        match self.tab {
+            RefTab::Branches => None,
+            RefTab::Tags => {
+                let tag = self.tags.get(self.selected)?;
+                Some(RefNotes {
+                    title: tag.name.clone(),
+                    body: tag.message.clone().filter(|message| !message.trim().is_empty()),
+                    markdown: false,
+                    url: format!("{}/-/tags/{}", self.url.trim_end_matches('/'), tag.name),
+                    scroll: 0,
+                })
+            }
+            RefTab::Releases => {
+                let release = self.releases.get(self.selected)?;
+                let url = release
+                    .links
+                    .as_ref()
+                    .and_then(|links| links.self_url.clone())
+                    .unwrap_or_else(|| {
+                        let base = self.url.trim_end_matches('/');
+                        format!("{base}/-/releases/{}", release.tag_name)
+                    });
+                Some(RefNotes {
+                    title: release.name.clone().unwrap_or_else(|| release.tag_name.clone()),
+                    body: release.description.clone().filter(|text| !text.trim().is_empty()),
+                    markdown: true,
+                    url,
+                    scroll: 0,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct GroupDetails {
+    group: GitLabGroup,
+    member_count: Option<usize>,
+    access_level: Option<u64>,
+    state: DetailState,
+}
+
+impl GroupDetails {
+    fn from_bulk(group: &GitLabGroup) -> Self {
+        Self {
+            group: group.clone(),
+            member_count: None,
+            access_level: None,
+            state: DetailState::Partial,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GroupStats {
+    direct_subgroups: usize,
+    subgroups: usize,
+    direct_projects: usize,
+    projects: usize,
+    public_projects: usize,
+    repository_size: u64,
+    sized_projects: usize,
+    stars: u64,
+    latest_activity: Option<DateTime<Utc>>,
+    /// Path and raw timestamp of the project behind `latest_activity`.
+    latest_project: Option<(String, String)>,
+}
+
+impl GroupStats {
+    fn add_project(&mut self, node: &Node, project_details: &HashMap<usize, ProjectDetails>) {
+        self.direct_projects += 1;
+        self.projects += 1;
+        if node.visibility == "public" {
+            self.public_projects += 1;
+        }
+        let activity = node.last_activity.as_deref().and_then(parse_timestamp);
+        if activity > self.latest_activity {
+            self.latest_activity = activity;
+            self.latest_project = node
+                .last_activity
+                .as_ref()
+                .map(|activity| (node.path.clone(), activity.clone()));
+        }
+        let project = node
+            .gitlab_id
+            .and_then(|id| project_details.get(&id))
+            .and_then(|details| details.project.as_ref());
+        self.stars += project.and_then(|project| project.star_count).unwrap_or(0);
+        let size = project
+            .and_then(|project| project.statistics.as_ref())
+            .map(|statistics| statistics.repository_size);
+        if let Some(size) = size {
+            self.repository_size += size;
+            self.sized_projects += 1;
+        }
+    }
+
+    /// Adds a child group's totals; its direct counts stay its own.
+    fn merge(&mut self, other: &GroupStats) {
+        self.subgroups += other.subgroups;
+        self.projects += other.projects;
+        self.public_projects += other.public_projects;
+        self.repository_size += other.repository_size;
+        self.sized_projects += other.sized_projects;
+        self.stars += other.stars;
+        if other.latest_activity > self.latest_activity {
+            self.latest_activity = other.latest_activity;
+            self.latest_project = other.latest_project.clone();
+        }
+    }
+}
+
+impl ProjectDetails {
+    fn from_bulk(project: &GitLabProject) -> Self {
+        Self {
+            project: Some(project.clone()),
+            creator: None,
+            state: DetailState::Partial,
+        }
+    }
+}
+
+struct Toast {
+    message: String,
+    remaining: u8,
+}
+
+#[derive(Clone)]
+enum KeyAction {
+    None,
+    Quit,
+    Reload,
+    Fetch(FetchRequest),
+}
+
+struct App {
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    parent: Vec<Option<usize>>,
+    selected: usize,
+    config: Config,
+    status: Option<String>,
+    pending_g: bool,
+    toast: Option<Toast>,
+    search_query: Option<String>,
+    search_mode: bool,
+    show_help: bool,
+    token: Option<TokenInfo>,
+    show_log: bool,
+    show_archived: bool,
+    show_group_stats: bool,
+    project_details: HashMap<usize, ProjectDetails>,
+    group_details: HashMap<usize, GroupDetails>,
+    group_stats: HashMap<usize, GroupStats>,
+    readmes: HashMap<usize, ProjectReadme>,
+    repo_trees: HashMap<usize, DetailState>,
+    repo_refs: HashMap<usize, String>,
+    pager: Option<FilePager>,
+    ref_picker: Option<RefPicker>,
+    view: Option<View>,
+    pipeline_badges: HashMap<usize, PipelineBadge>,
+}
+
+impl App {
+    const TOAST_TTL: u8 = 10;
+
+    fn load_failed(config: Config, status: String) -> Self {
+        Self::from_gitlab_data(Vec::new(), Vec::new(), None, config, status)
+    }
+
+    fn from_gitlab(api: &HttpGitLabApi) -> Result<Self> {
+        Self::from_api(api, api.config.clone())
+    }
+
+    fn from_api(api: &dyn GitLabApi, config: Config) -> Result<Self> {
+        let token = fetch_token_info(api).ok();
+        let mut app = Self::load_tree(api, config)?;
+        app.token = token;
+        Ok(app)
+    }
+
+    fn load_tree(api: &dyn GitLabApi, config: Config) -> Result<Self> {
+        let cache = CacheStore::new(config.cache_path.clone(), config.cache_ttl);
+        if let Some(cache) = cache.load()? {
+            config.log.record(
+                LogLevel::Info,
+                "cache",
+                format!("cache hit: {}", config.cache_path.display()),
+            );
+            let total_projects: usize =
+                cache.projects_by_group.iter().map(|entry| entry.projects.len()).sum();
+            let personal_count = cache.personal.as_ref().map(|entry| entry.projects.len()).unwrap_or(0);
+            let status = format!(
+                "cache hit | groups: {}, projects: {}, personal: {}",
+                cache.groups.len(),
+                total_projects,
+                personal_count
+            );
+            return Ok(Self::from_gitlab_data(
+                cache.groups,
+                cache.projects_by_group,
+                cache.personal,
+                config,
+                status,
+            ));
+        }
+
+        config.log.record(
+            LogLevel::Info,
+            "cache",
+            format!("cache miss: {}", config.cache_path.display()),
+        );
+        let (groups, projects, personal) = match config.loader {
+            LoaderKind::Rest => {
+                let groups = api.groups()?;
+                let projects = fetch_projects_by_group(api, &config, &groups);
+                let personal = fetch_personal_projects(api, &config).ok();
+                (groups, projects, personal)
+            }
+            LoaderKind::GraphQl => load_graphql(api, &config)?,
+        };
+        let total_projects: usize = projects.iter().map(|entry| entry.projects.len()).sum();
+        let personal_count = personal.as_ref().map(|entry| entry.projects.len()).unwrap_or(0);
+        let failed_groups = projects.iter().filter(|entry| entry.error.is_some()).count();
+        let mut status = format!(
+            "groups: {}, projects: {}, personal: {}",
+            groups.len(),
+            total_projects,
+            personal_count
+        );
+        if failed_groups > 0 {
+            status.push_str(&format!(", failed groups: {failed_groups}"));
+        }
+        if config.loader == LoaderKind::GraphQl {
+            status.push_str(", graphql: no forks/mirrors/shares");
+        }
+        for entry in &projects {
+            if let Some(error) = &entry.error {
+                config.log.record(
+                    LogLevel::Error,
+                    "load",
+                    format!("group {} projects failed: {error}", entry.group_id),
+                );
+            }
+        }
+        let cache_data = CacheData {
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            groups: groups.clone(),
+            projects_by_group: projects.clone(),
+            personal: personal.clone(),
+        };
+        if failed_groups == 0
+            && let Err(err) = cache.store(&cache_data)
+        {
+            config.log.record(LogLevel::Warn, "cache", format!("cache write failed: {err}"));
+        }
+        Ok(Self::from_gitlab_data(
+            groups,
+            projects,
+            personal,
+            config,
+            status,
+        ))
+    }
+
+    fn from_gitlab_data(
+        groups: Vec<GitLabGroup>,
+        projects_by_group: Vec<GroupProjects>,
+        personal: Option<PersonalProjects>,
+        config: Config,
+        status: String,
+    ) -> Self {
+        let mut nodes = Vec::new();
+        let mut id_to_node = HashMap::new();
+        let mut project_details = HashMap::new();
+        let group_details: HashMap<usize, GroupDetails> = groups
+            .iter()
+            .map(|group| (group.id, GroupDetails::from_bulk(group)))
+            .collect();
+        for group in &groups {
+            let node_id = push_node(
+                &mut nodes,
+                &group.name,
+                NodeKind::Group,
+                &group.web_url,
+                &group.full_path,
+                &group.visibility,
+                None,
+            );
+            nodes[node_id].gitlab_id = Some(group.id);
+            id_to_node.insert(group.id, node_id);
+        }
+
+        let mut roots = Vec::new();
+        for group in &groups {
+            let child_id = match id_to_node.get(&group.id) {
+                Some(id) => *id,
+                None => continue,
+            };
+            if let Some(parent_id) = group.parent_id
+                && let Some(parent_node) = id_to_node.get(&parent_id)
+            {
+                nodes[*parent_node].children.push(child_id);
+                continue;
+            }
+            roots.push(child_id);
+        }
+
+        for entry in projects_by_group {
+            let Some(parent_node) = id_to_node.get(&entry.group_id).copied() else {
+                continue;
+            };
+            nodes[parent_node].error = entry.error;
+            for project in entry.projects {
+                project_details.insert(project.id, ProjectDetails::from_bulk(&project));
+                let project_node = push_project_node(&mut nodes, &project);
+                nodes[parent_node].children.push(project_node);
+            }
+            if entry.shared_projects.is_empty() && entry.shared_groups.is_empty() {
+                continue;
+            }
+            let parent = nodes[parent_node].clone();
+            let heading = push_node(
+                &mut nodes,
+                "shared",
+                NodeKind::Shared,
+                &parent.url,
+                &format!("{} (shared)", parent.path),
+                &parent.visibility,
+                None,
+            );
+            nodes[parent_node].children.push(heading);
+            for group in entry.shared_groups {
+                let group_node = push_node(
+                    &mut nodes,
+                    &group.name,
+                    NodeKind::Group,
+                    &group.web_url,
+                    &group.full_path,
+                    &group.visibility,
+                    None,
+                );
+                nodes[group_node].gitlab_id = Some(group.id);
+                nodes[group_node].relation.shared = true;
+                nodes[heading].children.push(group_node);
+            }
+            for project in entry.shared_projects {
+                project_details
+                    .entry(project.id)
+                    .or_insert_with(|| ProjectDetails::from_bulk(&project));
+                let project_node = push_project_node(&mut nodes, &project);
+                nodes[project_node].relation.shared = true;
+                nodes[heading].children.push(project_node);
+            }
+        }
+
+        if let Some(personal) = personal {
+            let root = push_node(
+                &mut nodes,
+                &personal.username,
+                NodeKind::Group,
+                &personal.web_url,
+                &personal.username,
+                "private",
+                None,
+            );
+            for project in personal.projects {
+                project_details.insert(project.id, ProjectDetails::from_bulk(&project));
+                let project_node = push_project_node(&mut nodes, &project);
+                nodes[root].children.push(project_node);
+            }
+            roots.push(root);
+        }
+
+        let group_stats = rollup_group_stats(&nodes, &project_details);
+        apply_sorting(
+            &mut nodes,
+            &mut roots,
+            &config,
+            &project_details,
+            &group_details,
+            &group_stats,
+        );
+
+        for &root in &roots {
+            nodes[root].expanded = true;
+        }
+
+        let parent = build_parent_map(&nodes);
+        let show_archived = config.show_archived;
+        let show_group_stats = config.show_group_stats;
+
+        Self {
+            nodes,
+            roots,
+            parent,
+            selected: 0,
+            config,
+            status: Some(status),
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived,
+            show_group_stats,
+            project_details,
+            group_details,
+            group_stats,
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        }
+    }
+
+    fn visible_nodes(&self) -> Vec<VisibleNode> {
+        let mut out = Vec::new();
+        for &root in &self.roots {
+            self.walk_visible(root, 0, &mut out);
+        }
+        if let Some(query) = &self.search_query {
+            filter_visible_nodes(&out, &self.nodes, query)
+        } else {
+            out
+        }
+    }
+
+    fn walk_visible(&self, node_id: usize, depth: usize, out: &mut Vec<VisibleNode>) {
+        out.push(VisibleNode { id: node_id, depth });
+        let node = &self.nodes[node_id];
+        if node.expanded {
+            for &child in &node.children {
+                if self.nodes[child].archived && !self.show_archived {
+                    continue;
+                }
+                self.walk_visible(child, depth + 1, out);
+            }
+        }
+    }
+
+    fn ensure_selection(&mut self, visible_len: usize) {
+        if visible_len == 0 {
+            self.selected = 0;
+        } else if self.selected >= visible_len {
+            self.selected = visible_len - 1;
+        }
+    }
+
+    fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+        }
+    }
+
+    fn move_down(&mut self, visible_len: usize) {
+        if self.selected + 1 < visible_len {
+            self.selected += 1;
+        }
+    }
+
+    fn move_top(&mut self) {
+        self.selected = 0;
+    }
+
+    fn move_bottom(&mut self, visible_len: usize) {
+        if visible_len > 0 {
+            self.selected = visible_len - 1;
+        }
+    }
+
+    fn collapse_or_parent(&mut self, visible: &[VisibleNode]) {
+        if visible.is_empty() {
+            return;
+        }
+        let node_id = visible[self.selected].id;
+        if self.nodes[node_id].expanded {
+            self.nodes[node_id].expanded = false;
+        } else if let Some(parent) = self.parent[node_id] {
+            self.select_node(parent, visible);
+        }
+        self.ensure_selection(self.visible_nodes().len());
+    }
+
+    fn expand_or_child(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        if visible.is_empty() {
+            return KeyAction::None;
+        }
+        let node_id = visible[self.selected].id;
+        if let Some(request) = self.open_repo_entry(node_id) {
+            return KeyAction::Fetch(request);
+        }
+        if self.nodes[node_id].children.is_empty() {
+            return KeyAction::None;
+        }
+        if !self.nodes[node_id].expanded {
+            self.nodes[node_id].expanded = true;
+        } else {
+            let child = self.nodes[node_id].children[0];
+            self.select_node(child, visible);
+        }
+        self.ensure_selection(self.visible_nodes().len());
+        KeyAction::None
+    }
+
+    fn repo_ref(&self, project_id: usize) -> String {
+        if let Some(ref_name) = self.repo_refs.get(&project_id) {
+            return ref_name.clone();
+        }
+        self.project_details
+            .get(&project_id)
+            .and_then(|details| details.project.as_ref())
+            .and_then(|project| project.default_branch.clone())
+            .unwrap_or_else(|| "HEAD".to_string())
+    }
+
+    fn repo_project_node(&self, node_id: usize) -> Option<usize> {
+        let mut current = node_id;
+        loop {
+            match self.nodes[current].kind {
+                NodeKind::Project => return Some(current),
+                NodeKind::Directory | NodeKind::File => current = self.parent[current]?,
+                NodeKind::Group | NodeKind::Shared => return None,
+            }
+        }
+    }
+
+    fn open_repo_entry(&mut self, node_id: usize) -> Option<FetchRequest> {
+        let node = &self.nodes[node_id];
+        let (project_id, path) = match (node.kind, &node.repo) {
+            (NodeKind::Project, _) => (node.gitlab_id?, String::new()),
+            (NodeKind::Directory | NodeKind::File, Some(entry)) => {
+                (entry.project_id, entry.path.clone())
+            }
+            _ => return None,
+        };
+        let ref_name = self.repo_ref(project_id);
+        if node.kind == NodeKind::File {
+            let project_node = self.repo_project_node(node_id)?;
+            self.pager = Some(FilePager {
+                project_id,
+                path: path.clone(),
+                ref_name: ref_name.clone(),
+                project_url: self.nodes[project_node].url.clone(),
+                content: None,
+                commit: None,
+                state: DetailState::Loading,
+                scroll: 0,
+            });
+            return Some(FetchRequest::RepoFile { project_id, path, ref_name });
+        }
+        if self.repo_trees.contains_key(&node_id) {
+            return None;
+        }
+        self.repo_trees.insert(node_id, DetailState::Loading);
+        self.set_status(format!("loading {}", node.path));
+        Some(FetchRequest::RepoTree { node_id, project_id, path, ref_name })
+    }
+
+    fn open_ref_picker(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let Some(node_id) = visible.get(self.selected).map(|item| item.id) else {
+            return KeyAction::None;
+        };
+        let Some(project_node) = self.repo_project_node(node_id) else {
+            self.set_status("select a project to switch branch/tag".to_string());
+            return KeyAction::None;
+        };
+        let Some(project_id) = self.nodes[project_node].gitlab_id else {
+            return KeyAction::None;
+        };
+        self.ref_picker = Some(RefPicker {
+            project_id,
+            node_id: project_node,
+            refs: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+        });
+        KeyAction::Fetch(FetchRequest::Refs { project_id })
+    }
+
+    fn switch_ref(
+        &mut self,
+        project_node: usize,
+        project_id: usize,
+        ref_name: String,
+    ) -> KeyAction {
+        self.repo_refs.insert(project_id, ref_name.clone());
+        let project_node = self.remove_descendants(project_node);
+        self.nodes[project_node].expanded = false;
+        self.repo_trees.remove(&project_node);
+        let visible = self.visible_nodes();
+        self.select_node(project_node, &visible);
+        self.set_status(format!("switched {} to {ref_name}", self.nodes[project_node].path));
+        match self.open_repo_entry(project_node) {
+            Some(request) => KeyAction::Fetch(request),
+            None => KeyAction::None,
+        }
+    }
+
+    /// Drops every descendant of `node_id`, compacting `nodes` and renumbering
+    /// the ids held by the tree and `repo_trees`. Returns the node's new id.
+    fn remove_descendants(&mut self, node_id: usize) -> usize {
+        let mut removed = vec![false; self.nodes.len()];
+        let mut stack = self.nodes[node_id].children.clone();
+        while let Some(id) = stack.pop() {
+            removed[id] = true;
+            stack.extend_from_slice(&self.nodes[id].children);
+        }
+        let mut remap = vec![None; self.nodes.len()];
+        let mut next = 0;
+        for (id, gone) in removed.iter().enumerate() {
+            if !gone {
+                remap[id] = Some(next);
+                next += 1;
+            }
+        }
+        let mut index = 0;
+        self.nodes.retain(|_| {
+            index += 1;
+            !removed[index - 1]
+        });
+        for node in &mut self.nodes {
+            node.children = node.children.iter().filter_map(|child| remap[*child]).collect();
+        }
+        self.roots = self.roots.iter().filter_map(|root| remap[*root]).collect();
+        // A tree still loading under an old id cannot be matched to its node
+        // any more; dropping it lets the next expand request it again.
+        self.repo_trees = std::mem::take(&mut self.repo_trees)
+            .into_iter()
+            .filter_map(|(id, state)| {
+                let new_id = remap[id]?;
+                (new_id == id || state != DetailState::Loading).then_some((new_id, state))
+            })
+            .collect();
+        self.parent = build_parent_map(&self.nodes);
+        self.refresh_group_stats();
+        remap[node_id].unwrap_or(node_id)
+    }
+
+    /// Whether a finished tree fetch still belongs to the node it was made
+    /// for: ref switches remove and renumber repository nodes.
+    fn repo_tree_pending(
+        &self,
+        node_id: usize,
+        project_id: usize,
+        path: &str,
+        ref_name: &str,
+    ) -> bool {
+        let Some(node) = self.nodes.get(node_id) else {
+            return false;
+        };
+        let same_entry = match &node.repo {
+            Some(entry) => entry.project_id == project_id && entry.path == path,
+            None => node.gitlab_id == Some(project_id) && path.is_empty(),
+        };
+        same_entry
+            && self.repo_trees.get(&node_id) == Some(&DetailState::Loading)
+            && self.repo_ref(project_id) == ref_name
+    }
+
+    fn apply_repo_tree(
+        &mut self,
+        node_id: usize,
+        project_id: usize,
+        entries: Vec<GitLabTreeEntry>,
+    ) {
+        let ref_name = self.repo_ref(project_id);
+        let Some(project_node) = self.repo_project_node(node_id) else {
+            return;
+        };
+        let project_url = self.nodes[project_node].url.clone();
+        let project_path = self.nodes[project_node].path.clone();
+        let visibility = self.nodes[project_node].visibility.clone();
+        let count = entries.len();
+        for entry in entries {
+            let (kind, route) = match entry.kind.as_str() {
+                "tree" => (NodeKind::Directory, "tree"),
+                "blob" => (NodeKind::File, "blob"),
+                _ => continue,
+            };
+            let child = push_node(
+                &mut self.nodes,
+                &entry.name,
+                kind,
+                &format!("{project_url}/-/{route}/{ref_name}/{}", entry.path),
+                &format!("{project_path}/{}", entry.path),
+                &visibility,
+                None,
+            );
+            self.nodes[child].repo = Some(RepoEntry {
+                project_id,
+                path: entry.path,
+            });
+            self.nodes[node_id].children.push(child);
+        }
+        self.repo_trees.insert(node_id, DetailState::Loaded);
+        self.nodes[node_id].expanded = true;
+        self.resort();
+        self.parent = build_parent_map(&self.nodes);
+        self.set_status(format!("loaded {count} entries for {}", self.nodes[node_id].path));
+    }
+
+    fn handle_pager_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) {
+        let Some(pager) = self.pager.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.pager = None,
+            KeyCode::Char('j') | KeyCode::Down => pager.scroll_by(1),
+            KeyCode::Char('k') | KeyCode::Up => pager.scroll_by(-1),
+            KeyCode::PageDown => pager.scroll_by(20),
+            KeyCode::PageUp => pager.scroll_by(-20),
+            KeyCode::Char('g') => pager.scroll = 0,
+            KeyCode::Char('G') => pager.scroll_by(i32::MAX / 2),
+            KeyCode::Char('y') => {
+                let url = pager.permalink();
+                self.copy_url(url, clipboard, "Copied permalink");
+            }
+            KeyCode::Char('o') => {
+                let url = pager.permalink();
+                self.open_url(&url, browser);
+            }
+            _ => {}
+        }
+    }
+
+    fn copy_url(&mut self, url: String, clipboard: Option<&mut dyn ClipboardSink>, toast: &str) {
+        let result = match clipboard {
+            Some(clipboard) => clipboard.set_text(url.clone()),
+            None => Err(anyhow::anyhow!("clipboard unavailable")),
+        };
+        match result {
+            Ok(()) => {
+                self.set_status(format!("copied {url}"));
+                self.set_toast(toast.to_string());
+            }
+            Err(err) => {
+                self.config.log.record(LogLevel::Error, "clipboard", format!("copy failed: {err}"));
+                self.set_status(format!("copy failed: {err}"));
+            }
+        }
+    }
+
+    fn open_url(&mut self, url: &str, browser: &mut dyn BrowserOpener) {
+        match browser.open(url) {
+            Ok(()) => self.set_status(format!("opened {url}")),
+            Err(err) => {
+                self.config.log.record(LogLevel::Error, "browser", format!("open failed: {err}"));
+                self.set_status(format!("open failed: {err}"));
+            }
+        }
+    }
+
+    fn selected_scope(&self, visible: &[VisibleNode]) -> Option<(ViewScope, String)> {
+        let node_id = visible.get(self.selected)?.id;
+        let node = &self.nodes[node_id];
+        match node.kind {
+            NodeKind::Group => Some((ViewScope::Group(node.gitlab_id?), node.path.clone())),
+            NodeKind::Project | NodeKind::Directory | NodeKind::File => {
+                let project = &self.nodes[self.repo_project_node(node_id)?];
+                Some((ViewScope::Project(project.gitlab_id?), project.path.clone()))
+            }
+            NodeKind::Shared => None,
+        }
+    }
+
+    fn open_merge_requests(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let Some((scope, path)) = self.selected_scope(visible) else {
+            self.set_status("select a group or project to list merge requests".to_string());
+            return KeyAction::None;
+        };
+        let filter = MergeRequestFilter::All;
+        self.view = Some(View::MergeRequests(MergeRequestView {
+            scope,
+            path,
+            filter,
+            rows: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+        }));
+        let username = self.token.as_ref().map(|token| token.username.clone());
+        KeyAction::Fetch(FetchRequest::MergeRequests { scope, filter, username })
+    }
+
+    fn handle_view_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        match self.view {
+            Some(View::MergeRequests(_)) => self.handle_merge_request_key(key, clipboard, browser),
+            Some(View::Issues(_)) => self.handle_issue_key(key, clipboard, browser),
+            Some(View::Pipelines(_)) => self.handle_pipeline_key(key, clipboard, browser),
+            Some(View::Refs(_)) => self.handle_refs_key(key, clipboard, browser),
+            Some(View::Members(_)) => self.handle_members_key(key),
+            Some(View::Visibility(_)) => self.handle_visibility_key(key),
+            None => KeyAction::None,
+        }
+    }
+
+    fn open_issues(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let Some((scope, path)) = self.selected_scope(visible) else {
+            self.set_status("select a group or project to list issues".to_string());
+            return KeyAction::None;
+        };
+        let query = "state:opened".to_string();
+        self.view = Some(View::Issues(IssueView {
+            scope,
+            path,
+            query: query.clone(),
+            input: None,
+            rows: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+            detail: None,
+        }));
+        KeyAction::Fetch(FetchRequest::Issues { scope, query })
+    }
+
+    fn handle_issue_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        let Some(View::Issues(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        if let Some(input) = view.input.as_mut() {
+            match key.code {
+                KeyCode::Esc => view.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(ch) => input.push(ch),
+                KeyCode::Enter => {
+                    let query = view.input.take().unwrap_or_default();
+                    if let Err(err) = parse_issue_query(&query) {
+                        self.set_status(format!("invalid query: {err}"));
+                        return KeyAction::None;
+                    }
+                    view.query = query.clone();
+                    view.state = DetailState::Loading;
+                    view.selected = 0;
+                    return KeyAction::Fetch(FetchRequest::Issues { scope: view.scope, query });
+                }
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+        if let Some(detail) = view.detail.as_mut() {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => view.detail = None,
+                KeyCode::Char('j') | KeyCode::Down => {
+                    detail.scroll = detail.scroll.saturating_add(1);
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    detail.scroll = detail.scroll.saturating_sub(1);
+                }
+                KeyCode::PageDown => detail.scroll = detail.scroll.saturating_add(20),
+                KeyCode::PageUp => detail.scroll = detail.scroll.saturating_sub(20),
+                KeyCode::Char('g') => detail.scroll = 0,
+                KeyCode::Char('y') => {
+                    let url = detail.issue.web_url.clone();
+                    self.copy_url(url, clipboard, "Copied URL");
+                }
+                KeyCode::Char('o') => {
+                    let url = detail.issue.web_url.clone();
+                    self.open_url(&url, browser);
+                }
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < view.rows.len() => {
+                view.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = view.rows.len().saturating_sub(1),
+            KeyCode::Char('/') => view.input = Some(view.query.clone()),
+            KeyCode::Char('r') => {
+                view.state = DetailState::Loading;
+                return KeyAction::Fetch(FetchRequest::Issues {
+                    scope: view.scope,
+                    query: view.query.clone(),
+                });
+            }
+            KeyCode::Enter => {
+                if let Some(issue) = view.rows.get(view.selected) {
+                    let (project_id, iid) = (issue.project_id, issue.iid);
+                    view.detail = Some(Box::new(IssueDetail {
+                        issue: issue.clone(),
+                        notes: Vec::new(),
+                        state: DetailState::Loading,
+                        scroll: 0,
+                    }));
+                    return KeyAction::Fetch(FetchRequest::IssueNotes { project_id, iid });
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Some(issue) = view.rows.get(view.selected) {
+                    let url = issue.web_url.clone();
+                    self.copy_url(url, clipboard, "Copied URL");
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(issue) = view.rows.get(view.selected) {
+                    let url = issue.web_url.clone();
+                    self.open_url(&url, browser);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    /// Ancestor groups of a node from the loaded tree, nearest first.
+    fn ancestor_groups(&self, node_id: usize) -> Vec<(usize, String)> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent[node_id];
+        while let Some(id) = current {
+            let node = &self.nodes[id];
+            if node.kind == NodeKind::Group
+                && !node.relation.shared
+                && let Some(group_id) = node.gitlab_id
+            {
+                ancestors.push((group_id, node.path.clone()));
+            }
+            current = self.parent[id];
+        }
+        ancestors
+    }
+
+    fn open_members(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let Some((scope, path)) = self.selected_scope(visible) else {
+            self.set_status("select a group or project to list members".to_string());
+            return KeyAction::None;
+        };
+        let node_id = visible[self.selected].id;
+        let node_id = match scope {
+            ViewScope::Project(_) => self.repo_project_node(node_id).unwrap_or(node_id),
+            ViewScope::Group(_) => node_id,
+        };
+        let ancestors = self.ancestor_groups(node_id);
+        self.view = Some(View::Members(MemberView {
+            scope,
+            path,
+            ancestors: ancestors.clone(),
+            filter: String::new(),
+            input: None,
+            rows: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+        }));
+        KeyAction::Fetch(FetchRequest::Members { scope, ancestors })
+    }
+
+    fn handle_members_key(&mut self, key: KeyEvent) -> KeyAction {
+        let Some(View::Members(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        if let Some(input) = view.input.as_mut() {
+            match key.code {
+                KeyCode::Esc => view.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(ch) => input.push(ch),
+                KeyCode::Enter => {
+                    view.filter = view.input.take().unwrap_or_default();
+                    view.selected = 0;
+                }
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+        let len = view.visible_rows().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < len => view.selected += 1,
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = len.saturating_sub(1),
+            KeyCode::Char('/') => view.input = Some(view.filter.clone()),
+            KeyCode::Char('r') => {
+                view.state = DetailState::Loading;
+                let ancestors = view.ancestors.clone();
+                return KeyAction::Fetch(FetchRequest::Members { scope: view.scope, ancestors });
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn handle_visibility_key(&mut self, key: KeyEvent) -> KeyAction {
+        let Some(View::Visibility(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        let len = view.visible_rows().len();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < len => view.selected += 1,
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = len.saturating_sub(1),
+            KeyCode::Char('f') => {
+                view.flagged_only = !view.flagged_only;
+                view.selected = 0;
+            }
+            KeyCode::Enter => {
+                let target = view.visible_rows().get(view.selected).map(|row| row.node_id);
+                if let Some(target) = target {
+                    self.view = None;
+                    self.reveal_node(target);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn open_refs(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let project_node = visible
+            .get(self.selected)
+            .and_then(|item| self.repo_project_node(item.id));
+        let Some(project) = project_node.map(|node_id| &self.nodes[node_id]) else {
+            self.set_status("select a project to list branches, tags and releases".to_string());
+            return KeyAction::None;
+        };
+        let Some(project_id) = project.gitlab_id else {
+            return KeyAction::None;
+        };
+        let tab = RefTab::Branches;
+        self.view = Some(View::Refs(RefView {
+            project_id,
+            path: project.path.clone(),
+            url: project.url.clone(),
+            tab,
+            branches: Vec::new(),
+            tags: Vec::new(),
+            releases: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+            notes: None,
+        }));
+        KeyAction::Fetch(FetchRequest::RefList { project_id, tab })
+    }
+
+    fn handle_refs_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        let Some(View::Refs(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        if let Some(notes) = view.notes.as_mut() {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => view.notes = None,
+                KeyCode::Char('j') | KeyCode::Down => notes.scroll = notes.scroll.saturating_add(1),
+                KeyCode::Char('k') | KeyCode::Up => notes.scroll = notes.scroll.saturating_sub(1),
+                KeyCode::PageDown => notes.scroll = notes.scroll.saturating_add(20),
+                KeyCode::PageUp => notes.scroll = notes.scroll.saturating_sub(20),
+                KeyCode::Char('g') => notes.scroll = 0,
+                KeyCode::Char('y') => {
+                    let url = notes.url.clone();
+                    self.copy_url(url, clipboard, "Copied URL");
+                }
+                KeyCode::Char('o') => {
+                    let url = notes.url.clone();
+                    self.open_url(&url, browser);
+                }
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < view.len() => {
+                view.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = view.len().saturating_sub(1),
+            KeyCode::Tab | KeyCode::Char('r') => {
+                if key.code == KeyCode::Tab {
+                    view.tab = view.tab.next();
+                    view.selected = 0;
+                }
+                view.state = DetailState::Loading;
+                let (project_id, tab) = (view.project_id, view.tab);
+                return KeyAction::Fetch(FetchRequest::RefList { project_id, tab });
+            }
+            KeyCode::Enter => view.notes = view.selected_notes(),
+            KeyCode::Char('y') | KeyCode::Char('o') => {
+                let Some(url) = view.compare_url() else {
+                    let reason = match view.tab {
+                        RefTab::Branches => "nothing to compare with the default branch",
+                        RefTab::Tags | RefTab::Releases => "no earlier tag to compare with",
+                    };
+                    self.set_status(reason.to_string());
+                    return KeyAction::None;
+                };
+                if key.code == KeyCode::Char('y') {
+                    self.copy_url(url, clipboard, "Copied compare URL");
+                } else {
+                    self.open_url(&url, browser);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn open_pipelines(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        let Some((ViewScope::Project(project_id), path)) = self.selected_scope(visible) else {
+            self.set_status("select a project to list pipelines".to_string());
+            return KeyAction::None;
+        };
+        self.view = Some(View::Pipelines(PipelineView {
+            project_id,
+            path,
+            rows: Vec::new(),
+            selected: 0,
+            state: DetailState::Loading,
+            jobs: None,
+            detailed: HashSet::new(),
+        }));
+        KeyAction::Fetch(FetchRequest::Pipelines { project_id })
+    }
+
+    fn handle_pipeline_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        let Some(View::Pipelines(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        if view.jobs.is_some() {
+            return self.handle_jobs_key(key, clipboard, browser);
+        }
+        match key.code {
+            KeyCode::Enter => {
+                if let Some(pipeline) = view.rows.get(view.selected) {
+                    let pipeline_id = pipeline.id;
+                    view.jobs = Some(Box::new(JobsPanel {
+                        pipeline_id,
+                        rows: Vec::new(),
+                        selected: 0,
+                        state: DetailState::Loading,
+                        log: None,
+                    }));
+                    let project_id = view.project_id;
+                    return KeyAction::Fetch(FetchRequest::PipelineJobs { project_id, pipeline_id });
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < view.rows.len() => {
+                view.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = view.rows.len().saturating_sub(1),
+            KeyCode::Char('r') => {
+                view.state = DetailState::Loading;
+                return KeyAction::Fetch(FetchRequest::Pipelines { project_id: view.project_id });
+            }
+            KeyCode::Char('y') => {
+                if let Some(pipeline) = view.rows.get(view.selected) {
+                    let url = pipeline.web_url.clone();
+                    self.copy_url(url, clipboard, "Copied URL");
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(pipeline) = view.rows.get(view.selected) {
+                    let url = pipeline.web_url.clone();
+                    self.open_url(&url, browser);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    /// Warning for an action the loaded token lacks the scopes for; `None`
+    /// when the token is allowed or its scopes are unknown.
+    fn scope_warning(&self, action: TokenAction) -> Option<String> {
+        let token = self.token.as_ref()?;
+        (!token.has_any_scope(action.required_scopes())).then(|| action.missing_scopes())
+    }
+
+    fn job_action(&mut self, project_id: usize, job_id: usize, action: JobAction) -> KeyAction {
+        if let Some(warning) = self.scope_warning(TokenAction::ManageJobs) {
+            self.set_status(warning);
+            return KeyAction::None;
+        }
+        self.set_status(format!("{} job {job_id}", action.path()));
+        KeyAction::Fetch(FetchRequest::JobAction { project_id, job_id, action })
+    }
+
+    fn handle_jobs_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        let Some(View::Pipelines(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        let project_id = view.project_id;
+        let Some(jobs) = view.jobs.as_mut() else {
+            return KeyAction::None;
+        };
+        if let Some(log) = jobs.log.as_mut() {
+            let job = log.job.clone();
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => jobs.log = None,
+                KeyCode::Char('j') | KeyCode::Down => log.move_cursor(1),
+                KeyCode::Char('k') | KeyCode::Up => log.move_cursor(-1),
+                KeyCode::PageDown => log.move_cursor(20),
+                KeyCode::PageUp => log.move_cursor(-20),
+                KeyCode::Char('g') => log.move_cursor(i64::MIN / 2),
+                KeyCode::Char('G') => log.move_cursor(i64::MAX / 2),
+                KeyCode::Enter | KeyCode::Char(' ') => log.toggle_fold(),
+                KeyCode::Char('z') => log.toggle_all_folds(),
+                KeyCode::Char('f') => {
+                    log.follow = !log.follow;
+                    if log.follow {
+                        log.move_cursor(i64::MAX / 2);
+                    }
+                }
+                KeyCode::Char('R') => return self.job_action(project_id, job.id, JobAction::Retry),
+                KeyCode::Char('C') => {
+                    return self.job_action(project_id, job.id, JobAction::Cancel);
+                }
+                KeyCode::Char('y') => self.copy_url(job.web_url, clipboard, "Copied URL"),
+                KeyCode::Char('o') => self.open_url(&job.web_url, browser),
+                _ => {}
+            }
+            return KeyAction::None;
+        }
+        let selected = jobs.rows.get(jobs.selected).cloned();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => view.jobs = None,
+            KeyCode::Char('j') | KeyCode::Down if jobs.selected + 1 < jobs.rows.len() => {
+                jobs.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => jobs.selected = jobs.selected.saturating_sub(1),
+            KeyCode::Char('r') => {
+                jobs.state = DetailState::Loading;
+                let pipeline_id = jobs.pipeline_id;
+                return KeyAction::Fetch(FetchRequest::PipelineJobs { project_id, pipeline_id });
+            }
+            KeyCode::Enter => {
+                if let Some(job) = selected {
+                    let job_id = job.id;
+                    jobs.log = Some(JobLog {
+                        job,
+                        lines: Vec::new(),
+                        sections: Vec::new(),
+                        folded: HashMap::new(),
+                        cursor: 0,
+                        follow: true,
+                        state: DetailState::Loading,
+                        fetched_at: None,
+                    });
+                    return KeyAction::Fetch(FetchRequest::JobTrace { project_id, job_id });
+                }
+            }
+            KeyCode::Char('R') => {
+                if let Some(job) = selected {
+                    return self.job_action(project_id, job.id, JobAction::Retry);
+                }
+            }
+            KeyCode::Char('C') => {
+                if let Some(job) = selected {
+                    return self.job_action(project_id, job.id, JobAction::Cancel);
+                }
+            }
+            KeyCode::Char('y') => {
+                if let Some(job) = selected {
+                    self.copy_url(job.web_url, clipboard, "Copied URL");
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(job) = selected {
+                    self.open_url(&job.web_url, browser);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn poll_fetch(&mut self, now: Instant) -> Option<FetchRequest> {
+        let view = match self.view.as_mut() {
+            Some(View::Pipelines(view)) => view,
+            Some(View::Refs(view)) => return view.divergence_fetch(),
+            Some(View::MergeRequests(view)) => return view.detail_fetch(),
+            _ => return None,
+        };
+        let Some(jobs) = view.jobs.as_mut() else {
+            return view.detail_fetch();
+        };
+        if jobs.state == DetailState::Partial {
+            jobs.state = DetailState::Loading;
+            let pipeline_id = jobs.pipeline_id;
+            return Some(FetchRequest::PipelineJobs { project_id: view.project_id, pipeline_id });
+        }
+        let log = jobs.log.as_mut()?;
+        if !log.is_live() || log.state == DetailState::Loading {
+            return None;
+        }
+        let due = log
+            .fetched_at
+            .is_none_or(|fetched| now.duration_since(fetched) >= JOB_TAIL_INTERVAL);
+        if !due {
+            return None;
+        }
+        log.state = DetailState::Loading;
+        Some(FetchRequest::JobTrace { project_id: view.project_id, job_id: log.job.id })
+    }
+
+    fn handle_merge_request_key(
+        &mut self,
+        key: KeyEvent,
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> KeyAction {
+        let Some(View::MergeRequests(view)) = self.view.as_mut() else {
+            return KeyAction::None;
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.view = None,
+            KeyCode::Char('j') | KeyCode::Down if view.selected + 1 < view.rows.len() => {
+                view.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Char('g') => view.selected = 0,
+            KeyCode::Char('G') => view.selected = view.rows.len().saturating_sub(1),
+            KeyCode::Char('f') | KeyCode::Char('r') => {
+                if key.code == KeyCode::Char('f') {
+                    view.filter = view.filter.next();
+                }
+                view.state = DetailState::Loading;
+                view.selected = 0;
+                return KeyAction::Fetch(FetchRequest::MergeRequests {
+                    scope: view.scope,
+                    filter: view.filter,
+                    username: self.token.as_ref().map(|token| token.username.clone()),
+                });
+            }
+            KeyCode::Char('y') => {
+                if let Some(row) = view.rows.get(view.selected) {
+                    let url = row.merge_request.web_url.clone();
+                    self.copy_url(url, clipboard, "Copied URL");
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some(row) = view.rows.get(view.selected) {
+                    let url = row.merge_request.web_url.clone();
+                    self.open_url(&url, browser);
+                }
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn handle_ref_picker_key(&mut self, key: KeyEvent) -> KeyAction {
+        let Some(picker) = self.ref_picker.as_mut() else {
+            return KeyAction::None;
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.ref_picker = None,
+            KeyCode::Char('j') | KeyCode::Down if picker.selected + 1 < picker.refs.len() => {
+                picker.selected += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                picker.selected = picker.selected.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                let Some((_, ref_name)) = picker.refs.get(picker.selected).cloned() else {
+                    return KeyAction::None;
+                };
+                let (project_node, project_id) = (picker.node_id, picker.project_id);
+                self.ref_picker = None;
+                return self.switch_ref(project_node, project_id, ref_name);
+            }
+            _ => {}
+        }
+        KeyAction::None
+    }
+
+    fn select_node(&mut self, node_id: usize, visible: &[VisibleNode]) {
+        if let Some(pos) = visible.iter().position(|item| item.id == node_id) {
+            self.selected = pos;
+        }
+    }
+
+    fn yank_selected<C: ClipboardSink + ?Sized>(
+        &mut self,
+        visible: &[VisibleNode],
+        clipboard: &mut C,
+    ) -> Result<String> {
+        if visible.is_empty() {
+            anyhow::bail!("no selection");
+        }
+        let node_id = visible[self.selected].id;
+        let url = self.nodes[node_id].url.clone();
+        clipboard.set_text(url.clone())?;
+        Ok(url)
+    }
+
+    fn open_selected<B: BrowserOpener + ?Sized>(
+        &mut self,
+        visible: &[VisibleNode],
+        browser: &mut B,
+    ) -> Result<String> {
+        if visible.is_empty() {
+            anyhow::bail!("no selection");
+        }
+        let node_id = visible[self.selected].id;
+        let url = self.nodes[node_id].url.clone();
+        browser.open(&url)?;
+        Ok(url)
+    }
+
+    fn follow_upstream<B: BrowserOpener + ?Sized>(
+        &mut self,
+        visible: &[VisibleNode],
+        browser: &mut B,
+    ) -> Result<String> {
+        if visible.is_empty() {
+            anyhow::bail!("no selection");
+        }
+        let node_id = visible[self.selected].id;
+        let Some(upstream) = self.nodes[node_id].relation.forked_from.clone() else {
+            anyhow::bail!("not a fork");
+        };
+        let target = self.nodes.iter().position(|node| {
+            node.kind == NodeKind::Project && node.path == upstream.path_with_namespace
+        });
+        let Some(target) = target else {
+            browser.open(&upstream.web_url)?;
+            return Ok(format!("opened upstream {}", upstream.web_url));
+        };
+        self.reveal_node(target);
+        Ok(format!("jumped to upstream {}", upstream.path_with_namespace))
+    }
+
+    fn reveal_node(&mut self, target: usize) {
+        let mut ancestor = self.parent[target];
+        while let Some(id) = ancestor {
+            self.nodes[id].expanded = true;
+            ancestor = self.parent[id];
+        }
+        let visible = self.visible_nodes();
+        self.select_node(target, &visible);
+    }
+
+    fn set_status(&mut self, message: String) {
+        self.status = Some(message);
+    }
+
+    fn set_toast(&mut self, message: String) {
+        self.toast = Some(Toast {
+            message,
+            remaining: Self::TOAST_TTL,
+        });
+    }
+
+    fn tick_toast(&mut self) {
+        if let Some(toast) = self.toast.as_mut() {
+            if toast.remaining > 0 {
+                toast.remaining -= 1;
+            }
+            if toast.remaining == 0 {
+                self.toast = None;
+            }
+        }
+    }
+
+    fn set_pending_g(&mut self) {
+        self.pending_g = true;
+    }
+
+    fn clear_pending_g(&mut self) {
+        self.pending_g = false;
+    }
+
+    fn consume_pending_g(&mut self) -> bool {
+        if self.pending_g {
+            self.pending_g = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.search_mode = true;
+        self.search_query = Some(String::new());
+    }
+
+    fn exit_search_mode(&mut self) {
+        self.search_mode = false;
+        if let Some(query) = &self.search_query
+            && query.is_empty()
+        {
+            self.search_query = None;
+        }
+    }
+
+    fn clear_search(&mut self) {
+        self.search_query = None;
+        self.search_mode = false;
+    }
+
+    fn push_search_char(&mut self, ch: char) {
+        if let Some(query) = &mut self.search_query {
+            query.push(ch);
+        } else {
+            self.search_query = Some(ch.to_string());
+        }
+    }
+
+    fn pop_search_char(&mut self) {
+        if let Some(query) = &mut self.search_query {
+            query.pop();
+            if query.is_empty() && !self.search_mode {
+                self.search_query = None;
+            }
+        }
+    }
+
+    fn toggle_selected(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        if visible.is_empty() {
+            return KeyAction::None;
+        }
+        let node_id = visible[self.selected].id;
+        if let Some(request) = self.open_repo_entry(node_id) {
+            return KeyAction::Fetch(request);
+        }
+        if self.nodes[node_id].children.is_empty() {
+            return KeyAction::None;
+        }
+        self.nodes[node_id].expanded = !self.nodes[node_id].expanded;
+        KeyAction::None
+    }
+
+    fn toggle_archived(&mut self) {
+        self.show_archived = !self.show_archived;
+        let label = if self.show_archived { "showing" } else { "hiding" };
+        self.set_status(format!("{label} archived projects"));
+    }
+
+    /// Recomputes the cached group roll-ups after the tree or project
+    /// statistics change; rendering only reads the cache.
+    fn refresh_group_stats(&mut self) {
+        self.group_stats = rollup_group_stats(&self.nodes, &self.project_details);
+    }
+
+    fn resort(&mut self) {
+        self.refresh_group_stats();
+        apply_sorting(
+            &mut self.nodes,
+            &mut self.roots,
+            &self.config,
+            &self.project_details,
+            &self.group_details,
+            &self.group_stats,
+        );
+    }
+
+    /// Advances the sort order for the selected row's kind (groups or
+    /// projects) and re-sorts the tree, keeping the same node selected.
+    fn cycle_sort(&mut self, visible: &[VisibleNode]) {
+        let selected = visible.get(self.selected).map(|node| node.id);
+        let groups = selected.is_none_or(|id| self.nodes[id].kind == NodeKind::Group);
+        let (label, order) = if groups {
+            self.config.group_sort = self.config.group_sort.next();
+            ("groups", self.config.group_sort)
+        } else {
+            self.config.project_sort = self.config.project_sort.next();
+            ("projects", self.config.project_sort)
+        };
+        self.resort();
+        if let Some(id) = selected {
+            let visible = self.visible_nodes();
+            self.select_node(id, &visible);
+        }
+        self.set_status(format!("sorting {label} by {}", order.label()));
+    }
+
+    fn toggle_group_stats(&mut self) {
+        self.show_group_stats = !self.show_group_stats;
+        let label = if self.show_group_stats { "showing" } else { "hiding" };
+        self.set_status(format!("{label} group statistics"));
+    }
+
+    fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    fn page_up(&mut self, visible_len: usize, page_size: usize) {
+        if visible_len == 0 {
+            self.selected = 0;
+            return;
+        }
+        let step = page_size.min(visible_len.saturating_sub(1)).max(1);
+        self.selected = self.selected.saturating_sub(step);
+    }
+
+    fn page_down(&mut self, visible_len: usize, page_size: usize) {
+        if visible_len == 0 {
+            self.selected = 0;
+            return;
+        }
+        let step = page_size.min(visible_len.saturating_sub(1)).max(1);
+        self.selected = (self.selected + step).min(visible_len - 1);
+    }
+
+    fn lazy_fetch(&mut self, visible: &[VisibleNode]) -> Option<FetchRequest> {
+        let node = &self.nodes[visible.get(self.selected)?.id];
+        let gitlab_id = node.gitlab_id?;
+        match node.kind {
+            NodeKind::Project => {
+                let details = self.project_details.entry(gitlab_id).or_insert(ProjectDetails {
+                    project: None,
+                    creator: None,
+                    state: DetailState::Partial,
+                });
+                match details.state {
+                    DetailState::Partial => {
+                        details.state = DetailState::Loading;
+                        return Some(FetchRequest::ProjectDetails { project_id: gitlab_id });
+                    }
+                    DetailState::Loading => return None,
+                    DetailState::Loaded | DetailState::Failed(_) => {}
+                }
+                if self.readmes.contains_key(&gitlab_id) {
+                    return None;
+                }
+                let target = details.project.as_ref().and_then(|project| {
+                    let ref_name = project.default_branch.clone()?;
+                    let path = readme_path(project)?;
+                    Some((path, ref_name))
+                });
+                let state = if target.is_some() { DetailState::Loading } else { DetailState::Loaded };
+                self.readmes.insert(
+                    gitlab_id,
+                    ProjectReadme {
+                        content: None,
+                        state,
+                        scroll: 0,
+                    },
+                );
+                let (path, ref_name) = target?;
+                Some(FetchRequest::Readme { project_id: gitlab_id, path, ref_name })
+            }
+            NodeKind::Group => {
+                let details = self.group_details.get_mut(&gitlab_id)?;
+                if details.state != DetailState::Partial {
+                    return None;
+                }
+                details.state = DetailState::Loading;
+                let username = self.token.as_ref().map(|token| token.username.clone());
+                Some(FetchRequest::GroupDetails { group_id: gitlab_id, username })
+            }
+            NodeKind::Shared | NodeKind::Directory | NodeKind::File => None,
+        }
+    }
+
+    fn pipeline_badge_fetch(
+        &mut self,
+        visible: &[VisibleNode],
+        now: Instant,
+    ) -> Option<FetchRequest> {
+        if !self.config.show_pipelines {
+            return None;
+        }
+        let mut projects = Vec::new();
+        for item in visible {
+            let node = &self.nodes[item.id];
+            let Some(project_id) = node.gitlab_id.filter(|_| node.kind == NodeKind::Project) else {
+                continue;
+            };
+            let due = match self.pipeline_badges.get(&project_id) {
+                None => true,
+                Some(PipelineBadge::Missing(at)) => now.duration_since(*at) >= PIPELINE_BADGE_RETRY,
+                Some(PipelineBadge::Pending | PipelineBadge::Status(_)) => false,
+            };
+            if !due {
+                continue;
+            }
+            // Every loaded project has its bulk payload here, selected or not.
+            let default_branch = self
+                .project_details
+                .get(&project_id)
+                .and_then(|details| details.project.as_ref())
+                .and_then(|project| project.default_branch.clone());
+            self.pipeline_badges.insert(project_id, PipelineBadge::Pending);
+            projects.push((project_id, default_branch));
+            if projects.len() == PIPELINE_BADGE_BATCH {
+                break;
+            }
+        }
+        (!projects.is_empty()).then_some(FetchRequest::PipelineBadges { projects })
+    }
+
+    fn selected_readme(&self, visible: &[VisibleNode]) -> Option<&ProjectReadme> {
+        let node = &self.nodes[visible.get(self.selected)?.id];
+        if node.kind != NodeKind::Project {
+            return None;
+        }
+        self.readmes.get(&node.gitlab_id?)
+    }
+
+    fn scroll_readme(&mut self, visible: &[VisibleNode], delta: i32) {
+        let Some(node) = visible.get(self.selected).map(|item| &self.nodes[item.id]) else {
+            return;
+        };
+        if node.kind != NodeKind::Project {
+            return;
+        }
+        let Some(readme) = node.gitlab_id.and_then(|id| self.readmes.get_mut(&id)) else {
+            return;
+        };
+        let lines = readme.content.as_deref().map_or(0, |text| text.lines().count());
+        let max = u16::try_from(lines.saturating_sub(1)).unwrap_or(u16::MAX);
+        let next = i32::from(readme.scroll) + delta;
+        readme.scroll = u16::try_from(next.max(0)).unwrap_or(u16::MAX).min(max);
+    }
+
+    fn selected_group_details(&self, visible: &[VisibleNode]) -> Option<&GroupDetails> {
+        let node = &self.nodes[visible.get(self.selected)?.id];
+        if node.kind != NodeKind::Group {
+            return None;
+        }
+        self.group_details.get(&node.gitlab_id?)
+    }
+
+    fn selected_project_details(&self, visible: &[VisibleNode]) -> Option<&ProjectDetails> {
+        let node = &self.nodes[visible.get(self.selected)?.id];
+        if node.kind != NodeKind::Project {
+            return None;
+        }
+        self.project_details.get(&node.gitlab_id?)
+    }
+
+    fn retry_selected(&mut self, visible: &[VisibleNode]) -> KeyAction {
+        if visible.is_empty() {
+            return KeyAction::None;
+        }
+        let node_id = visible[self.selected].id;
+        let node = &self.nodes[node_id];
+        match (node.kind, node.gitlab_id, &node.error) {
+            (NodeKind::Group, Some(_), Some(_)) if node.loading => {
+                self.set_status(format!("already retrying {}", node.path));
+                KeyAction::None
+            }
+            (NodeKind::Group, Some(group_id), Some(_)) => {
+                self.set_status(format!("retrying {}", node.path));
+                self.nodes[node_id].loading = true;
+                KeyAction::Fetch(FetchRequest::GroupProjects { node_id, group_id })
+            }
+            (kind, gitlab_id, _) => {
+                let path = node.path.clone();
+                if let Some(gitlab_id) = gitlab_id
+                    && self.reset_failed_details(kind, gitlab_id)
+                {
+                    self.set_status(format!("retrying details for {path}"));
+                } else {
+                    self.set_status("nothing to retry".to_string());
+                }
+                KeyAction::None
+            }
+        }
+    }
+
+    /// Puts failed project or group details back to `Partial` so the next
+    /// `lazy_fetch` requests them again.
+    fn reset_failed_details(&mut self, kind: NodeKind, gitlab_id: usize) -> bool {
+        let state = match kind {
+            NodeKind::Project => {
+                self.project_details.get_mut(&gitlab_id).map(|details| &mut details.state)
+            }
+            NodeKind::Group => {
+                self.group_details.get_mut(&gitlab_id).map(|details| &mut details.state)
+            }
+            NodeKind::Shared | NodeKind::Directory | NodeKind::File => None,
+        };
+        match state {
+            Some(state @ DetailState::Failed(_)) => {
+                *state = DetailState::Partial;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn apply_fetch(&mut self, result: FetchResult) {
+        match (result.request, result.payload) {
+            (FetchRequest::GroupProjects { node_id, .. }, Ok(FetchPayload::Projects(projects))) => {
+                let count = projects.len();
+                // Replace rather than append so a repeated retry cannot duplicate projects.
+                let mut children = std::mem::take(&mut self.nodes[node_id].children);
+                children.retain(|child| self.nodes[*child].kind != NodeKind::Project);
+                for project in &projects {
+                    self.project_details.insert(project.id, ProjectDetails::from_bulk(project));
+                    children.push(push_project_node(&mut self.nodes, project));
+                }
+                self.nodes[node_id].children = children;
+                self.nodes[node_id].error = None;
+                self.nodes[node_id].loading = false;
+                self.resort();
+                self.parent = build_parent_map(&self.nodes);
+                self.set_status(format!("loaded {count} projects for {}", self.nodes[node_id].path));
+            }
+            (
+                FetchRequest::ProjectDetails { project_id },
+                Ok(FetchPayload::ProjectDetails(project, creator)),
+            ) => {
+                self.project_details.insert(
+                    project_id,
+                    ProjectDetails {
+                        project: Some(*project),
+                        creator,
+                        state: DetailState::Loaded,
+                    },
+                );
+                self.refresh_group_stats();
+            }
+            (
+                FetchRequest::GroupDetails { group_id, .. },
+                Ok(FetchPayload::GroupDetails(group, member_count, access_level)),
+            ) => {
+                self.group_details.insert(
+                    group_id,
+                    GroupDetails {
+                        group: *group,
+                        member_count,
+                        access_level,
+                        state: DetailState::Loaded,
+                    },
+                );
+            }
+            (FetchRequest::GroupDetails { group_id, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Warn,
+                    "details",
+                    format!("group {group_id} details failed: {err}"),
+                );
+                if let Some(details) = self.group_details.get_mut(&group_id) {
+                    details.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (FetchRequest::Readme { project_id, .. }, Ok(FetchPayload::File(content))) => {
+                if let Some(readme) = self.readmes.get_mut(&project_id) {
+                    readme.content = Some(content);
+                    readme.state = DetailState::Loaded;
+                }
+            }
+            (FetchRequest::Readme { project_id, path, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Warn,
+                    "details",
+                    format!("project {project_id} {path} failed: {err}"),
+                );
+                if let Some(readme) = self.readmes.get_mut(&project_id) {
+                    readme.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (FetchRequest::RepoTree { node_id, project_id, ref path, ref ref_name }, _)
+                if !self.repo_tree_pending(node_id, project_id, path, ref_name) => {}
+            (
+                FetchRequest::RepoTree { node_id, project_id, .. },
+                Ok(FetchPayload::RepoTree(entries)),
+            ) => self.apply_repo_tree(node_id, project_id, entries),
+            (FetchRequest::RepoTree { node_id, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Error,
+                    "repo",
+                    format!("tree {} failed: {err}", self.nodes[node_id].path),
+                );
+                self.repo_trees.remove(&node_id);
+                self.set_status(format!("tree load failed: {err}"));
+            }
+            (
+                FetchRequest::RepoFile { project_id, path, ref_name },
+                Ok(FetchPayload::RepoFile(content, commit)),
+            ) => {
+                if let Some(pager) = self.pager.as_mut()
+                    && pager.project_id == project_id
+                    && pager.path == path
+                    && pager.ref_name == ref_name
+                {
+                    pager.content = Some(content);
+                    pager.commit = commit;
+                    pager.state = DetailState::Loaded;
+                }
+            }
+            (FetchRequest::RepoFile { project_id, path, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Error,
+                    "repo",
+                    format!("file {path} failed: {err}"),
+                );
+                if let Some(pager) = self.pager.as_mut()
+                    && pager.project_id == project_id
+                    && pager.path == path
+                {
+                    pager.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (FetchRequest::Refs { project_id }, Ok(FetchPayload::Refs(branches, tags))) => {
+                if let Some(picker) = self.ref_picker.as_mut()
+                    && picker.project_id == project_id
+                {
+                    picker.refs = branches
+                        .into_iter()
+                        .map(|name| ("branch", name))
+                        .chain(tags.into_iter().map(|name| ("tag", name)))
+                        .collect();
+                    picker.state = DetailState::Loaded;
+                }
+            }
+            (FetchRequest::Refs { project_id }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Error,
+                    "repo",
+                    format!("refs for project {project_id} failed: {err}"),
+                );
+                if let Some(picker) = self.ref_picker.as_mut()
+                    && picker.project_id == project_id
+                {
+                    picker.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (
+                FetchRequest::MergeRequests { scope, filter, .. },
+                Ok(FetchPayload::MergeRequests(rows)),
+            ) => {
+                if let Some(View::MergeRequests(view)) = self.view.as_mut()
+                    && view.scope == scope
+                    && view.filter == filter
+                {
+                    view.rows = rows;
+                    view.selected = 0;
+                    view.state = DetailState::Loaded;
+                }
+            }
+            (FetchRequest::MergeRequests { scope, filter, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Error,
+                    "views",
+                    format!("merge requests for {} failed: {err}", scope.api_path()),
+                );
+                if let Some(View::MergeRequests(view)) = self.view.as_mut()
+                    && view.scope == scope
+                    && view.filter == filter
+                {
+                    view.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (FetchRequest::MergeRequestDetail { project_id, iid }, payload) => {
+                let Some(View::MergeRequests(view)) = self.view.as_mut() else {
+                    return;
+                };
+                let Some(row) = view.rows.iter_mut().find(|row| {
+                    row.merge_request.project_id == project_id && row.merge_request.iid == iid
+                }) else {
+                    return;
+                };
+                match payload {
+                    Ok(FetchPayload::MergeRequestDetail(pipeline, approvals)) => {
+                        row.pipeline = pipeline;
+                        row.approvals = Some(approvals);
+                        row.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    // The row keeps its list fields; only the extras stay blank.
+                    Err(err) => {
+                        row.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Warn,
+                            "views",
+                            format!("merge request {project_id}!{iid} details failed: {err}"),
+                        );
+                    }
+                }
+            }
+            (FetchRequest::Issues { scope, query }, payload) => {
+                let Some(View::Issues(view)) = self.view.as_mut() else {
+                    return;
+                };
+                if view.scope != scope || view.query != query {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::Issues(rows)) => {
+                        view.rows = rows;
+                        view.selected = 0;
+                        view.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        view.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "views",
+                            format!("issues for {} failed: {err}", scope.api_path()),
+                        );
+                    }
+                }
+            }
+            (FetchRequest::IssueNotes { project_id, iid }, payload) => {
+                let Some(View::Issues(view)) = self.view.as_mut() else {
+                    return;
+                };
+                let Some(detail) = view.detail.as_mut() else {
+                    return;
+                };
+                if detail.issue.project_id != project_id || detail.issue.iid != iid {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::IssueNotes(notes)) => {
+                        detail.notes = notes;
+                        detail.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => detail.state = DetailState::Failed(err.to_string()),
+                }
+            }
+            (FetchRequest::PipelineBadges { projects }, payload) => {
+                match payload {
+                    Ok(FetchPayload::PipelineBadges(statuses)) => {
+                        for (project_id, status) in statuses {
+                            if let Some(status) = status {
+                                let badge = PipelineBadge::Status(status);
+                                self.pipeline_badges.insert(project_id, badge);
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => self.config.log.record(
+                        LogLevel::Warn,
+                        "pipelines",
+                        format!("pipeline badges failed: {err}"),
+                    ),
+                }
+                // Anything still pending found no pipeline or failed; retry later.
+                let now = Instant::now();
+                for (project_id, _) in projects {
+                    if let Some(badge) = self.pipeline_badges.get_mut(&project_id)
+                        && *badge == PipelineBadge::Pending
+                    {
+                        *badge = PipelineBadge::Missing(now);
+                    }
+                }
+            }
+            (
+                FetchRequest::PipelineDetail { project_id, .. },
+                Ok(FetchPayload::Pipeline(pipeline)),
+            ) => {
+                if let Some(View::Pipelines(view)) = self.view.as_mut()
+                    && view.project_id == project_id
+                    && let Some(row) = view.rows.iter_mut().find(|row| row.id == pipeline.id)
+                {
+                    *row = *pipeline;
+                }
+            }
+            (FetchRequest::PipelineDetail { pipeline_id, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Warn,
+                    "pipelines",
+                    format!("pipeline {pipeline_id} details failed: {err}"),
+                );
+            }
+            (FetchRequest::PipelineJobs { project_id, pipeline_id }, payload) => {
+                let Some(View::Pipelines(view)) = self.view.as_mut() else {
+                    return;
+                };
+                let Some(jobs) = view.jobs.as_mut() else {
+                    return;
+                };
+                if view.project_id != project_id || jobs.pipeline_id != pipeline_id {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::PipelineJobs(rows)) => {
+                        jobs.selected = jobs.selected.min(rows.len().saturating_sub(1));
+                        jobs.rows = rows;
+                        jobs.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => jobs.state = DetailState::Failed(err.to_string()),
+                }
+            }
+            (FetchRequest::JobTrace { job_id, .. }, payload) => {
+                let Some(View::Pipelines(view)) = self.view.as_mut() else {
+                    return;
+                };
+                let Some(jobs) = view.jobs.as_mut() else {
+                    return;
+                };
+                let Some(log) = jobs.log.as_mut().filter(|log| log.job.id == job_id) else {
+                    return;
+                };
+                log.fetched_at = Some(Instant::now());
+                match payload {
+                    Ok(FetchPayload::JobTrace(job, trace)) => {
+                        if let Some(row) = jobs.rows.iter_mut().find(|row| row.id == job_id) {
+                            *row = (*job).clone();
+                        }
+                        log.job = *job;
+                        log.set_trace(&trace);
+                        log.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => log.state = DetailState::Failed(err.to_string()),
+                }
+            }
+            (FetchRequest::JobAction { project_id, job_id, action }, payload) => {
+                let job = match payload {
+                    Ok(FetchPayload::Job(job)) => *job,
+                    Ok(_) => return,
+                    Err(err) => {
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "pipelines",
+                            format!("{} job {job_id} failed: {err}", action.path()),
+                        );
+                        self.set_status(format!("{} failed: {err}", action.path()));
+                        return;
+                    }
+                };
+                // A retry creates a new job; follow it instead of the old id.
+                if job.id == job_id {
+                    self.set_status(format!("{} job {job_id}: now {}", action.path(), job.status));
+                } else {
+                    self.set_status(format!(
+                        "{} job {job_id}: new job {} {}",
+                        action.path(),
+                        job.id,
+                        job.status
+                    ));
+                }
+                if let Some(View::Pipelines(view)) = self.view.as_mut()
+                    && view.project_id == project_id
+                    && let Some(jobs) = view.jobs.as_mut()
+                {
+                    // Partial marks the list stale; poll_fetch refreshes it on the next tick.
+                    jobs.state = DetailState::Partial;
+                    if let Some(row) = jobs.rows.iter_mut().find(|row| row.id == job_id) {
+                        *row = job.clone();
+                    }
+                    if let Some(log) = jobs.log.as_mut().filter(|log| log.job.id == job_id) {
+                        if log.job.id != job.id {
+                            log.set_trace("");
+                        }
+                        log.job = job;
+                        log.fetched_at = None;
+                    }
+                }
+            }
+            (FetchRequest::Members { scope, .. }, payload) => {
+                let Some(View::Members(view)) = self.view.as_mut() else {
+                    return;
+                };
+                if view.scope != scope {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::Members(rows)) => {
+                        view.rows = rows;
+                        view.selected = 0;
+                        view.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        view.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "members",
+                            format!("members of {} failed: {err}", scope.api_path()),
+                        );
+                    }
+                }
+            }
+            (FetchRequest::RefList { project_id, tab }, payload) => {
+                let Some(View::Refs(view)) = self.view.as_mut() else {
+                    return;
+                };
+                if view.project_id != project_id || view.tab != tab {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::Branches(rows)) => view.branches = rows,
+                    Ok(FetchPayload::Tags(rows)) => view.tags = rows,
+                    Ok(FetchPayload::Releases(rows)) => view.releases = rows,
+                    Ok(_) => return,
+                    Err(err) => {
+                        view.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "refs",
+                            format!("{} for project {project_id} failed: {err}", tab.label()),
+                        );
+                        return;
+                    }
+                }
+                view.selected = view.selected.min(view.len().saturating_sub(1));
+                view.state = DetailState::Loaded;
+            }
+            (FetchRequest::BranchDivergence { project_id, branch, .. }, payload) => {
+                let Some(View::Refs(view)) = self.view.as_mut() else {
+                    return;
+                };
+                if view.project_id != project_id {
+                    return;
+                }
+                let Some(row) = view.branches.iter_mut().find(|row| row.branch.name == branch)
+                else {
+                    return;
+                };
+                match payload {
+                    Ok(FetchPayload::Divergence(ahead, behind)) => {
+                        row.divergence = Some((ahead, behind));
+                        row.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        row.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Warn,
+                            "refs",
+                            format!("divergence of {branch} failed: {err}"),
+                        );
+                    }
+                }
+            }
+            (FetchRequest::Pipelines { project_id }, payload) => {
+                let Some(View::Pipelines(view)) = self.view.as_mut() else {
+                    return;
+                };
+                if view.project_id != project_id {
+                    return;
+                }
+                match payload {
+                    Ok(FetchPayload::Pipelines(rows)) => {
+                        if let Some(latest) = rows.first() {
+                            let badge = PipelineBadge::Status(latest.status.clone());
+                            self.pipeline_badges.insert(project_id, badge);
+                        }
+                        view.rows = rows;
+                        view.selected = 0;
+                        view.state = DetailState::Loaded;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        view.state = DetailState::Failed(err.to_string());
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "pipelines",
+                            format!("pipelines for project {project_id} failed: {err}"),
+                        );
+                    }
+                }
+            }
+            (FetchRequest::ProjectDetails { project_id }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Warn,
+                    "details",
+                    format!("project {project_id} details failed: {err}"),
+                );
+                if let Some(details) = self.project_details.get_mut(&project_id) {
+                    details.state = DetailState::Failed(err.to_string());
+                }
+            }
+            (FetchRequest::GroupProjects { node_id, .. }, Err(err)) => {
+                self.config.log.record(
+                    LogLevel::Error,
+                    "load",
+                    format!("retry {} failed: {err}", self.nodes[node_id].path),
+                );
+                self.nodes[node_id].error = Some(err.to_string());
+                self.nodes[node_id].loading = false;
+                self.set_status(format!("retry failed: {err}"));
+            }
+            (request, Ok(_)) => {
+                self.config.log.record(
+                    LogLevel::Warn,
+                    "fetch",
+                    format!("unexpected payload for {request:?}"),
+                );
+            }
+        }
+    }
+
+    fn handle_key(
+        &mut self,
+        key: KeyEvent,
+        visible: &[VisibleNode],
+        clipboard: Option<&mut dyn ClipboardSink>,
+        browser: &mut dyn BrowserOpener,
+    ) -> Result<KeyAction> {
+        if self.search_mode {
+            match key.code {
+                KeyCode::Esc => self.clear_search(),
+                KeyCode::Enter => self.exit_search_mode(),
+                KeyCode::Backspace => self.pop_search_char(),
+                KeyCode::Char(ch) => self.push_search_char(ch),
+                _ => {}
+            }
+            return Ok(KeyAction::None);
+        }
+        if self.ref_picker.is_some() {
+            return Ok(self.handle_ref_picker_key(key));
+        }
+        if self.pager.is_some() {
+            self.handle_pager_key(key, clipboard, browser);
+            return Ok(KeyAction::None);
+        }
+        if self.view.is_some() {
+            return Ok(self.handle_view_key(key, clipboard, browser));
+        }
+
+        let action = match (key.code, key.modifiers) {
+            (KeyCode::Char('c'), modifiers) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyAction::Quit
+            }
+            (KeyCode::Char('q'), _) => KeyAction::Quit,
+            (KeyCode::Char('r'), _) => KeyAction::Reload,
+            (KeyCode::Char('R'), _) => self.retry_selected(visible),
+            (KeyCode::Char('?'), _) => {
+                self.toggle_help();
+                KeyAction::None
+            }
+            (KeyCode::Char('L'), _) => {
+                self.show_log = !self.show_log;
+                KeyAction::None
+            }
+            (KeyCode::Char('J'), _) => {
+                self.scroll_readme(visible, 1);
+                KeyAction::None
+            }
+            (KeyCode::Char('K'), _) => {
+                self.scroll_readme(visible, -1);
+                KeyAction::None
+            }
+            (KeyCode::Char('a'), _) => {
+                self.toggle_archived();
+                KeyAction::None
+            }
+            (KeyCode::Char('S'), _) => {
+                self.toggle_group_stats();
+                KeyAction::None
+            }
+            (KeyCode::Char('s'), _) => {
+                self.cycle_sort(visible);
+                KeyAction::None
+            }
+            (KeyCode::Enter, _) => self.toggle_selected(visible),
+            (KeyCode::Char('b'), _) => self.open_ref_picker(visible),
+            (KeyCode::Char('M'), _) => self.open_merge_requests(visible),
+            (KeyCode::Char('I'), _) => self.open_issues(visible),
+            (KeyCode::Char('P'), _) => self.open_pipelines(visible),
+            (KeyCode::Char('B'), _) => self.open_refs(visible),
+            (KeyCode::Char('U'), _) => self.open_members(visible),
+            (KeyCode::Char('V'), _) => {
+                self.view = Some(View::Visibility(VisibilityView {
+                    rows: visibility_audit(self),
+                    flagged_only: false,
+                    selected: 0,
+                }));
+                KeyAction::None
+            }
+            (KeyCode::PageUp, _) => {
+                self.page_up(visible.len(), 10);
+                KeyAction::None
+            }
+            (KeyCode::PageDown, _) => {
+                self.page_down(visible.len(), 10);
+                KeyAction::None
+            }
+            (KeyCode::Up, _) => {
+                self.move_up();
+                KeyAction::None
+            }
+            (KeyCode::Down, _) => {
+                self.move_down(visible.len());
+                KeyAction::None
+            }
+            (KeyCode::Left, _) => {
+                self.collapse_or_parent(visible);
+                KeyAction::None
+            }
+            (KeyCode::Right, _) => self.expand_or_child(visible),
+            (KeyCode::Char('k'), _) => {
+                self.move_up();
+                KeyAction::None
+            }
+            (KeyCode::Char('j'), _) => {
+                self.move_down(visible.len());
+                KeyAction::None
+            }
+            (KeyCode::Char('h'), _) => {
+                self.collapse_or_parent(visible);
+                KeyAction::None
+            }
+            (KeyCode::Char('l'), _) => self.expand_or_child(visible),
+            (KeyCode::Char('g'), _) => {
+                if self.consume_pending_g() {
+                    self.move_top();
+                } else {
+                    self.set_pending_g();
+                }
+                KeyAction::None
+            }
+            (KeyCode::Char('G'), _) => {
+                self.move_bottom(visible.len());
+                KeyAction::None
+            }
+            (KeyCode::Char('y'), _) => {
+                if let Some(clipboard) = clipboard {
+                    match self.yank_selected(visible, clipboard) {
+                        Ok(url) => {
+                            self.set_status(format!("copied {url}"));
+                            self.set_toast("Copied URL".to_string());
+                        }
+                        Err(err) => {
+                            self.config.log.record(
+                                LogLevel::Error,
+                                "clipboard",
+                                format!("copy failed: {err}"),
+                            );
+                            self.set_status(format!("copy failed: {err}"));
+                        }
+                    }
+                } else {
+                    self.config.log.record(
+                        LogLevel::Warn,
+                        "clipboard",
+                        "clipboard unavailable".to_string(),
+                    );
+                    self.set_status("clipboard unavailable".to_string());
+                }
+                KeyAction::None
+            }
+            (KeyCode::Char('o'), _) => {
+                match self.open_selected(visible, browser) {
+                    Ok(url) => self.set_status(format!("opened {url}")),
+                    Err(err) => {
+                        self.config.log.record(
+                            LogLevel::Error,
+                            "browser",
+                            format!("open failed: {err}"),
+                        );
+                        self.set_status(format!("open failed: {err}"));
+                    }
+                }
+                KeyAction::None
+            }
+            (KeyCode::Char('u'), _) => {
+                match self.follow_upstream(visible, browser) {
+                    Ok(message) => self.set_status(message),
+                    Err(err) => self.set_status(format!("upstream unavailable: {err}")),
+                }
+                KeyAction::None
+            }
+            (KeyCode::Char('/'), _) => {
+                self.start_search();
+                KeyAction::None
+            }
+            (KeyCode::Esc, _) => {
+                self.clear_search();
+                KeyAction::None
+            }
+            _ => KeyAction::None,
+        };
+
+        if key.code != KeyCode::Char('g') {
+            self.clear_pending_g();
+        }
+
+        Ok(action)
+    }
+
+}
+
+#[derive(Clone, Copy)]
+struct VisibleNode {
+    id: usize,
+    depth: usize,
+}
+
+fn push_node(
+    nodes: &mut Vec<Node>,
+    name: &str,
+    kind: NodeKind,
+    url: &str,
+    path: &str,
+    visibility: &str,
+    last_activity: Option<String>,
+) -> usize {
+    let id = nodes.len();
+    nodes.push(Node {
+        name: name.to_string(),
+        kind,
+        children: Vec::new(),
+        expanded: false,
+        url: url.to_string(),
+        path: path.to_string(),
+        visibility: visibility.to_string(),
+        last_activity,
+        gitlab_id: None,
+        error: None,
+        loading: false,
+        archived: false,
+        relation: NodeRelation::default(),
+        repo: None,
+    });
+    id
+}
+
+fn push_project_node(nodes: &mut Vec<Node>, project: &GitLabProject) -> usize {
+    let id = push_node(
+        nodes,
+        &project.name,
+        NodeKind::Project,
+        &project.web_url,
+        &project.path_with_namespace,
+        &project.visibility,
+        project.last_activity_at.clone(),
+    );
+    nodes[id].gitlab_id = (project.id != 0).then_some(project.id);
+    nodes[id].archived = project.archived;
+    nodes[id].relation.forked_from = project.forked_from_project.clone();
+    nodes[id].relation.mirror = project.mirror;
+    id
+}
+
+fn build_parent_map(nodes: &[Node]) -> Vec<Option<usize>> {
+    let mut parent = vec![None; nodes.len()];
+    for (idx, node) in nodes.iter().enumerate() {
+        for &child in &node.children {
+            parent[child] = Some(idx);
+        }
+    }
+    parent
+}
+
+/// Per-node values the sort orders compare. Groups take activity, stars and
+/// size from their descendant projects so they sort alongside each other.
+struct SortKeys {
+    names: Vec<String>,
+    paths: Vec<String>,
+    activities: Vec<Option<DateTime<Utc>>>,
+    created: Vec<Option<DateTime<Utc>>>,
+    stars: Vec<Option<u64>>,
+    sizes: Vec<Option<u64>>,
+}
+
+impl SortKeys {
+    fn new(
+        nodes: &[Node],
+        project_details: &HashMap<usize, ProjectDetails>,
+        group_details: &HashMap<usize, GroupDetails>,
+        group_stats: &HashMap<usize, GroupStats>,
+    ) -> Self {
+        let mut keys = SortKeys {
+            names: Vec::with_capacity(nodes.len()),
+            paths: Vec::with_capacity(nodes.len()),
+            activities: Vec::with_capacity(nodes.len()),
+            created: Vec::with_capacity(nodes.len()),
+            stars: Vec::with_capacity(nodes.len()),
+            sizes: Vec::with_capacity(nodes.len()),
+        };
+        for (id, node) in nodes.iter().enumerate() {
+            keys.names.push(node.name.to_lowercase());
+            keys.paths.push(node.path.to_lowercase());
+            if node.kind == NodeKind::Group {
+                let stats = group_stats.get(&id).cloned().unwrap_or_default();
+                let created = node
+                    .gitlab_id
+                    .and_then(|id| group_details.get(&id))
+                    .and_then(|details| details.group.created_at.as_deref());
+                keys.activities.push(stats.latest_activity);
+                keys.created.push(created.and_then(parse_timestamp));
+                keys.stars.push(Some(stats.stars));
+                keys.sizes.push((stats.sized_projects > 0).then_some(stats.repository_size));
+                continue;
+            }
+            let project = node
+                .gitlab_id
+                .filter(|_| node.kind == NodeKind::Project)
+                .and_then(|id| project_details.get(&id))
+                .and_then(|details| details.project.as_ref());
+            let created = project.and_then(|project| project.created_at.as_deref());
+            keys.activities.push(node.last_activity.as_deref().and_then(parse_timestamp));
+            keys.created.push(created.and_then(parse_timestamp));
+            keys.stars.push(project.and_then(|project| project.star_count));
+            keys.sizes.push(
+                project
+                    .and_then(|project| project.statistics.as_ref())
+                    .map(|statistics| statistics.repository_size),
+            );
+        }
+        keys
+    }
+}
+
+/// Sorts roots and every child list. Subgroups come before projects, each
+/// ordered by its own configured sort; repository entries stay alphabetical.
+fn apply_sorting(
+    nodes: &mut [Node],
+    roots: &mut [usize],
+    config: &Config,
+    project_details: &HashMap<usize, ProjectDetails>,
+    group_details: &HashMap<usize, GroupDetails>,
+    group_stats: &HashMap<usize, GroupStats>,
+) {
+    let keys = SortKeys::new(nodes, project_details, group_details, group_stats);
+    let kinds: Vec<NodeKind> = nodes.iter().map(|node| node.kind).collect();
+    let rank = |kind: NodeKind| match kind {
+        NodeKind::Group => 0,
+        NodeKind::Project => 1,
+        NodeKind::Directory => 2,
+        NodeKind::File => 3,
+        NodeKind::Shared => 4,
+    };
+    let order = |kind: NodeKind| match kind {
+        NodeKind::Group => config.group_sort,
+        NodeKind::Project => config.project_sort,
+        NodeKind::Shared | NodeKind::Directory | NodeKind::File => SortOrder::Alpha,
+    };
+    let compare = |a: &usize, b: &usize| {
+        rank(kinds[*a])
+            .cmp(&rank(kinds[*b]))
+            .then_with(|| compare_nodes(*a, *b, &keys, order(kinds[*a])))
+    };
+
+    roots.sort_by(compare);
+    for node in nodes.iter_mut() {
+        node.children.sort_by(compare);
+    }
+}
+
+fn compare_nodes(a: usize, b: usize, keys: &SortKeys, order: SortOrder) -> std::cmp::Ordering {
+    let by_name = || keys.names[a].cmp(&keys.names[b]);
+    match order {
+        SortOrder::Alpha => by_name(),
+        SortOrder::Path => keys.paths[a].cmp(&keys.paths[b]).then_with(by_name),
+        SortOrder::Activity => activity_key(keys.activities[a])
+            .cmp(&activity_key(keys.activities[b]))
+            .then_with(by_name),
+        SortOrder::Created => activity_key(keys.created[a])
+            .cmp(&activity_key(keys.created[b]))
+            .then_with(by_name),
+        SortOrder::Stars => largest_first_key(keys.stars[a])
+            .cmp(&largest_first_key(keys.stars[b]))
+            .then_with(by_name),
+        SortOrder::Size => largest_first_key(keys.sizes[a])
+            .cmp(&largest_first_key(keys.sizes[b]))
+            .then_with(by_name),
+    }
+}
+
+/// Largest first; nodes without a value go last.
+fn largest_first_key(value: Option<u64>) -> (bool, std::cmp::Reverse<u64>) {
+    (value.is_none(), std::cmp::Reverse(value.unwrap_or_default()))
+}
+
+/// Most recent first; nodes without a (parseable) timestamp go last.
+fn activity_key(instant: Option<DateTime<Utc>>) -> (bool, std::cmp::Reverse<DateTime<Utc>>) {
+    (instant.is_none(), std::cmp::Reverse(instant.unwrap_or_default()))
+}
+
+fn filter_visible_nodes(
+    visible: &[VisibleNode],
+    nodes: &[Node],
+    query: &str,
+) -> Vec<VisibleNode> {
+    let needle = query.trim().to_lowercase();
+    if needle.is_empty() {
+        return visible.to_vec();
+    }
+    visible
+        .iter()
+        .copied()
+        .filter(|node| fuzzy_match(&needle, &nodes[node.id].name.to_lowercase()))
+        .collect()
+}
+
+fn fuzzy_match(needle: &str, haystack: &str) -> bool {
+    let mut needle_chars = needle.chars();
+    let mut current = needle_chars.next();
+    for ch in haystack.chars() {
+        match current {
+            Some(target) if ch == target => {
+                current = needle_chars.next();
+                if current.is_none() {
+                    return true;
+                }
+            }
+            None => return true,
+            _ => {}
+        }
+    }
+    current.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn test_config() -> Config {
+        Config {
+            gitlab_url: "https://gitlab.com".to_string(),
+            gitlab_token: "token".to_string(),
+            filters: ApiFilters::default(),
+            http: HttpOptions::default(),
+            cache_path: default_cache_path(),
+            cache_ttl: Duration::from_secs(300),
+            group_sort: SortOrder::Alpha,
+            project_sort: SortOrder::Alpha,
+            token_warn_days: 14,
+            loader: LoaderKind::Rest,
+            log_file: None,
+            log: EventLog::new(LogLevel::Debug),
+            show_archived: true,
+            show_group_stats: false,
+            show_shared: false,
+            show_pipelines: true,
+            stale_days: 180,
+            time: TimeDisplay {
+                timezone: DisplayTimezone::Named(chrono_tz::UTC),
+                format: DEFAULT_TIME_FORMAT.to_string(),
+            },
+        }
+    }
+
+    fn key_event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            state: KeyEventState::NONE,
+        }
+    }
+
+    #[test]
+    fn visible_nodes_respects_expansion() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let child = push_node(
+            &mut nodes,
+            "child",
+            NodeKind::Project,
+            "https://example.com/child",
+            "root/child",
+            "private",
+            None,
+        );
+        nodes[root].children.push(child);
+
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].id, root);
+
+        app.nodes[root].expanded = true;
+        let visible = app.visible_nodes();
+        assert_eq!(visible.len(), 2);
+        assert_eq!(visible[0].id, root);
+        assert_eq!(visible[1].id, child);
+    }
+
+    #[test]
+    fn config_from_env_reader_requires_token_and_defaults_url() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            _ => None,
+        };
+
+        let config = Config::from_env_reader(reader).expect("config should load");
+        assert_eq!(config.gitlab_url, "https://gitlab.com");
+        assert_eq!(config.gitlab_token, "token");
+        assert_eq!(config.filters.per_page, 100);
+        assert!(config.filters.keyset_pagination);
+        assert_eq!(config.filters.page_concurrency, 4);
+        assert_eq!(config.log_file, None);
+        assert!(config.filters.all_available.is_none());
+        assert_eq!(config.cache_ttl.as_secs(), 300);
+        assert!(config
+            .cache_path
+            .ends_with(PathBuf::from("gitlab-tree").join("cache.json")));
+        assert_eq!(config.group_sort, SortOrder::Alpha);
+        assert_eq!(config.project_sort, SortOrder::Alpha);
+    }
+
+    #[test]
+    fn config_from_env_reader_fails_without_token() {
+        let reader = |_key: &str| None;
+        let result = Config::from_env_reader(reader);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_from_env_reader_parses_filters() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_ALL_AVAILABLE" => Some("true".to_string()),
+            "GITLAB_OWNED" => Some("0".to_string()),
+            "GITLAB_TOP_LEVEL_ONLY" => Some("yes".to_string()),
+            "GITLAB_INCLUDE_SUBGROUPS" => Some("on".to_string()),
+            "GITLAB_VISIBILITY" => Some("private".to_string()),
+            "GITLAB_PER_PAGE" => Some("50".to_string()),
+            "GITLAB_CACHE_TTL_SECONDS" => Some("120".to_string()),
+            "GITLAB_CACHE_PATH" => Some("/tmp/gitlab-tree-cache.json".to_string()),
+            "GITLAB_GROUP_SORT" => Some("activity".to_string()),
+            "GITLAB_PROJECT_SORT" => Some("alpha".to_string()),
+            _ => None,
+        };
+
+        let config = Config::from_env_reader(reader).expect("config should load");
+        assert_eq!(config.filters.per_page, 50);
+        assert_eq!(config.filters.all_available, Some(true));
+        assert_eq!(config.filters.owned, Some(false));
+        assert_eq!(config.filters.top_level_only, Some(true));
+        assert_eq!(config.filters.include_subgroups, Some(true));
+        assert_eq!(config.filters.visibility.as_deref(), Some("private"));
+        assert_eq!(config.cache_ttl.as_secs(), 120);
+        assert_eq!(
+            config.cache_path,
+            PathBuf::from("/tmp/gitlab-tree-cache.json")
+        );
+        assert_eq!(config.group_sort, SortOrder::Activity);
+        assert_eq!(config.project_sort, SortOrder::Alpha);
+    }
+
+    #[test]
+    fn config_from_env_reader_rejects_invalid_bool() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_OWNED" => Some("maybe".to_string()),
+            _ => None,
+        };
+
+        let result = Config::from_env_reader(reader);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_from_env_reader_rejects_invalid_sort() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_GROUP_SORT" => Some("random".to_string()),
+            _ => None,
+        };
+
+        let result = Config::from_env_reader(reader);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_from_env_reader_parses_http_options() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_CA_BUNDLE" => Some("/etc/ssl/internal.pem".to_string()),
+            "GITLAB_CLIENT_CERT" => Some("/etc/ssl/client.pem".to_string()),
+            "GITLAB_CLIENT_KEY" => Some("/etc/ssl/client.key".to_string()),
+            "GITLAB_HTTPS_PROXY" => Some("http://proxy.internal:3128".to_string()),
+            "GITLAB_NO_PROXY" => Some("localhost,.internal".to_string()),
+            "GITLAB_TIMEOUT_SECONDS" => Some("5".to_string()),
+            _ => None,
+        };
+
+        let config = Config::from_env_reader(reader).expect("config should load");
+        assert_eq!(config.http.ca_bundle, Some(PathBuf::from("/etc/ssl/internal.pem")));
+        assert_eq!(config.http.client_cert, Some(PathBuf::from("/etc/ssl/client.pem")));
+        assert_eq!(config.http.client_key, Some(PathBuf::from("/etc/ssl/client.key")));
+        assert_eq!(config.http.http_proxy, None);
+        assert_eq!(config.http.https_proxy.as_deref(), Some("http://proxy.internal:3128"));
+        assert_eq!(config.http.no_proxy.as_deref(), Some("localhost,.internal"));
+        assert_eq!(config.http.timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn config_from_env_reader_rejects_client_key_without_cert() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_CLIENT_KEY" => Some("/etc/ssl/client.key".to_string()),
+            _ => None,
+        };
+
+        let result = Config::from_env_reader(reader);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn config_from_env_reader_rejects_no_proxy_without_explicit_proxy() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_NO_PROXY" => Some("localhost".to_string()),
+            _ => None,
+        };
+
+        let err = Config::from_env_reader(reader).err().expect("no-proxy rejected");
+        assert_eq!(
+            err.to_string(),
+            "GITLAB_NO_PROXY requires GITLAB_HTTP_PROXY or GITLAB_HTTPS_PROXY"
+        );
+    }
+
+    #[test]
+    fn build_http_client_applies_proxy_and_reports_missing_ca() {
+        let options = HttpOptions {
+            http_proxy: Some("http://proxy.internal:3128".to_string()),
+            no_proxy: Some("localhost".to_string()),
+            timeout: Some(Duration::from_secs(5)),
+            ..HttpOptions::default()
+        };
+        assert!(build_http_client(&options).is_ok());
+
+        let options = HttpOptions {
+            ca_bundle: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..HttpOptions::default()
+        };
+        let err = build_http_client(&options).expect_err("missing CA should fail");
+        assert!(err.to_string().contains("CA bundle"));
+    }
+
+    #[test]
+    fn loading_message_cycles_frames() {
+        assert_eq!(loading_message(0), "| loading GitLab data...");
+        assert_eq!(loading_message(1), "/ loading GitLab data...");
+        assert_eq!(loading_message(2), "- loading GitLab data...");
+        assert_eq!(loading_message(3), "\\ loading GitLab data...");
+        assert_eq!(loading_message(4), "| loading GitLab data...");
+    }
+
+    #[test]
+    fn format_node_details_includes_metadata() {
+        let node = Node {
+            name: "root".to_string(),
+            kind: NodeKind::Group,
+            children: Vec::new(),
+            expanded: false,
+            url: "https://example.com/root".to_string(),
+            path: "root".to_string(),
+            visibility: "private".to_string(),
+            last_activity: None,
+            gitlab_id: None,
+            error: None,
+            loading: false,
+            archived: false,
+            relation: NodeRelation::default(),
+            repo: None,
+        };
+
+        let lines = format_node_details(&node, &test_config().time, SystemTime::now());
+        assert!(lines.iter().any(|line| line == "Name: root"));
+        assert!(lines.iter().any(|line| line == "Kind: Group"));
+        assert!(lines.iter().any(|line| line == "Path: root"));
+        assert!(lines.iter().any(|line| line == "Visibility: private"));
+        assert!(lines.iter().any(|line| line == "URL: https://example.com/root"));
+        assert!(!lines.iter().any(|line| line.starts_with("Last activity:")));
+    }
+
+    #[test]
+    fn format_node_details_includes_last_activity_when_present() {
+        let node = Node {
+            name: "proj".to_string(),
+            kind: NodeKind::Project,
+            children: Vec::new(),
+            expanded: false,
+            url: "https://example.com/root/proj".to_string(),
+            path: "root/proj".to_string(),
+            visibility: "internal".to_string(),
+            last_activity: Some("2024-01-01T00:00:00Z".to_string()),
+            gitlab_id: None,
+            error: None,
+            loading: false,
+            archived: false,
+            relation: NodeRelation::default(),
+            repo: None,
+        };
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let lines = format_node_details(&node, &test_config().time, now);
+        assert!(lines
+            .iter()
+            .any(|line| line == "Last activity: 2 months ago (2024-01-01 00:00 UTC)"));
+    }
+
+    #[test]
+    fn filter_visible_nodes_matches_query_case_insensitive() {
+        let nodes = vec![
+            Node {
+                name: "API".to_string(),
+                kind: NodeKind::Project,
+                children: Vec::new(),
+                expanded: false,
+                url: "https://example.com/api".to_string(),
+                path: "root/api".to_string(),
+                visibility: "private".to_string(),
+                last_activity: None,
+                gitlab_id: None,
+                error: None,
+                loading: false,
+                archived: false,
+                relation: NodeRelation::default(),
+                repo: None,
+            },
+            Node {
+                name: "web".to_string(),
+                kind: NodeKind::Project,
+                children: Vec::new(),
+                expanded: false,
+                url: "https://example.com/web".to_string(),
+                path: "root/web".to_string(),
+                visibility: "private".to_string(),
+                last_activity: None,
+                gitlab_id: None,
+                error: None,
+                loading: false,
+                archived: false,
+                relation: NodeRelation::default(),
+                repo: None,
+            },
+        ];
+        let visible = vec![
+            VisibleNode { id: 0, depth: 0 },
+            VisibleNode { id: 1, depth: 0 },
+        ];
+
+        let filtered = filter_visible_nodes(&visible, &nodes, "api");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, 0);
+    }
+
+    #[test]
+    fn filter_visible_nodes_matches_fuzzy_subsequence() {
+        let nodes = vec![Node {
+            name: "gitlab".to_string(),
+            kind: NodeKind::Project,
+            children: Vec::new(),
+            expanded: false,
+            url: "https://example.com/gitlab".to_string(),
+            path: "root/gitlab".to_string(),
+            visibility: "private".to_string(),
+            last_activity: None,
+            gitlab_id: None,
+            error: None,
+            loading: false,
+            archived: false,
+            relation: NodeRelation::default(),
+            repo: None,
+        }];
+        let visible = vec![VisibleNode { id: 0, depth: 0 }];
+
+        let filtered = filter_visible_nodes(&visible, &nodes, "glb");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, 0);
+    }
+
+    #[test]
+    fn handle_key_returns_reload_on_r() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let action = app
+            .handle_key(key_event(KeyCode::Char('r'), KeyModifiers::NONE), &visible, None, &mut browser)
+            .expect("handle key");
+
+        matches!(action, KeyAction::Reload);
+        if let KeyAction::Reload = action {
+        } else {
+            panic!("expected reload action");
+        }
+    }
+
+    #[test]
+    fn handle_key_ctrl_c_quits() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let action = app
+            .handle_key(
+                key_event(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                &visible,
+                None,
+                &mut browser,
+            )
+            .expect("handle key");
+
+        if let KeyAction::Quit = action {
+        } else {
+            panic!("expected quit action");
+        }
+    }
+
+    #[test]
+    fn handle_key_question_toggles_help() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        app.handle_key(
+            key_event(KeyCode::Char('?'), KeyModifiers::NONE),
+            &visible,
+            None,
+            &mut browser,
+        )
+        .expect("handle key");
+        assert!(app.show_help);
+
+        app.handle_key(
+            key_event(KeyCode::Char('?'), KeyModifiers::NONE),
+            &visible,
+            None,
+            &mut browser,
+        )
+        .expect("handle key");
+        assert!(!app.show_help);
+    }
+
+    #[test]
+    fn page_navigation_moves_selection_by_page() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        for idx in 0..20 {
+            let child = push_node(
+                &mut nodes,
+                &format!("child-{idx}"),
+                NodeKind::Project,
+                "https://example.com/root/child",
+                "root/child",
+                "private",
+                None,
+            );
+            nodes[root].children.push(child);
+        }
+        nodes[root].expanded = true;
+
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        app.page_down(visible.len(), 10);
+        assert_eq!(app.selected, 10);
+
+        app.page_up(visible.len(), 10);
+        assert_eq!(app.selected, 0);
+    }
+
+    #[test]
+    fn handle_key_enter_toggles_expansion() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let child = push_node(
+            &mut nodes,
+            "child",
+            NodeKind::Project,
+            "https://example.com/child",
+            "root/child",
+            "private",
+            None,
+        );
+        nodes[root].children.push(child);
+
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+
+        app.handle_key(
+            key_event(KeyCode::Enter, KeyModifiers::NONE),
+            &visible,
+            None,
+            &mut browser,
+        )
+            .expect("handle key");
+        assert!(app.nodes[root].expanded);
+
+        app.handle_key(
+            key_event(KeyCode::Enter, KeyModifiers::NONE),
+            &visible,
+            None,
+            &mut browser,
+        )
+            .expect("handle key");
+        assert!(!app.nodes[root].expanded);
+    }
+
+    fn sort_tree(
+        nodes: &mut [Node],
+        roots: &mut [usize],
+        config: &Config,
+        project_details: &HashMap<usize, ProjectDetails>,
+    ) {
+        let group_stats = rollup_group_stats(nodes, project_details);
+        apply_sorting(nodes, roots, config, project_details, &HashMap::new(), &group_stats);
+    }
+
+    #[test]
+    fn apply_sorting_orders_children_and_roots() {
+        let mut nodes = Vec::new();
+        let zeta = push_node(
+            &mut nodes,
+            "zeta",
+            NodeKind::Group,
+            "https://example.com/zeta",
+            "zeta",
+            "private",
+            None,
+        );
+        let alpha = push_node(
+            &mut nodes,
+            "alpha",
+            NodeKind::Group,
+            "https://example.com/alpha",
+            "alpha",
+            "private",
+            None,
+        );
+        let beta = push_node(
+            &mut nodes,
+            "beta",
+            NodeKind::Project,
+            "https://example.com/alpha/beta",
+            "alpha/beta",
+            "private",
+            None,
+        );
+        let gamma = push_node(
+            &mut nodes,
+            "gamma",
+            NodeKind::Project,
+            "https://example.com/alpha/gamma",
+            "alpha/gamma",
+            "private",
+            None,
+        );
+
+        nodes[alpha].children.extend([gamma, beta]);
+
+        let mut roots = vec![zeta, alpha];
+        let config = test_config();
+        sort_tree(&mut nodes, &mut roots, &config, &HashMap::new());
+
+        assert_eq!(roots, vec![alpha, zeta]);
+        assert_eq!(nodes[alpha].children, vec![beta, gamma]);
+    }
+
+    #[test]
+    fn apply_sorting_orders_by_activity() {
+        let mut nodes = Vec::new();
+        let recent = push_node(
+            &mut nodes,
+            "recent",
+            NodeKind::Project,
+            "https://example.com/recent",
+            "recent",
+            "private",
+            Some("2024-10-02T10:00:00Z".to_string()),
+        );
+        let older = push_node(
+            &mut nodes,
+            "older",
+            NodeKind::Project,
+            "https://example.com/older",
+            "older",
+            "private",
+            Some("2024-01-01T10:00:00Z".to_string()),
+        );
+        let mut roots = vec![older, recent];
+        let mut config = test_config();
+        config.project_sort = SortOrder::Activity;
+
+        sort_tree(&mut nodes, &mut roots, &config, &HashMap::new());
+        assert_eq!(roots, vec![recent, older]);
+    }
+
+    #[test]
+    fn apply_sorting_compares_activity_across_offsets() {
+        let mut nodes = Vec::new();
+        let mut push = |name: &str, activity: Option<&str>| {
+            push_node(
+                &mut nodes,
+                name,
+                NodeKind::Project,
+                "https://example.com/x",
+                name,
+                "private",
+                activity.map(str::to_string),
+            )
+        };
+        // 09:30+02:00 is 07:30Z, earlier than 08:00Z despite sorting later as text.
+        let berlin = push("berlin", Some("2024-03-01T09:30:00+02:00"));
+        let utc = push("utc", Some("2024-03-01T08:00:00Z"));
+        let unknown = push("unknown", None);
+        let mut roots = vec![unknown, berlin, utc];
+        let mut config = test_config();
+        config.project_sort = SortOrder::Activity;
+
+        sort_tree(&mut nodes, &mut roots, &config, &HashMap::new());
+        assert_eq!(roots, vec![utc, berlin, unknown]);
+    }
+
+    #[test]
+    fn apply_sorting_sorts_groups_and_projects_separately() {
+        let mut nodes = Vec::new();
+        let mut push = |name: &str, kind: NodeKind, activity: Option<&str>| {
+            push_node(
+                &mut nodes,
+                name,
+                kind,
+                "https://example.com/x",
+                name,
+                "private",
+                activity.map(str::to_string),
+            )
+        };
+        let root = push("root", NodeKind::Group, None);
+        let idle = push("idle", NodeKind::Group, None);
+        let busy = push("zeta", NodeKind::Group, None);
+        let nested = push("nested", NodeKind::Project, Some("2024-06-01T00:00:00Z"));
+        let popular = push("popular", NodeKind::Project, Some("2024-01-01T00:00:00Z"));
+        let plain = push("plain", NodeKind::Project, Some("2024-02-01T00:00:00Z"));
+        nodes[busy].children.push(nested);
+        nodes[root].children.extend([plain, idle, popular, busy]);
+        let mut project_details = HashMap::new();
+        for (node, project_id, stars) in [(popular, 1, 40), (plain, 2, 3)] {
+            nodes[node].gitlab_id = Some(project_id);
+            let project = GitLabProject {
+                id: project_id,
+                star_count: Some(stars),
+                ..GitLabProject::default()
+            };
+            project_details.insert(project_id, ProjectDetails::from_bulk(&project));
+        }
+        let mut roots = vec![root];
+        let mut config = test_config();
+        config.group_sort = SortOrder::Activity;
+        config.project_sort = SortOrder::Stars;
+
+        sort_tree(&mut nodes, &mut roots, &config, &project_details);
+        assert_eq!(nodes[root].children, vec![busy, idle, popular, plain]);
+
+        config.project_sort = SortOrder::Activity;
+        sort_tree(&mut nodes, &mut roots, &config, &project_details);
+        assert_eq!(nodes[root].children, vec![busy, idle, plain, popular]);
+    }
+
+    #[test]
+    fn sort_key_cycles_order_for_selected_kind() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let tools = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "tools")
+            .expect("tools");
+        let visible = app.visible_nodes();
+        app.select_node(tools, &visible);
+        let mut browser = MockBrowser { opened: None };
+
+        app.handle_key(
+            key_event(KeyCode::Char('s'), KeyModifiers::NONE),
+            &visible,
+            None,
+            &mut browser,
+        )
+        .expect("handle key");
+        assert_eq!(app.config.project_sort, SortOrder::Activity);
+        assert_eq!(app.config.group_sort, SortOrder::Alpha);
+        assert_eq!(app.status.as_deref(), Some("sorting projects by last activity"));
+        let visible = app.visible_nodes();
+        assert_eq!(visible[app.selected].id, tools);
+    }
+
+    #[test]
+    fn timestamps_parse_and_format_relative_and_absolute() {
+        let instant = parse_timestamp("2024-03-01T09:30:00.000+02:00").expect("rfc3339");
+        assert_eq!(instant.to_rfc3339(), "2024-03-01T07:30:00+00:00");
+        assert_eq!(
+            parse_timestamp("2024-02-15").map(|date| date.to_rfc3339()).as_deref(),
+            Some("2024-02-15T00:00:00+00:00")
+        );
+        assert!(parse_timestamp("yesterday").is_none());
+
+        let now = parse_timestamp("2024-03-04T07:30:00Z").expect("now");
+        assert_eq!(format_relative(instant, now), "3 days ago");
+        assert_eq!(format_relative(now, now), "just now");
+        assert_eq!(format_relative(instant, instant + chrono::Duration::hours(1)), "1 hour ago");
+        assert_eq!(format_relative(now, instant), "in 3 days");
+
+        let reader = |key: &str| match key {
+            "GITLAB_TIMEZONE" => Some("Europe/Berlin".to_string()),
+            "GITLAB_TIME_FORMAT" => Some("%d.%m.%Y %H:%M %Z".to_string()),
+            _ => None,
+        };
+        let time = TimeDisplay::from_env(&reader).expect("time display");
+        assert_eq!(time.absolute(instant), "01.03.2024 08:30 CET");
+        assert_eq!(
+            time.describe("2024-03-01T09:30:00+02:00", SystemTime::from(now)),
+            "3 days ago (01.03.2024 08:30 CET)"
+        );
+        assert_eq!(time.describe("not a date", SystemTime::from(now)), "not a date");
+
+        let bad_zone = |key: &str| (key == "GITLAB_TIMEZONE").then(|| "Mars/Olympus".to_string());
+        assert!(TimeDisplay::from_env(&bad_zone).is_err());
+        let bad_format = |key: &str| (key == "GITLAB_TIME_FORMAT").then(|| "%Q".to_string());
+        assert!(TimeDisplay::from_env(&bad_format).is_err());
+    }
+
+    #[test]
+    fn select_clipboard_prefers_arboard() {
+        let probe = MockClipboardProbe {
+            arboard_ok: true,
+            has_wayland: true,
+            has_display: true,
+            has_tmux: true,
+            has_wl_copy: true,
+            has_xclip: true,
+        };
+
+        assert_eq!(
+            select_clipboard_backend(&probe),
+            ClipboardBackend::Arboard
+        );
+    }
+
+    #[test]
+    fn select_clipboard_uses_wl_copy_on_wayland() {
+        let probe = MockClipboardProbe {
+            arboard_ok: false,
+            has_wayland: true,
+            has_display: true,
+            has_tmux: false,
+            has_wl_copy: true,
+            has_xclip: true,
+        };
+
+        assert_eq!(
+            select_clipboard_backend(&probe),
+            ClipboardBackend::WlCopy
+        );
+    }
+
+    #[test]
+    fn select_clipboard_uses_xclip_on_x11() {
+        let probe = MockClipboardProbe {
+            arboard_ok: false,
+            has_wayland: false,
+            has_display: true,
+            has_tmux: false,
+            has_wl_copy: false,
+            has_xclip: true,
+        };
+
+        assert_eq!(
+            select_clipboard_backend(&probe),
+            ClipboardBackend::Xclip
+        );
+    }
+
+    #[test]
+    fn select_clipboard_none_when_unavailable() {
+        let probe = MockClipboardProbe {
+            arboard_ok: false,
+            has_wayland: false,
+            has_display: false,
+            has_tmux: false,
+            has_wl_copy: false,
+            has_xclip: false,
+        };
+
+        assert_eq!(
+            select_clipboard_backend(&probe),
+            ClipboardBackend::None
+        );
+    }
+
+    #[test]
+    fn select_clipboard_uses_osc52_in_tmux() {
+        let probe = MockClipboardProbe {
+            arboard_ok: false,
+            has_wayland: false,
+            has_display: false,
+            has_tmux: true,
+            has_wl_copy: false,
+            has_xclip: false,
+        };
+
+        assert_eq!(
+            select_clipboard_backend(&probe),
+            ClipboardBackend::Osc52
+        );
+    }
+
+    #[test]
+    fn osc52_sequence_wraps_for_tmux() {
+        let sequence = osc52_sequence("test", true);
+        assert!(sequence.starts_with("\x1bPtmux;"));
+        assert!(sequence.contains("\x1b]52;c;"));
+        assert!(sequence.ends_with("\x1b\\"));
+    }
+
+    #[test]
+    fn from_gitlab_data_builds_parent_child_relationships() {
+        let groups = vec![
+            GitLabGroup {
+                id: 1,
+                name: "root".to_string(),
+                web_url: "https://example.com/root".to_string(),
+                full_path: "root".to_string(),
+                visibility: "private".to_string(),
+                parent_id: None,
+                ..GitLabGroup::default()
+            },
+            GitLabGroup {
+                id: 2,
+                name: "child".to_string(),
+                web_url: "https://example.com/root/child".to_string(),
+                full_path: "root/child".to_string(),
+                visibility: "private".to_string(),
+                parent_id: Some(1),
+                ..GitLabGroup::default()
+            },
+        ];
+        let projects = vec![GroupProjects {
+            group_id: 1,
+            projects: vec![GitLabProject {
+                name: "proj".to_string(),
+                web_url: "https://example.com/root/proj".to_string(),
+                path_with_namespace: "root/proj".to_string(),
+                visibility: "private".to_string(),
+                last_activity_at: Some("2024-01-01T00:00:00Z".to_string()),
+                namespace: Some(GitLabNamespace {
+                    kind: "group".to_string(),
+                }),
+                ..GitLabProject::default()
+            }],
+            ..GroupProjects::default()
+        }];
+
+        let app = App::from_gitlab_data(
+            groups,
+            projects,
+            None,
+            test_config(),
+            "groups: 2".to_string(),
+        );
+
+        assert_eq!(app.roots.len(), 1);
+        let root_id = app.roots[0];
+        assert_eq!(app.nodes[root_id].name, "root");
+        assert_eq!(app.nodes[root_id].children.len(), 2);
+        let mut child_ids = app.nodes[root_id].children.clone();
+        child_ids.sort_by_key(|id| app.nodes[*id].name.clone());
+
+        let child_id = child_ids[0];
+        let project_id = child_ids[1];
+        assert_eq!(app.nodes[child_id].name, "child");
+        assert_eq!(app.nodes[project_id].name, "proj");
+        assert_eq!(app.parent[child_id], Some(root_id));
+        assert_eq!(app.parent[project_id], Some(root_id));
+    }
+
+    #[test]
+    fn from_gitlab_data_adds_personal_projects_root() {
+        let personal = PersonalProjects {
+            username: "alice".to_string(),
+            web_url: "https://example.com/alice".to_string(),
+            projects: vec![GitLabProject {
+                name: "notes".to_string(),
+                web_url: "https://example.com/alice/notes".to_string(),
+                path_with_namespace: "alice/notes".to_string(),
+                visibility: "private".to_string(),
+                last_activity_at: None,
+                namespace: Some(GitLabNamespace {
+                    kind: "user".to_string(),
+                }),
+                ..GitLabProject::default()
+            }],
+        };
+
+        let app = App::from_gitlab_data(
+            Vec::new(),
+            Vec::new(),
+            Some(personal),
+            test_config(),
+            "personal: 1".to_string(),
+        );
+
+        assert_eq!(app.roots.len(), 1);
+        let root_id = app.roots[0];
+        assert_eq!(app.nodes[root_id].name, "alice");
+        assert_eq!(app.nodes[root_id].children.len(), 1);
+        let project_id = app.nodes[root_id].children[0];
+        assert_eq!(app.nodes[project_id].name, "notes");
+        assert_eq!(app.parent[project_id], Some(root_id));
+    }
+
+    #[test]
+    fn archived_projects_are_marked_and_toggleable() {
+        let groups = vec![GitLabGroup {
+            id: 1,
+            name: "root".to_string(),
+            web_url: "https://example.com/root".to_string(),
+            full_path: "root".to_string(),
+            visibility: "private".to_string(),
+            parent_id: None,
+            ..GitLabGroup::default()
+        }];
+        let projects = vec![GroupProjects {
+            group_id: 1,
+            projects: vec![
+                GitLabProject {
+                    id: 10,
+                    name: "active".to_string(),
+                    web_url: "https://example.com/root/active".to_string(),
+                    path_with_namespace: "root/active".to_string(),
+                    visibility: "private".to_string(),
+                    ..GitLabProject::default()
+                },
+                GitLabProject {
+                    id: 11,
+                    name: "legacy".to_string(),
+                    web_url: "https://example.com/root/legacy".to_string(),
+                    path_with_namespace: "root/legacy".to_string(),
+                    visibility: "private".to_string(),
+                    archived: true,
+                    ..GitLabProject::default()
+                },
+            ],
+            ..GroupProjects::default()
+        }];
+        let mut app = App::from_gitlab_data(groups, projects, None, test_config(), String::new());
+
+        let visible = app.visible_nodes();
+        assert_eq!(visible.len(), 3);
+        let legacy = visible[2].id;
+        assert!(app.nodes[legacy].archived);
+        let lines = format_node_details(&app.nodes[legacy], &test_config().time, SystemTime::now());
+        assert!(lines.contains(&"Archived: yes".to_string()));
+
+        let mut browser = MockBrowser { opened: None };
+        app.handle_key(key_event(KeyCode::Char('a'), KeyModifiers::NONE), &visible, None, &mut browser)
+            .expect("handle key");
+        let visible = app.visible_nodes();
+        assert_eq!(visible.len(), 2);
+        assert!(visible.iter().all(|node| !app.nodes[node.id].archived));
+        assert_eq!(app.status.as_deref(), Some("hiding archived projects"));
+    }
+
+    #[test]
+    fn vim_navigation_helpers_update_selection() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let child = push_node(
+            &mut nodes,
+            "child",
+            NodeKind::Project,
+            "https://example.com/child",
+            "root/child",
+            "private",
+            None,
+        );
+        nodes[root].children.push(child);
+        nodes[root].expanded = true;
+
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 1,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        app.move_top();
+        assert_eq!(app.selected, 0);
+
+        app.move_bottom(2);
+        assert_eq!(app.selected, 1);
+    }
+
+    #[test]
+    fn vim_navigation_pending_g_toggles() {
+        let mut app = App {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            parent: Vec::new(),
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        assert!(!app.consume_pending_g());
+        app.set_pending_g();
+        assert!(app.consume_pending_g());
+        assert!(!app.consume_pending_g());
+    }
+
+    #[test]
+    fn yank_selected_copies_url() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
+            "root",
+            "private",
+            None,
+        );
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut clipboard = MockClipboard { text: None };
+        let url = app
+            .yank_selected(&visible, &mut clipboard)
+            .expect("yank should succeed");
+
+        assert_eq!(url, "https://example.com/root");
+        assert_eq!(clipboard.text.as_deref(), Some("https://example.com/root"));
+    }
+
+    #[test]
+    fn open_selected_opens_url() {
+        let mut nodes = Vec::new();
+        let root = push_node(
+            &mut nodes,
+            "root",
+            NodeKind::Group,
+            "https://example.com/root",
             "root",
             "private",
             None,
         );
-        let child = push_node(
-            &mut nodes,
-            "child",
-            NodeKind::Project,
-            "https://example.com/child",
-            "root/child",
-            "private",
-            None,
+        let parent = build_parent_map(&nodes);
+        let mut app = App {
+            nodes,
+            roots: vec![root],
+            parent,
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let url = app
+            .open_selected(&visible, &mut browser)
+            .expect("open should succeed");
+
+        assert_eq!(url, "https://example.com/root");
+        assert_eq!(browser.opened.as_deref(), Some("https://example.com/root"));
+    }
+
+    #[test]
+    fn toast_expires_after_ticks() {
+        let mut app = App {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            parent: Vec::new(),
+            selected: 0,
+            config: test_config(),
+            status: None,
+            pending_g: false,
+            toast: None,
+            search_query: None,
+            search_mode: false,
+            show_help: false,
+            token: None,
+            show_log: false,
+            show_archived: true,
+            show_group_stats: false,
+            project_details: HashMap::new(),
+            group_details: HashMap::new(),
+            group_stats: HashMap::new(),
+            readmes: HashMap::new(),
+            repo_trees: HashMap::new(),
+            repo_refs: HashMap::new(),
+            pager: None,
+            ref_picker: None,
+            view: None,
+            pipeline_badges: HashMap::new(),
+        };
+
+        app.set_toast("Copied URL".to_string());
+        for _ in 0..App::TOAST_TTL {
+            app.tick_toast();
+        }
+
+        assert!(app.toast.is_none());
+    }
+
+    #[test]
+    fn cache_is_valid_respects_ttl() {
+        let ttl = Duration::from_secs(10);
+        let now = UNIX_EPOCH + Duration::from_secs(100);
+        assert!(cache_is_valid(95, ttl, now));
+        assert!(!cache_is_valid(80, ttl, now));
+    }
+
+    #[test]
+    fn cache_store_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("cache.json");
+        let store = CacheStore::new(path, Duration::from_secs(60));
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let data = CacheData {
+            created_at,
+            groups: vec![GitLabGroup {
+                id: 1,
+                name: "root".to_string(),
+                web_url: "https://example.com/root".to_string(),
+                full_path: "root".to_string(),
+                visibility: "private".to_string(),
+                parent_id: None,
+                ..GitLabGroup::default()
+            }],
+            projects_by_group: vec![GroupProjects {
+                group_id: 1,
+                projects: vec![GitLabProject {
+                    name: "proj".to_string(),
+                    web_url: "https://example.com/root/proj".to_string(),
+                    path_with_namespace: "root/proj".to_string(),
+                    visibility: "private".to_string(),
+                    last_activity_at: Some("2024-01-01T00:00:00Z".to_string()),
+                    namespace: Some(GitLabNamespace {
+                        kind: "group".to_string(),
+                    }),
+                    ..GitLabProject::default()
+                }],
+                ..GroupProjects::default()
+            }],
+            personal: None,
+        };
+
+        store.store(&data).expect("store cache");
+        let loaded = store.load().expect("load cache");
+        let loaded = loaded.expect("cache should be present");
+
+        assert_eq!(loaded.groups.len(), 1);
+        assert_eq!(loaded.groups[0].name, "root");
+        assert_eq!(loaded.projects_by_group.len(), 1);
+        assert_eq!(loaded.projects_by_group[0].projects[0].name, "proj");
+    }
+
+    #[test]
+    fn personal_projects_filter_only_user_namespace() {
+        let projects = vec![
+            GitLabProject {
+                name: "personal".to_string(),
+                web_url: "https://example.com/alice/personal".to_string(),
+                path_with_namespace: "alice/personal".to_string(),
+                visibility: "private".to_string(),
+                last_activity_at: None,
+                namespace: Some(GitLabNamespace {
+                    kind: "user".to_string(),
+                }),
+                ..GitLabProject::default()
+            },
+            GitLabProject {
+                name: "grouped".to_string(),
+                web_url: "https://example.com/group/grouped".to_string(),
+                path_with_namespace: "group/grouped".to_string(),
+                visibility: "private".to_string(),
+                last_activity_at: None,
+                namespace: Some(GitLabNamespace {
+                    kind: "group".to_string(),
+                }),
+                ..GitLabProject::default()
+            },
+        ];
+
+        let filtered = filter_personal_projects(projects);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "personal");
+    }
+
+    fn test_token(scopes: &[&str], days_until_expiry: Option<i64>) -> TokenInfo {
+        TokenInfo {
+            username: "alice".to_string(),
+            display_name: Some("Alice".to_string()),
+            token_name: "cli".to_string(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires_at: days_until_expiry.map(|_| "2024-01-31".to_string()),
+            days_until_expiry,
+        }
+    }
+
+    #[test]
+    fn days_until_counts_calendar_days() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_704_067_200 + 3_600);
+        assert_eq!(days_until("2024-01-31", now), Some(30));
+        assert_eq!(days_until("2023-12-31", now), Some(-1));
+        assert_eq!(days_until("not-a-date", now), None);
+        // 23:30 at -02:00 is already the next day in UTC.
+        assert_eq!(days_until("2023-12-31T23:30:00-02:00", now), Some(0));
+        assert_eq!(days_since("2024-01-01T00:30:00+02:00", now), Some(1));
+    }
+
+    #[test]
+    fn token_warnings_flag_expiry_and_missing_scopes() {
+        let token = test_token(&["read_api"], Some(30));
+        assert!(token_warnings(&token, 14).is_empty());
+
+        let token = test_token(&["read_api", "write_repository"], Some(3));
+        assert_eq!(token_warnings(&token, 14), vec!["token expires in 3d".to_string()]);
+
+        let token = test_token(&["read_user"], None);
+        assert_eq!(token_warnings(&token, 14), vec!["browse needs api or read_api".to_string()]);
+    }
+
+    #[test]
+    fn scope_warning_only_names_actions_the_token_cannot_perform() {
+        let mut app = App::load_failed(test_config(), String::new());
+        assert_eq!(app.scope_warning(TokenAction::CloneHttps), None);
+
+        app.token = Some(test_token(&["read_api", "read_repository"], None));
+        assert_eq!(app.scope_warning(TokenAction::CloneHttps), None);
+        assert_eq!(
+            app.scope_warning(TokenAction::ManageJobs),
+            Some("retry/cancel jobs needs api".to_string())
+        );
+
+        app.token = Some(test_token(&["read_api"], None));
+        assert_eq!(
+            app.scope_warning(TokenAction::CloneHttps),
+            Some("clone over HTTPS needs read_repository or write_repository or api".to_string())
         );
-        nodes[root].children.push(child);
+    }
 
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
+    #[test]
+    fn format_token_summary_includes_identity_and_expiry() {
+        let token = test_token(&["api"], Some(5));
+        assert_eq!(
+            format_token_summary(&token),
+            "user: alice | scopes: api | expires in 5d"
+        );
+        let token = test_token(&[], None);
+        assert_eq!(
+            format_token_summary(&token),
+            "user: alice | scopes: none | no expiry"
+        );
+    }
+
+    fn fixture_config(gitlab_url: &str, cache_dir: &tempfile::TempDir) -> Config {
+        Config {
+            gitlab_url: gitlab_url.to_string(),
+            cache_path: cache_dir.path().join("cache.json"),
+            http: HttpOptions {
+                timeout: Some(Duration::from_secs(5)),
+                ..HttpOptions::default()
+            },
+            ..test_config()
+        }
+    }
+
+    #[test]
+    fn from_api_builds_tree_from_fixtures() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+
+        let app = App::from_api(&api, config).expect("load from fake api");
+
+        let names: Vec<&str> = app.roots.iter().map(|id| app.nodes[*id].name.as_str()).collect();
+        assert_eq!(names, vec!["alice", "platform"]);
+        let platform = app.roots[1];
+        let children: Vec<&str> = app.nodes[platform]
+            .children
+            .iter()
+            .map(|id| app.nodes[*id].name.as_str())
+            .collect();
+        assert_eq!(children, vec!["backend", "tools"]);
+        let alice = app.roots[0];
+        assert_eq!(app.nodes[alice].children.len(), 1);
+        let token = app.token.expect("token info");
+        assert_eq!(token.username, "alice");
+        assert_eq!(token.scopes, vec!["read_api".to_string(), "read_user".to_string()]);
+    }
+
+    #[test]
+    fn from_api_marks_failed_group_with_error() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let mut api = FakeGitLabApi::from_fixtures();
+        api.group_projects.remove(&2);
+
+        let app = App::from_api(&api, config).expect("partial load");
+
+        let backend = app
+            .nodes
+            .iter()
+            .find(|node| node.name == "backend")
+            .expect("failed group still present");
+        assert!(backend.children.is_empty());
+        assert!(backend.error.as_deref().unwrap_or("").contains("404"));
+        let tools = app.nodes.iter().find(|node| node.name == "tools").expect("tools");
+        assert!(tools.error.is_none());
+        assert!(app.status.as_deref().unwrap_or("").contains("failed groups: 1"));
+        assert!(!cache_dir.path().join("cache.json").exists());
+    }
+
+    #[test]
+    fn from_api_marks_forks_mirrors_and_shared_nodes() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config("https://gitlab.example.com", &cache_dir);
+        config.show_shared = true;
+        let api = FakeGitLabApi::from_fixtures();
+
+        let app = App::from_api(&api, config).expect("load");
+
+        let platform = app.roots[1];
+        let children: Vec<&str> = app.nodes[platform]
+            .children
+            .iter()
+            .map(|id| app.nodes[*id].name.as_str())
+            .collect();
+        assert_eq!(children, vec!["backend", "tools", "shared"]);
+        let heading = *app.nodes[platform].children.last().expect("heading");
+        assert!(app.nodes[heading].kind == NodeKind::Shared);
+        let shared: Vec<(&str, bool)> = app.nodes[heading]
+            .children
+            .iter()
+            .map(|id| (app.nodes[*id].name.as_str(), app.nodes[*id].relation.shared))
+            .collect();
+        assert_eq!(shared, vec![("design", true), ("handbook", true)]);
+
+        let api_node = app.nodes.iter().find(|node| node.name == "api").expect("api");
+        let upstream = api_node.relation.forked_from.as_ref().expect("fork parent");
+        assert_eq!(upstream.path_with_namespace, "upstream/api");
+        assert_eq!(relation_tags(&api_node.relation), " [fork]");
+        let details = format_node_details(api_node, &test_config().time, SystemTime::now());
+        assert!(details.contains(&"Forked from: upstream/api".to_string()));
+        let auth = app.nodes.iter().find(|node| node.name == "auth").expect("auth");
+        assert!(auth.relation.mirror);
+        let lines = format_node_details(auth, &test_config().time, SystemTime::now());
+        assert!(lines.contains(&"Mirror: pull mirror".to_string()));
+    }
+
+    #[test]
+    fn follow_upstream_opens_upstream_outside_tree() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let backend = app.nodes.iter().position(|node| node.name == "backend").expect("backend");
+        app.nodes[backend].expanded = true;
+        let api_node = app.nodes.iter().position(|node| node.name == "api").expect("api");
+        let visible = app.visible_nodes();
+        app.select_node(api_node, &visible);
+
+        let mut browser = MockBrowser { opened: None };
+        let message = app.follow_upstream(&visible, &mut browser).expect("upstream");
+        assert_eq!(browser.opened.as_deref(), Some("https://gitlab.example.com/upstream/api"));
+        assert!(message.starts_with("opened upstream"));
+
+        let tools = app.nodes.iter().position(|node| node.name == "tools").expect("tools");
+        app.select_node(tools, &visible);
+        assert!(app.follow_upstream(&visible, &mut browser).is_err());
+    }
+
+    #[test]
+    fn retry_selected_refetches_failed_group() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let mut api = FakeGitLabApi::from_fixtures();
+        api.group_projects.remove(&2);
+        let mut app = App::from_api(&api, config).expect("partial load");
+        let backend = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "backend")
+            .expect("backend");
+        let visible = app.visible_nodes();
+        app.select_node(backend, &visible);
+
+        let action = app.retry_selected(&visible);
+        let KeyAction::Fetch(request) = action else {
+            panic!("expected fetch action");
         };
+        assert_eq!(request, FetchRequest::GroupProjects { node_id: backend, group_id: 2 });
+        assert!(matches!(app.retry_selected(&visible), KeyAction::None));
+        assert_eq!(app.status.as_deref(), Some("already retrying platform/backend"));
+
+        let api = FakeGitLabApi::from_fixtures();
+        for _ in 0..2 {
+            let payload = run_fetch(&api, &request);
+            app.apply_fetch(FetchResult { request: request.clone(), payload });
+        }
+
+        assert!(app.nodes[backend].error.is_none());
+        assert!(!app.nodes[backend].loading);
+        let children: Vec<&str> = app.nodes[backend]
+            .children
+            .iter()
+            .map(|id| app.nodes[*id].name.as_str())
+            .collect();
+        assert_eq!(children, vec!["api", "auth"]);
+        assert_eq!(app.parent[app.nodes[backend].children[0]], Some(backend));
+    }
 
+    #[test]
+    fn lazy_fetch_loads_project_details_once() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let tools = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "tools")
+            .expect("tools");
+        let visible = app.visible_nodes();
+        app.select_node(tools, &visible);
         let visible = app.visible_nodes();
-        assert_eq!(visible.len(), 1);
-        assert_eq!(visible[0].id, root);
 
-        app.nodes[root].expanded = true;
+        let request = app.lazy_fetch(&visible).expect("details request");
+        assert_eq!(request, FetchRequest::ProjectDetails { project_id: 10 });
+        assert!(app.lazy_fetch(&visible).is_none());
+
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+
+        let details = app.selected_project_details(&visible).expect("details");
+        assert_eq!(details.state, DetailState::Loaded);
+        let lines = format_project_details(details, &test_config().time, SystemTime::now());
+        assert!(lines.contains(&"Default branch: main".to_string()));
+        assert!(lines.contains(&"Topics: ci, tooling".to_string()));
+        assert!(lines.contains(&"Stars: 4 | Forks: 2 | Open issues: 9".to_string()));
+        assert!(lines.contains(&"Creator: @alice".to_string()));
+        assert!(lines.contains(&"Repository size: 3.0 MiB".to_string()));
+        let ssh = "Clone (SSH): git@gitlab.example.com:platform/tools.git";
+        assert!(lines.contains(&ssh.to_string()));
+        assert!(matches!(app.lazy_fetch(&visible), Some(FetchRequest::Readme { .. })));
+        assert!(app.lazy_fetch(&visible).is_none());
+    }
+
+    #[test]
+    fn lazy_fetch_records_failed_details() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let auth = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "auth")
+            .expect("auth");
+        for node in &mut app.nodes {
+            node.expanded = true;
+        }
         let visible = app.visible_nodes();
-        assert_eq!(visible.len(), 2);
-        assert_eq!(visible[0].id, root);
-        assert_eq!(visible[1].id, child);
+        app.select_node(auth, &visible);
+        let visible = app.visible_nodes();
+
+        let request = app.lazy_fetch(&visible).expect("details request");
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+
+        let details = app.selected_project_details(&visible).expect("details");
+        assert!(matches!(details.state, DetailState::Failed(_)));
+        let lines = format_project_details(details, &test_config().time, SystemTime::now());
+        assert!(lines.iter().any(|line| line.starts_with("Details unavailable")));
+        assert!(lines.contains(&"Project ID: 21".to_string()));
+        assert!(app.lazy_fetch(&visible).is_none());
+
+        assert!(matches!(app.retry_selected(&visible), KeyAction::None));
+        assert_eq!(app.status.as_deref(), Some("retrying details for platform/backend/auth"));
+        let request = app.lazy_fetch(&visible).expect("details retried");
+        assert_eq!(request, FetchRequest::ProjectDetails { project_id: 21 });
     }
 
     #[test]
-    fn config_from_env_reader_requires_token_and_defaults_url() {
-        let reader = |key: &str| match key {
-            "GITLAB_TOKEN" => Some("token".to_string()),
-            _ => None,
-        };
+    fn lazy_fetch_loads_group_details() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let platform = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "platform")
+            .expect("platform");
+        let visible = app.visible_nodes();
+        app.select_node(platform, &visible);
+
+        let request = app.lazy_fetch(&visible).expect("details request");
+        let username = Some("alice".to_string());
+        assert_eq!(request, FetchRequest::GroupDetails { group_id: 1, username });
+        assert!(app.lazy_fetch(&visible).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+
+        let details = app.selected_group_details(&visible).expect("details");
+        assert_eq!(details.state, DetailState::Loaded);
+        let summary = &app.group_stats[&platform];
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let lines = format_group_details(details, summary, &test_config().time, now);
+        assert!(lines.contains(&"Group ID: 1".to_string()));
+        assert!(lines.contains(&"Full name: Platform".to_string()));
+        assert!(lines.contains(&"Description: Platform engineering".to_string()));
+        assert!(lines.contains(&"Members: 3".to_string()));
+        assert!(lines.contains(&"My access: Maintainer".to_string()));
+        assert!(lines.contains(&"Created: 3 years ago (2021-02-03 10:00 UTC)".to_string()));
+    }
 
-        let config = Config::from_env_reader(reader).expect("config should load");
-        assert_eq!(config.gitlab_url, "https://gitlab.com");
-        assert_eq!(config.gitlab_token, "token");
-        assert_eq!(config.filters.per_page, 100);
-        assert!(config.filters.all_available.is_none());
-        assert_eq!(config.cache_ttl.as_secs(), 300);
-        assert!(config
-            .cache_path
-            .ends_with(PathBuf::from("gitlab-tree").join("cache.json")));
-        assert_eq!(config.group_sort, SortOrder::Alpha);
-        assert_eq!(config.project_sort, SortOrder::Alpha);
+    #[test]
+    fn group_stats_count_direct_and_recursive_children() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let app = App::from_api(&api, config).expect("load");
+        let platform = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "platform")
+            .expect("platform");
+
+        let summary = &app.group_stats[&platform];
+        assert_eq!(summary.direct_subgroups, 1);
+        assert_eq!(summary.subgroups, 1);
+        assert_eq!(summary.direct_projects, 1);
+        assert_eq!(summary.projects, 3);
+        assert_eq!(
+            summary.latest_project,
+            Some((
+                "platform/backend/api".to_string(),
+                "2024-03-02T08:30:00Z".to_string()
+            ))
+        );
     }
 
     #[test]
-    fn config_from_env_reader_fails_without_token() {
-        let reader = |_key: &str| None;
-        let result = Config::from_env_reader(reader);
-        assert!(result.is_err());
+    fn group_stats_roll_up_descendant_projects() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let position = |name: &str| app.nodes.iter().position(|node| node.name == name);
+        let platform = position("platform").expect("platform");
+        let tools = position("tools").expect("tools");
+        app.nodes[tools].visibility = "public".to_string();
+        for (project_id, size) in [(10, 3 * 1024 * 1024), (20, 1024 * 1024)] {
+            let details = app.project_details.get_mut(&project_id).expect("details");
+            let project = details.project.as_mut().expect("project");
+            project.statistics = Some(GitLabProjectStatistics {
+                repository_size: size,
+                ..GitLabProjectStatistics::default()
+            });
+        }
+
+        app.resort();
+        let stats = app.group_stats[&platform].clone();
+        assert_eq!(stats.projects, 3);
+        assert_eq!(stats.public_projects, 1);
+        assert_eq!(stats.repository_size, 4 * 1024 * 1024);
+        assert_eq!(stats.sized_projects, 2);
+        assert_eq!(stats.latest_activity, parse_timestamp("2024-03-02T08:30:00Z"));
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400 + 10 * 86_400);
+        assert_eq!(format_group_stats(&stats, now), "3 proj, 1 public, 4.0 MiB+, 9d");
     }
 
     #[test]
-    fn config_from_env_reader_parses_filters() {
-        let reader = |key: &str| match key {
-            "GITLAB_TOKEN" => Some("token".to_string()),
-            "GITLAB_ALL_AVAILABLE" => Some("true".to_string()),
-            "GITLAB_OWNED" => Some("0".to_string()),
-            "GITLAB_TOP_LEVEL_ONLY" => Some("yes".to_string()),
-            "GITLAB_INCLUDE_SUBGROUPS" => Some("on".to_string()),
-            "GITLAB_VISIBILITY" => Some("private".to_string()),
-            "GITLAB_PER_PAGE" => Some("50".to_string()),
-            "GITLAB_CACHE_TTL_SECONDS" => Some("120".to_string()),
-            "GITLAB_CACHE_PATH" => Some("/tmp/gitlab-tree-cache.json".to_string()),
-            "GITLAB_GROUP_SORT" => Some("activity".to_string()),
-            "GITLAB_PROJECT_SORT" => Some("alpha".to_string()),
-            _ => None,
-        };
+    fn lazy_fetch_loads_readme_after_details() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let tools = app
+            .nodes
+            .iter()
+            .position(|node| node.name == "tools")
+            .expect("tools");
+        let visible = app.visible_nodes();
+        app.select_node(tools, &visible);
 
-        let config = Config::from_env_reader(reader).expect("config should load");
-        assert_eq!(config.filters.per_page, 50);
-        assert_eq!(config.filters.all_available, Some(true));
-        assert_eq!(config.filters.owned, Some(false));
-        assert_eq!(config.filters.top_level_only, Some(true));
-        assert_eq!(config.filters.include_subgroups, Some(true));
-        assert_eq!(config.filters.visibility.as_deref(), Some("private"));
-        assert_eq!(config.cache_ttl.as_secs(), 120);
+        let request = app.lazy_fetch(&visible).expect("details request");
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        let request = app.lazy_fetch(&visible).expect("readme request");
         assert_eq!(
-            config.cache_path,
-            PathBuf::from("/tmp/gitlab-tree-cache.json")
+            request,
+            FetchRequest::Readme {
+                project_id: 10,
+                path: "docs/README.md".to_string(),
+                ref_name: "main".to_string(),
+            }
         );
-        assert_eq!(config.group_sort, SortOrder::Activity);
-        assert_eq!(config.project_sort, SortOrder::Alpha);
+        assert!(app.lazy_fetch(&visible).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+
+        let readme = app.selected_readme(&visible).expect("readme");
+        assert_eq!(readme.state, DetailState::Loaded);
+        assert!(readme.content.as_deref().unwrap_or("").starts_with("# Tools"));
+        app.scroll_readme(&visible, 3);
+        app.scroll_readme(&visible, -1);
+        assert_eq!(app.selected_readme(&visible).map(|readme| readme.scroll), Some(2));
+        app.scroll_readme(&visible, -10);
+        assert_eq!(app.selected_readme(&visible).map(|readme| readme.scroll), Some(0));
     }
 
     #[test]
-    fn config_from_env_reader_rejects_invalid_bool() {
-        let reader = |key: &str| match key {
-            "GITLAB_TOKEN" => Some("token".to_string()),
-            "GITLAB_OWNED" => Some("maybe".to_string()),
-            _ => None,
+    fn render_markdown_formats_headings_lists_code_and_links() {
+        let text = fixture("project_10_readme.md");
+        let lines: Vec<String> = render_markdown(text)
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert_eq!(lines[0], "Tools");
+        assert_eq!(lines[4], "Usage");
+        assert_eq!(lines[6], "• Run make build");
+        assert_eq!(
+            lines[7],
+            "• See the handbook <https://gitlab.example.com/handbook>"
+        );
+        assert_eq!(lines[9], "  make test");
+        let heading = &render_markdown("# Title")[0].spans[0];
+        assert!(heading.style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn encode_path_segment_escapes_separators() {
+        assert_eq!(encode_path_segment("docs/README.md"), "docs%2FREADME%2Emd");
+    }
+
+    fn select_named(app: &mut App, name: &str) -> usize {
+        let node_id = app
+            .nodes
+            .iter()
+            .position(|node| node.name == name)
+            .unwrap_or_else(|| panic!("missing node {name}"));
+        let visible = app.visible_nodes();
+        app.select_node(node_id, &visible);
+        node_id
+    }
+
+    fn press(app: &mut App, api: &FakeGitLabApi, code: KeyCode) {
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let action = app
+            .handle_key(key_event(code, KeyModifiers::NONE), &visible, None, &mut browser)
+            .expect("handle key");
+        if let KeyAction::Fetch(request) = action {
+            let payload = run_fetch(api, &request);
+            app.apply_fetch(FetchResult { request, payload });
+        }
+    }
+
+    #[test]
+    fn repository_browser_loads_tree_on_demand() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let tools = select_named(&mut app, "tools");
+
+        press(&mut app, &api, KeyCode::Char('l'));
+        assert!(app.nodes[tools].expanded);
+        let names: Vec<&str> = app.nodes[tools]
+            .children
+            .iter()
+            .map(|id| app.nodes[*id].name.as_str())
+            .collect();
+        assert_eq!(names, vec!["docs", "src", ".gitlab-ci.yml", "Makefile"]);
+        let src = app.nodes[tools].children[1];
+        assert_eq!(app.parent[src], Some(tools));
+        assert_eq!(app.nodes[src].url, "https://gitlab.example.com/platform/tools/-/tree/main/src");
+
+        select_named(&mut app, "src");
+        press(&mut app, &api, KeyCode::Char('l'));
+        let main_rs = app.nodes[src].children[0];
+        assert!(app.nodes[main_rs].kind == NodeKind::File);
+        assert_eq!(app.nodes[main_rs].path, "platform/tools/src/main.rs");
+        press(&mut app, &api, KeyCode::Char('h'));
+        assert!(!app.nodes[src].expanded);
+    }
+
+    #[test]
+    fn repository_browser_opens_file_pager_with_permalink() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        app.repo_refs.insert(10, "main".to_string());
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('l'));
+        select_named(&mut app, "src");
+        press(&mut app, &api, KeyCode::Char('l'));
+        select_named(&mut app, "main.rs");
+        press(&mut app, &api, KeyCode::Enter);
+
+        let pager = app.pager.as_ref().expect("pager");
+        assert_eq!(pager.state, DetailState::Loaded);
+        assert_eq!(pager.line_count(), 5);
+        assert_eq!(
+            pager.permalink(),
+            "https://gitlab.example.com/platform/tools/-/blob/\
+             1111111111111111111111111111111111111111/src/main.rs"
+        );
+        press(&mut app, &api, KeyCode::Char('G'));
+        assert_eq!(app.pager.as_ref().map(|pager| pager.scroll), Some(4));
+
+        let visible = app.visible_nodes();
+        let mut clipboard = MockClipboard { text: None };
+        let mut browser = MockBrowser { opened: None };
+        app.handle_key(
+            key_event(KeyCode::Char('y'), KeyModifiers::NONE),
+            &visible,
+            Some(&mut clipboard),
+            &mut browser,
+        )
+        .expect("yank");
+        assert!(clipboard.text.as_deref().is_some_and(|url| url.ends_with("/src/main.rs")));
+        press(&mut app, &api, KeyCode::Char('q'));
+        assert!(app.pager.is_none());
+    }
+
+    #[test]
+    fn ref_picker_switches_branch_and_reloads_tree() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let loaded = app.nodes.len();
+        let tools = select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('l'));
+        let src = select_named(&mut app, "src");
+        press(&mut app, &api, KeyCode::Char('l'));
+        let stale = FetchRequest::RepoTree {
+            node_id: src,
+            project_id: 10,
+            path: "src".to_string(),
+            ref_name: "main".to_string(),
+        };
+        select_named(&mut app, "Makefile");
+
+        press(&mut app, &api, KeyCode::Char('b'));
+        let picker = app.ref_picker.as_ref().expect("picker");
+        assert_eq!(picker.node_id, tools);
+        assert_eq!(picker.refs.len(), 4);
+        press(&mut app, &api, KeyCode::Char('j'));
+        press(&mut app, &api, KeyCode::Enter);
+
+        assert!(app.ref_picker.is_none());
+        assert_eq!(app.repo_refs.get(&10).map(String::as_str), Some("develop"));
+        assert!(app.nodes[tools].expanded);
+        assert_eq!(app.nodes[tools].children.len(), 4);
+        assert_eq!(app.nodes.len(), loaded + 4);
+        assert_eq!(app.repo_trees.keys().collect::<Vec<_>>(), vec![&tools]);
+        assert_eq!(app.parent.len(), app.nodes.len());
+        let visible = app.visible_nodes();
+        assert_eq!(visible[app.selected].id, tools);
+        let first = app.nodes[tools].children[0];
+        assert!(app.nodes[first].url.contains("/-/tree/develop/"));
+
+        let payload = run_fetch(&api, &stale);
+        app.apply_fetch(FetchResult { request: stale, payload });
+        assert_eq!(app.nodes.len(), loaded + 4);
+    }
+
+    #[test]
+    fn highlight_line_marks_keywords_strings_and_comments() {
+        let spans = highlight_line("let name = \"x\"; // note", "src/main.rs");
+        let keyword = spans.iter().find(|span| span.content == "let").expect("keyword");
+        assert_eq!(keyword.style.fg, Some(Color::Magenta));
+        let string = spans.iter().find(|span| span.content == "\"x\"").expect("string");
+        assert_eq!(string.style.fg, Some(Color::Green));
+        let comment = spans.last().expect("comment");
+        assert_eq!(comment.content, "// note");
+        assert_eq!(highlight_line("plain", "notes.txt").len(), 1);
+    }
+
+    #[test]
+    fn merge_request_view_lists_and_filters_group_merge_requests() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "platform");
+
+        press(&mut app, &api, KeyCode::Char('M'));
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
+        };
+        assert_eq!(view.scope, ViewScope::Group(1));
+        assert_eq!(view.state, DetailState::Loaded);
+        assert_eq!(view.rows.len(), 2);
+        assert_eq!(view.rows[0].approvals, None);
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        assert_eq!(
+            format_merge_request_row(&view.rows[1], true, now),
+            "platform/backend/api!7 [draft] Rework token refresh | @carol -> develop \
+             | - | 1 day ago"
+        );
+
+        let request = app.poll_fetch(Instant::now()).expect("detail request");
+        assert!(app.poll_fetch(Instant::now()).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        press(&mut app, &api, KeyCode::Down);
+        let request = app.poll_fetch(Instant::now()).expect("second detail request");
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
+        };
+        assert_eq!(view.rows[0].pipeline.as_deref(), Some("success"));
+        assert_eq!(view.rows[0].approvals, Some((1, 2)));
+        assert_eq!(
+            format_merge_request_row(&view.rows[1], true, now),
+            "platform/backend/api!7 [draft] Rework token refresh | @carol -> develop \
+             | 0/1 approvals | 1 day ago"
+        );
+
+        press(&mut app, &api, KeyCode::Char('f'));
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
+        };
+        assert_eq!(view.filter, MergeRequestFilter::AssignedToMe);
+        let titles: Vec<&str> =
+            view.rows.iter().map(|row| row.merge_request.title.as_str()).collect();
+        assert_eq!(titles, vec!["Add lint target"]);
+
+        press(&mut app, &api, KeyCode::Char('f'));
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
         };
+        assert_eq!(view.filter, MergeRequestFilter::ReviewRequested);
+        assert_eq!(view.rows.len(), 1);
+        assert_eq!(view.rows[0].merge_request.iid, 7);
 
-        let result = Config::from_env_reader(reader);
-        assert!(result.is_err());
+        // Review requests are filtered by the token's owner without asking
+        // the API for the current user again.
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let key = key_event(KeyCode::Char('r'), KeyModifiers::NONE);
+        let KeyAction::Fetch(request) =
+            app.handle_key(key, &visible, None, &mut browser).expect("refresh")
+        else {
+            panic!("expected merge request refresh");
+        };
+        assert!(matches!(&request, FetchRequest::MergeRequests { username: Some(name), .. }
+            if name == "alice"));
+        let offline = FakeGitLabApi { user: "{}", ..FakeGitLabApi::from_fixtures() };
+        let Ok(FetchPayload::MergeRequests(rows)) = run_fetch(&offline, &request) else {
+            panic!("expected merge requests");
+        };
+        assert_eq!(rows.len(), 1);
+
+        let visible = app.visible_nodes();
+        let mut browser = MockBrowser { opened: None };
+        let key = key_event(KeyCode::Char('o'), KeyModifiers::NONE);
+        app.handle_key(key, &visible, None, &mut browser).expect("open");
+        assert_eq!(
+            browser.opened.as_deref(),
+            Some("https://gitlab.example.com/platform/backend/api/-/merge_requests/7")
+        );
+        press(&mut app, &api, KeyCode::Esc);
+        assert!(app.view.is_none());
     }
 
     #[test]
-    fn config_from_env_reader_rejects_invalid_sort() {
-        let reader = |key: &str| match key {
-            "GITLAB_TOKEN" => Some("token".to_string()),
-            "GITLAB_GROUP_SORT" => Some("random".to_string()),
-            _ => None,
+    fn merge_request_view_scopes_repository_entries_to_their_project() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('l'));
+        select_named(&mut app, "Makefile");
+
+        press(&mut app, &api, KeyCode::Char('M'));
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
         };
+        assert_eq!(view.scope, ViewScope::Project(10));
+        assert_eq!(view.path, "platform/tools");
+        assert_eq!(view.rows.len(), 1);
 
-        let result = Config::from_env_reader(reader);
-        assert!(result.is_err());
+        if let Some(View::MergeRequests(view)) = app.view.as_mut() {
+            view.rows[0].merge_request.iid = 999;
+        }
+        let request = app.poll_fetch(Instant::now()).expect("detail request");
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        let Some(View::MergeRequests(view)) = &app.view else {
+            panic!("expected merge request view");
+        };
+        assert_eq!(view.rows.len(), 1);
+        assert!(matches!(view.rows[0].state, DetailState::Failed(_)));
+        assert_eq!(view.rows[0].approvals, None);
     }
 
     #[test]
-    fn loading_message_cycles_frames() {
-        assert_eq!(loading_message(0), "| loading GitLab data...");
-        assert_eq!(loading_message(1), "/ loading GitLab data...");
-        assert_eq!(loading_message(2), "- loading GitLab data...");
-        assert_eq!(loading_message(3), "\\ loading GitLab data...");
-        assert_eq!(loading_message(4), "| loading GitLab data...");
+    fn parse_issue_query_maps_filters_to_api_params() {
+        let params = parse_issue_query(
+            "label:bug label:ci -label:wontfix assignee:@alice milestone:\"v1.1 beta\" lint target",
+        )
+        .expect("query");
+        assert_eq!(
+            params,
+            vec![
+                ("state", "opened".to_string()),
+                ("assignee_username", "alice".to_string()),
+                ("milestone", "v1.1 beta".to_string()),
+                ("labels", "bug,ci".to_string()),
+                ("not[labels]", "wontfix".to_string()),
+                ("search", "lint target".to_string()),
+            ]
+        );
+        let params = parse_issue_query("state:all assignee:none").expect("query");
+        assert_eq!(
+            params,
+            vec![("state", "all".to_string()), ("assignee_id", "None".to_string())]
+        );
+        assert!(parse_issue_query("weight:3").is_err());
+        assert!(parse_issue_query("label:\"open").is_err());
+        assert!(parse_issue_query("state:merged").is_err());
     }
 
     #[test]
-    fn format_node_details_includes_metadata() {
-        let node = Node {
-            name: "root".to_string(),
-            kind: NodeKind::Group,
-            children: Vec::new(),
-            expanded: false,
-            url: "https://example.com/root".to_string(),
-            path: "root".to_string(),
-            visibility: "private".to_string(),
-            last_activity: None,
+    fn issue_view_filters_with_query_and_shows_comments() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "platform");
+
+        press(&mut app, &api, KeyCode::Char('I'));
+        let Some(View::Issues(view)) = &app.view else {
+            panic!("expected issue view");
         };
+        assert_eq!(view.rows.len(), 2);
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        assert_eq!(
+            format_issue_row(&view.rows[0], true, now),
+            "platform/tools#3 [opened] Lint target ignores generated files | ~bug ~ci \
+             | @alice | %v1.1 | updated 2 days ago"
+        );
 
-        let lines = format_node_details(&node);
-        assert!(lines.iter().any(|line| line == "Name: root"));
-        assert!(lines.iter().any(|line| line == "Kind: Group"));
-        assert!(lines.iter().any(|line| line == "Path: root"));
-        assert!(lines.iter().any(|line| line == "Visibility: private"));
-        assert!(lines.iter().any(|line| line == "URL: https://example.com/root"));
-        assert!(!lines.iter().any(|line| line.starts_with("Last activity:")));
-    }
-
-    #[test]
-    fn format_node_details_includes_last_activity_when_present() {
-        let node = Node {
-            name: "proj".to_string(),
-            kind: NodeKind::Project,
-            children: Vec::new(),
-            expanded: false,
-            url: "https://example.com/root/proj".to_string(),
-            path: "root/proj".to_string(),
-            visibility: "internal".to_string(),
-            last_activity: Some("2024-01-01T00:00:00Z".to_string()),
+        press(&mut app, &api, KeyCode::Char('/'));
+        for _ in 0.."state:opened".len() {
+            press(&mut app, &api, KeyCode::Backspace);
+        }
+        for ch in "state:all label:bug".chars() {
+            press(&mut app, &api, KeyCode::Char(ch));
+        }
+        press(&mut app, &api, KeyCode::Enter);
+        let Some(View::Issues(view)) = &app.view else {
+            panic!("expected issue view");
         };
+        assert_eq!(view.query, "state:all label:bug");
+        let iids: Vec<usize> = view.rows.iter().map(|issue| issue.iid).collect();
+        assert_eq!(iids, vec![3, 2]);
 
-        let lines = format_node_details(&node);
-        assert!(lines
+        press(&mut app, &api, KeyCode::Enter);
+        let Some(View::Issues(view)) = &app.view else {
+            panic!("expected issue view");
+        };
+        let detail = view.detail.as_ref().expect("detail");
+        assert_eq!(detail.state, DetailState::Loaded);
+        let text: Vec<String> = format_issue_detail(detail, &app.config.time, SystemTime::now())
             .iter()
-            .any(|line| line == "Last activity: 2024-01-01T00:00:00Z"));
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert!(text.contains(&"Steps".to_string()));
+        assert!(text.contains(&"Comments (1)".to_string()));
+        assert!(text.contains(&"I can reproduce this with **make lint**.".to_string()));
+        assert!(text.iter().any(|line| line.starts_with('@') && line.contains(" ago (")));
+
+        press(&mut app, &api, KeyCode::Char('q'));
+        assert!(matches!(&app.view, Some(View::Issues(view)) if view.detail.is_none()));
+        press(&mut app, &api, KeyCode::Char('q'));
+        assert!(app.view.is_none());
     }
 
     #[test]
-    fn filter_visible_nodes_matches_query_case_insensitive() {
-        let nodes = vec![
-            Node {
-                name: "API".to_string(),
-                kind: NodeKind::Project,
-                children: Vec::new(),
-                expanded: false,
-                url: "https://example.com/api".to_string(),
-                path: "root/api".to_string(),
-                visibility: "private".to_string(),
-                last_activity: None,
-            },
-            Node {
-                name: "web".to_string(),
-                kind: NodeKind::Project,
-                children: Vec::new(),
-                expanded: false,
-                url: "https://example.com/web".to_string(),
-                path: "root/web".to_string(),
-                visibility: "private".to_string(),
-                last_activity: None,
-            },
-        ];
-        let visible = vec![
-            VisibleNode { id: 0, depth: 0 },
-            VisibleNode { id: 1, depth: 0 },
-        ];
+    fn pipeline_badges_load_for_visible_projects_once() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        for node in &mut app.nodes {
+            node.expanded = true;
+        }
+        let visible = app.visible_nodes();
 
-        let filtered = filter_visible_nodes(&visible, &nodes, "api");
-        assert_eq!(filtered.len(), 1);
-        assert_eq!(filtered[0].id, 0);
+        let now = Instant::now();
+        let request = app.pipeline_badge_fetch(&visible, now).expect("badge request");
+        let FetchRequest::PipelineBadges { projects } = &request else {
+            panic!("expected badge request");
+        };
+        assert!(projects.contains(&(10, Some("main".to_string()))));
+        assert!(app.pipeline_badge_fetch(&visible, now).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+
+        let status = |app: &App, id: usize| {
+            app.pipeline_badges.get(&id).and_then(|badge| badge.status().map(str::to_string))
+        };
+        assert_eq!(status(&app, 10).as_deref(), Some("failed"));
+        assert_eq!(status(&app, 20).as_deref(), Some("running"));
+        assert!(matches!(app.pipeline_badges.get(&21), Some(PipelineBadge::Missing(_))));
+        assert_eq!(pipeline_glyph("failed"), "✖");
+        assert_eq!(pipeline_color("failed"), Color::Red);
+
+        assert!(app.pipeline_badge_fetch(&visible, Instant::now()).is_none());
+        let later = Instant::now() + PIPELINE_BADGE_RETRY;
+        let retry = app.pipeline_badge_fetch(&visible, later).expect("retry request");
+        let FetchRequest::PipelineBadges { projects } = &retry else {
+            panic!("expected badge request");
+        };
+        let ids: Vec<usize> = projects.iter().map(|(id, _)| *id).collect();
+        assert!(ids.contains(&21));
+        assert!(!ids.contains(&10));
+
+        app.config.show_pipelines = false;
+        app.pipeline_badges.clear();
+        assert!(app.pipeline_badge_fetch(&visible, later).is_none());
     }
 
     #[test]
-    fn filter_visible_nodes_matches_fuzzy_subsequence() {
-        let nodes = vec![Node {
-            name: "gitlab".to_string(),
-            kind: NodeKind::Project,
-            children: Vec::new(),
-            expanded: false,
-            url: "https://example.com/gitlab".to_string(),
-            path: "root/gitlab".to_string(),
-            visibility: "private".to_string(),
-            last_activity: None,
-        }];
-        let visible = vec![VisibleNode { id: 0, depth: 0 }];
+    fn pipeline_view_lists_recent_pipelines_with_durations() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "platform");
+        press(&mut app, &api, KeyCode::Char('P'));
+        assert!(app.view.is_none());
+
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('P'));
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        {
+            let Some(View::Pipelines(view)) = &app.view else {
+                panic!("expected pipeline view");
+            };
+            assert_eq!(view.rows.len(), 2);
+            let row = format_pipeline_row(&view.rows[0], now);
+            assert_eq!(row, "#501 failed | main | - | push | 1 day ago");
+        }
 
-        let filtered = filter_visible_nodes(&visible, &nodes, "glb");
-        assert_eq!(filtered.len(), 1);
-        assert_eq!(filtered[0].id, 0);
+        let request = app.poll_fetch(Instant::now()).expect("detail request");
+        assert_eq!(request, FetchRequest::PipelineDetail { project_id: 10, pipeline_id: 501 });
+        assert!(app.poll_fetch(Instant::now()).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        let Some(View::Pipelines(view)) = &app.view else {
+            panic!("expected pipeline view");
+        };
+        assert_eq!(
+            format_pipeline_row(&view.rows[0], now),
+            "#501 failed | main | 12m 34s | push | 1 day ago"
+        );
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(7_380), "2h 03m");
+        let badge = app.pipeline_badges.get(&10);
+        assert_eq!(badge, Some(&PipelineBadge::Status("failed".to_string())));
     }
 
     #[test]
-    fn handle_key_returns_reload_on_r() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
+    fn parse_cli_reads_access_review_options() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_cli(&[]).expect("no command"), None);
+        assert_eq!(parse_cli(&args(&["--help"])).expect("help"), Some(CliCommand::Help));
+        let command = parse_cli(&args(&[
+            "access-review",
+            "--format",
+            "json",
+            "-o",
+            "review.json",
+            "--privileged-threshold",
+            "2",
+        ]))
+        .expect("parse");
+        assert_eq!(
+            command,
+            Some(CliCommand::AccessReview(AccessReviewOptions {
+                report: ReportOptions {
+                    format: ReportFormat::Json,
+                    output: Some(PathBuf::from("review.json")),
+                },
+                privileged_threshold: 2,
+            }))
         );
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        assert_eq!(
+            parse_cli(&args(&["visibility-audit", "--format", "json"])).expect("parse"),
+            Some(CliCommand::VisibilityAudit(ReportOptions {
+                format: ReportFormat::Json,
+                output: None,
+            }))
+        );
+        assert!(parse_cli(&args(&["visibility-audit", "--privileged-threshold", "2"])).is_err());
+        assert_eq!(
+            parse_cli(&args(&["stale-projects", "--days", "30"])).expect("parse"),
+            Some(CliCommand::StaleProjects(StaleProjectOptions {
+                report: ReportOptions::default(),
+                days: Some(30),
+            }))
+        );
+        assert!(parse_cli(&args(&["stale-projects", "--days", "soon"])).is_err());
+        assert!(parse_cli(&args(&["access-review", "--format", "xml"])).is_err());
+        assert!(parse_cli(&args(&["access-review", "--output"])).is_err());
+        assert!(parse_cli(&args(&["audit"])).is_err());
+    }
 
-        let visible = app.visible_nodes();
-        let mut browser = MockBrowser { opened: None };
-        let action = app
-            .handle_key(key_event(KeyCode::Char('r'), KeyModifiers::NONE), &visible, None, &mut browser)
-            .expect("handle key");
+    #[test]
+    fn stale_projects_groups_inactive_projects_by_parent() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let app = App::from_api(&api, config).expect("load");
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_200_000);
+
+        let groups = stale_projects(&app, 90, now);
+        let summary: Vec<(&str, Vec<(&str, u64)>)> = groups
+            .iter()
+            .map(|group| {
+                let projects = group
+                    .projects
+                    .iter()
+                    .map(|project| (project.path.as_str(), project.days_inactive))
+                    .collect();
+                (group.group.as_str(), projects)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("alice", vec![("alice/dotfiles", 112)]),
+                ("platform", vec![("platform/tools", 92)]),
+                ("platform/backend", vec![("platform/backend/api", 91)]),
+            ]
+        );
+        assert!(stale_projects(&app, 365, now).is_empty());
+        let csv = format_stale_projects(&groups, ReportFormat::Csv).expect("csv");
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.contains("platform,platform/tools,2024-03-01T12:00:00Z,92,false,"));
+    }
 
-        matches!(action, KeyAction::Reload);
-        if let KeyAction::Reload = action {
-        } else {
-            panic!("expected reload action");
-        }
+    #[test]
+    fn age_indicator_grades_by_days_since_activity() {
+        assert_eq!(format_compact_age(3), "3d");
+        assert_eq!(format_compact_age(95), "3mo");
+        assert_eq!(format_compact_age(800), "2y");
+        assert_eq!(age_color(10, 180), Color::Green);
+        assert_eq!(age_color(60, 180), Color::Yellow);
+        assert_eq!(age_color(120, 180), Color::LightRed);
+        assert_eq!(age_color(180, 180), Color::Red);
+        assert_eq!(age_color(20, 14), Color::Red);
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        assert_eq!(days_since("2024-02-10T09:00:00Z", now), Some(20));
+        assert_eq!(days_since("2024-03-02T08:30:00Z", now), Some(0));
     }
 
     #[test]
-    fn handle_key_ctrl_c_quits() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
+    fn visibility_audit_flags_projects_broader_than_their_group() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        let auth = app.nodes.iter().position(|node| node.name == "auth").expect("auth");
+        app.nodes[auth].visibility = "public".to_string();
+
+        let findings = visibility_audit(&app);
+        let rows: Vec<String> = findings.iter().map(format_visibility_row).collect();
+        assert_eq!(
+            rows,
+            vec![
+                "group platform/backend [internal] | in platform [private]",
+                "project platform/backend/api [internal] | in platform/backend [internal]",
+                "project platform/backend/auth [public] | in platform/backend [internal] \
+                 | public-in-private-group, broader-than-group",
+            ]
         );
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
-
+        let csv = format_visibility_audit(&findings, ReportFormat::Csv).expect("csv");
+        assert!(csv.ends_with(
+            "platform/backend/auth,project,public,platform/backend,internal,\
+             public-in-private-group;broader-than-group\n"
+        ));
+
+        press(&mut app, &api, KeyCode::Char('V'));
+        press(&mut app, &api, KeyCode::Char('f'));
+        press(&mut app, &api, KeyCode::Enter);
+        assert!(app.view.is_none());
         let visible = app.visible_nodes();
-        let mut browser = MockBrowser { opened: None };
-        let action = app
-            .handle_key(
-                key_event(KeyCode::Char('c'), KeyModifiers::CONTROL),
-                &visible,
-                None,
-                &mut browser,
+        assert_eq!(visible[app.selected].id, auth);
+    }
+
+    #[test]
+    fn access_review_flags_privileged_expiring_and_external_members() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let app = App::from_api(&api, config).expect("load");
+        let options = AccessReviewOptions { privileged_threshold: 1, ..Default::default() };
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let AccessReview { records, failures } = access_review(&app, &api, &options, now);
+        assert!(failures.is_empty());
+
+        let platform: Vec<&AccessRecord> =
+            records.iter().filter(|record| record.path == "platform").collect();
+        assert_eq!(platform.len(), 3);
+        assert_eq!(platform[0].flags, vec!["privileged-on-1-nodes"]);
+        assert_eq!(platform[2].username, "carol");
+        assert_eq!(platform[2].flags, vec!["expired"]);
+
+        let frank = records.iter().find(|record| record.username == "frank").expect("frank");
+        assert_eq!(frank.path, "platform/backend/api");
+        assert_eq!(frank.source, "shared/invited");
+        assert_eq!(frank.external, Some(true));
+        let bob = records
+            .iter()
+            .find(|record| record.username == "bob" && record.path == "platform/backend/api")
+            .expect("bob");
+        assert_eq!(bob.source, "inherited from platform/backend");
+        assert!(bob.flags.is_empty());
+
+        let csv = format_access_review(&records, ReportFormat::Csv).expect("csv");
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("path,kind,username,name,access_level,source,expires_at,external,flags")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(
+                "platform,group,alice,Alice Example,Maintainer,direct,,,\
+                 privileged-on-1-nodes"
             )
-            .expect("handle key");
+        );
+        assert!(csv.contains(
+            "platform/backend,group,dave,Dave Contractor,Developer,direct,2024-03-10,false,expiring"
+        ));
+        let json: Vec<serde_json::Value> =
+            serde_json::from_str(&format_access_review(&records, ReportFormat::Json).expect("json"))
+                .expect("valid json");
+        assert_eq!(json.len(), records.len());
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
 
-        if let KeyAction::Quit = action {
-        } else {
-            panic!("expected quit action");
-        }
+    #[test]
+    fn access_review_does_not_count_inherited_access_as_privileged() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let app = App::from_api(&api, config).expect("load");
+        let options = AccessReviewOptions { privileged_threshold: 2, ..Default::default() };
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let records = access_review(&app, &api, &options, now).records;
+
+        // alice is a direct Maintainer on platform only and inherits that role
+        // on backend and api below it.
+        let alice: Vec<&AccessRecord> =
+            records.iter().filter(|record| record.username == "alice").collect();
+        assert_eq!(alice.len(), 3);
+        assert!(alice.iter().all(|record| record.access_level == "Maintainer"));
+        assert!(records.iter().all(|record| record.flags.iter().all(|flag| {
+            !flag.starts_with("privileged")
+        })));
     }
 
     #[test]
-    fn handle_key_question_toggles_help() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
+    fn access_review_reports_failed_nodes_and_skips_external_for_non_admins() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi {
+            user: r#"{"id": 7, "username": "alice", "name": "Alice Example"}"#,
+            ..FakeGitLabApi::from_fixtures()
+        };
+        let mut app = App::from_api(&api, config).expect("load");
+        let tools = app.nodes.iter().position(|node| node.name == "tools").expect("tools");
+        app.nodes[tools].gitlab_id = Some(404);
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let review = access_review(&app, &api, &AccessReviewOptions::default(), now);
+
+        assert_eq!(review.failures.len(), 1);
+        assert!(review.failures[0].starts_with("members of platform/tools (projects/404) failed"));
+        let frank =
+            review.records.iter().find(|record| record.username == "frank").expect("frank");
+        assert_eq!(frank.external, None);
+        assert!(frank.flags.is_empty());
+        let csv = format_access_review(&review.records, ReportFormat::Csv).expect("csv");
+        assert!(csv.contains("platform/backend/api,project,frank,Frank Partner,"));
+        assert!(csv.lines().all(|line| !line.contains(",true,")));
+    }
+
+    #[test]
+    fn members_view_separates_direct_and_inherited_access() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        for node in &mut app.nodes {
+            node.expanded = true;
+        }
+        select_named(&mut app, "api");
+        press(&mut app, &api, KeyCode::Char('U'));
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        let Some(View::Members(view)) = &app.view else {
+            panic!("expected members view");
+        };
+        let rows: Vec<String> = view.rows.iter().map(|row| format_member_row(row, now)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                "@alice (Alice Example) | Maintainer | inherited from platform | no expiry \
+                 | 2FA on",
+                "@bob (Bob Example) | Maintainer | inherited from platform/backend | no expiry",
+                "@dave (Dave Contractor) | Developer | inherited from platform/backend \
+                 | expires 2024-03-10 (9d)",
+                "@frank (Frank Partner) | Developer | shared/invited | no expiry",
+                "@erin (Erin Example) | Reporter | direct | no expiry",
+                "@carol (Carol Example) | Guest | inherited from platform | expired 2024-02-15",
+            ]
         );
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
+
+        press(&mut app, &api, KeyCode::Char('/'));
+        for ch in "EXAMPLE".chars() {
+            press(&mut app, &api, KeyCode::Char(ch));
+        }
+        press(&mut app, &api, KeyCode::Enter);
+        let Some(View::Members(view)) = &app.view else {
+            panic!("expected members view");
         };
+        let names: Vec<&str> =
+            view.visible_rows().iter().map(|row| row.member.username.as_str()).collect();
+        assert_eq!(names, vec!["alice", "bob", "erin", "carol"]);
+    }
+
+    #[test]
+    fn refs_view_lists_branches_with_divergence_and_compare_urls() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "platform");
+        press(&mut app, &api, KeyCode::Char('B'));
+        assert!(app.view.is_none());
+
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('B'));
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        {
+            let Some(View::Refs(view)) = &app.view else {
+                panic!("expected refs view");
+            };
+            assert_eq!(view.branches.len(), 2);
+            assert_eq!(
+                format_branch_row(&view.branches[0], now),
+                "main [default] [protected] | 11111111 Merge branch 'feature/lint' into 'main' \
+                 | Alice | 1 day ago | -"
+            );
+            assert_eq!(
+                format_branch_row(&view.branches[1], now),
+                "develop | 22222222 Add release checklist | Bob | 10 days ago | ?"
+            );
+            assert!(view.compare_url().is_none());
+        }
+        assert!(app.poll_fetch(Instant::now()).is_none());
 
-        let visible = app.visible_nodes();
+        let mut clipboard = MockClipboard { text: None };
         let mut browser = MockBrowser { opened: None };
+        press(&mut app, &api, KeyCode::Down);
+        let request = app.poll_fetch(Instant::now()).expect("divergence request");
+        assert_eq!(
+            request,
+            FetchRequest::BranchDivergence {
+                project_id: 10,
+                branch: "develop".to_string(),
+                default: "main".to_string(),
+            }
+        );
+        assert!(app.poll_fetch(Instant::now()).is_none());
+        let payload = run_fetch(&api, &request);
+        app.apply_fetch(FetchResult { request, payload });
+        if let Some(View::Refs(view)) = &app.view {
+            assert_eq!(
+                format_branch_row(&view.branches[1], now),
+                "develop | 22222222 Add release checklist | Bob | 10 days ago | ↑1 ↓4"
+            );
+        }
         app.handle_key(
-            key_event(KeyCode::Char('?'), KeyModifiers::NONE),
-            &visible,
-            None,
-            &mut browser,
-        )
-        .expect("handle key");
-        assert!(app.show_help);
-
-        app.handle_key(
-            key_event(KeyCode::Char('?'), KeyModifiers::NONE),
-            &visible,
-            None,
+            key_event(KeyCode::Char('y'), KeyModifiers::NONE),
+            &[],
+            Some(&mut clipboard),
             &mut browser,
         )
-        .expect("handle key");
-        assert!(!app.show_help);
+        .expect("copy");
+        assert_eq!(
+            clipboard.text.as_deref(),
+            Some("https://gitlab.example.com/platform/tools/-/compare/main...develop")
+        );
     }
 
     #[test]
-    fn page_navigation_moves_selection_by_page() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
-        );
-        for idx in 0..20 {
-            let child = push_node(
-                &mut nodes,
-                &format!("child-{idx}"),
-                NodeKind::Project,
-                "https://example.com/root/child",
-                "root/child",
-                "private",
-                None,
+    fn refs_view_renders_release_notes_and_compares_tags() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('B'));
+        press(&mut app, &api, KeyCode::Tab);
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_294_400);
+        {
+            let Some(View::Refs(view)) = &app.view else {
+                panic!("expected refs view");
+            };
+            assert!(view.tab == RefTab::Tags);
+            assert_eq!(
+                format_tag_row(&view.tags[0], now),
+                "v1.1.0 | Release 1.1.0 | 11111111 Merge branch 'feature/lint' into 'main' \
+                 | Alice | 1 day ago"
+            );
+            assert_eq!(
+                view.compare_url().as_deref(),
+                Some("https://gitlab.example.com/platform/tools/-/compare/v1.0.0...v1.1.0")
             );
-            nodes[root].children.push(child);
         }
-        nodes[root].expanded = true;
 
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
+        press(&mut app, &api, KeyCode::Tab);
+        press(&mut app, &api, KeyCode::Enter);
+        let Some(View::Refs(view)) = &app.view else {
+            panic!("expected refs view");
+        };
+        assert_eq!(
+            format_release_row(&view.releases[1], now),
+            "Tools 1.0.0 (v1.0.0) | 1 month ago"
+        );
+        let notes = view.notes.as_ref().expect("release notes");
+        assert_eq!(notes.title, "Tools 1.1.0");
+        assert!(notes.markdown);
+        assert_eq!(notes.url, "https://gitlab.example.com/platform/tools/-/releases/v1.1.0");
+        let rendered: Vec<String> = render_markdown(notes.body.as_deref().unwrap_or_default())
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert_eq!(rendered[0], "Changes");
+    }
+
+    #[test]
+    fn parse_job_trace_tracks_sections_and_carriage_returns() {
+        let (lines, sections) = parse_job_trace(fixture("job_7003_trace.txt"));
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].name, "prepare_script");
+        assert!(sections[0].collapsed);
+        assert_eq!(sections[0].duration, Some(4));
+        assert_eq!(sections[1].name, "step_script");
+        assert!(!sections[1].collapsed);
+        assert_eq!(sections[1].duration, Some(312));
+
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[1].header, Some(0));
+        assert_eq!(lines[2].sections, vec![0]);
+        assert_eq!(lines[5].text, "Downloading crates 100%");
+        assert_eq!(lines[8].sections, Vec::<usize>::new());
+        let header: String = ansi_line(&lines[1].text)
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        assert_eq!(header, "Preparing environment");
+    }
+
+    #[test]
+    fn ansi_line_applies_sgr_colors() {
+        let line = ansi_line("\x1b[32;1mok\x1b[0m plain \x1b[38;5;208mx\x1b[38;2;1;2;3my\x1b[2K");
+        let spans: Vec<(&str, Style)> =
+            line.spans.iter().map(|span| (span.content.as_ref(), span.style)).collect();
+        assert_eq!(spans.len(), 4);
+        let bold_green = Style::default().fg(Color::Green).add_modifier(Modifier::BOLD);
+        assert_eq!(spans[0], ("ok", bold_green));
+        assert_eq!(spans[1], (" plain ", Style::default()));
+        assert_eq!(spans[2], ("x", Style::default().fg(Color::Indexed(208))));
+        assert_eq!(spans[3], ("y", Style::default().fg(Color::Rgb(1, 2, 3))));
+    }
+
+    fn job_log(app: &App) -> &JobLog {
+        let Some(View::Pipelines(view)) = &app.view else {
+            panic!("expected pipeline view");
         };
+        view.jobs.as_ref().and_then(|jobs| jobs.log.as_ref()).expect("job log")
+    }
 
-        let visible = app.visible_nodes();
-        app.page_down(visible.len(), 10);
-        assert_eq!(app.selected, 10);
+    #[test]
+    fn pipeline_jobs_open_folded_log() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('P'));
+        press(&mut app, &api, KeyCode::Enter);
+        {
+            let Some(View::Pipelines(view)) = &app.view else {
+                panic!("expected pipeline view");
+            };
+            let jobs = view.jobs.as_ref().expect("jobs panel");
+            let order: Vec<usize> = jobs.rows.iter().map(|job| job.id).collect();
+            assert_eq!(order, vec![7001, 7002, 7003, 7004]);
+            assert_eq!(format_job_row(&jobs.rows[2]), "unit #7003 | failed | 5m 12s");
+        }
 
-        app.page_up(visible.len(), 10);
-        assert_eq!(app.selected, 0);
+        press(&mut app, &api, KeyCode::Down);
+        press(&mut app, &api, KeyCode::Down);
+        press(&mut app, &api, KeyCode::Enter);
+        let log = job_log(&app);
+        assert_eq!(log.job.id, 7003);
+        assert_eq!(log.visible_lines().len(), 8);
+        assert_eq!(log.cursor, 7);
+        assert!(!log.is_live());
+
+        press(&mut app, &api, KeyCode::Char('g'));
+        press(&mut app, &api, KeyCode::Char('j'));
+        press(&mut app, &api, KeyCode::Enter);
+        let log = job_log(&app);
+        assert!(!log.follow);
+        assert_eq!(log.visible_lines().len(), 9);
+
+        press(&mut app, &api, KeyCode::Char('z'));
+        assert_eq!(job_log(&app).visible_lines().len(), 4);
+
+        press(&mut app, &api, KeyCode::Char('q'));
+        press(&mut app, &api, KeyCode::Char('q'));
+        let Some(View::Pipelines(view)) = &app.view else {
+            panic!("expected pipeline view");
+        };
+        assert!(view.jobs.is_none());
     }
 
     #[test]
-    fn handle_key_enter_toggles_expansion() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
-        );
-        let child = push_node(
-            &mut nodes,
-            "child",
-            NodeKind::Project,
-            "https://example.com/child",
-            "root/child",
-            "private",
-            None,
-        );
-        nodes[root].children.push(child);
+    fn running_job_log_tails_on_interval() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('P'));
+        press(&mut app, &api, KeyCode::Enter);
+        press(&mut app, &api, KeyCode::Enter);
+        let now = Instant::now();
+        assert!(app.poll_fetch(now).is_none());
+
+        if let Some(View::Pipelines(view)) = app.view.as_mut()
+            && let Some(log) = view.jobs.as_mut().and_then(|jobs| jobs.log.as_mut())
+        {
+            log.job.status = "running".to_string();
+            log.fetched_at = Some(now);
+        }
+        assert!(app.poll_fetch(now).is_none());
+        let request = app.poll_fetch(now + JOB_TAIL_INTERVAL).expect("tail request");
+        assert_eq!(request, FetchRequest::JobTrace { project_id: 10, job_id: 7001 });
+        assert!(app.poll_fetch(now + JOB_TAIL_INTERVAL * 2).is_none());
+    }
 
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
+    #[test]
+    fn job_retry_requires_api_scope() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('P'));
+        press(&mut app, &api, KeyCode::Enter);
+
+        press(&mut app, &api, KeyCode::Char('R'));
+        assert_eq!(app.status.as_deref(), Some("retry/cancel jobs needs api"));
+
+        if let Some(token) = app.token.as_mut() {
+            token.scopes.push("api".to_string());
+        }
+        press(&mut app, &api, KeyCode::Char('R'));
+        assert_eq!(app.status.as_deref(), Some("retry job 7001: new job 8001 pending"));
+        let request = app.poll_fetch(Instant::now()).expect("refresh jobs");
+        assert_eq!(request, FetchRequest::PipelineJobs { project_id: 10, pipeline_id: 501 });
+    }
+
+    #[test]
+    fn job_retry_follows_new_job_in_log() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
+        if let Some(token) = app.token.as_mut() {
+            token.scopes.push("api".to_string());
+        }
+        select_named(&mut app, "tools");
+        press(&mut app, &api, KeyCode::Char('P'));
+        press(&mut app, &api, KeyCode::Enter);
+        press(&mut app, &api, KeyCode::Down);
+        press(&mut app, &api, KeyCode::Down);
+        press(&mut app, &api, KeyCode::Enter);
+        assert_eq!(job_log(&app).job.id, 7003);
+
+        press(&mut app, &api, KeyCode::Char('R'));
+        assert_eq!(app.status.as_deref(), Some("retry job 7003: new job 8003 pending"));
+        let log = job_log(&app);
+        assert_eq!(log.job.id, 8003);
+        assert!(log.is_live());
+        assert!(log.lines.is_empty());
+        let Some(View::Pipelines(view)) = &app.view else {
+            panic!("expected pipeline view");
         };
+        let jobs = view.jobs.as_ref().expect("jobs panel");
+        assert!(jobs.rows.iter().any(|job| job.id == 8003));
+
+        let now = Instant::now();
+        let refresh = app.poll_fetch(now).expect("refresh jobs");
+        assert_eq!(refresh, FetchRequest::PipelineJobs { project_id: 10, pipeline_id: 501 });
+        let tail = app.poll_fetch(now).expect("tail new job");
+        assert_eq!(tail, FetchRequest::JobTrace { project_id: 10, job_id: 8003 });
+    }
+
+    #[test]
+    fn format_bytes_uses_binary_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
 
+    #[test]
+    fn retry_selected_ignores_healthy_nodes() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config("https://gitlab.example.com", &cache_dir);
+        let api = FakeGitLabApi::from_fixtures();
+        let mut app = App::from_api(&api, config).expect("load");
         let visible = app.visible_nodes();
-        let mut browser = MockBrowser { opened: None };
 
-        app.handle_key(
-            key_event(KeyCode::Enter, KeyModifiers::NONE),
-            &visible,
-            None,
-            &mut browser,
-        )
-            .expect("handle key");
-        assert!(app.nodes[root].expanded);
+        let action = app.retry_selected(&visible);
+        assert!(matches!(action, KeyAction::None));
+        assert_eq!(app.status.as_deref(), Some("nothing to retry"));
+    }
 
-        app.handle_key(
-            key_event(KeyCode::Enter, KeyModifiers::NONE),
-            &visible,
-            None,
-            &mut browser,
-        )
-            .expect("handle key");
-        assert!(!app.nodes[root].expanded);
+    #[test]
+    fn load_failed_shows_empty_tree_instead_of_sample_data() {
+        let app = App::load_failed(test_config(), "load error: boom".to_string());
+        assert!(app.nodes.is_empty());
+        assert!(app.visible_nodes().is_empty());
+        assert_eq!(app.status.as_deref(), Some("load error: boom"));
     }
 
     #[test]
-    fn apply_sorting_orders_children_and_roots() {
-        let mut nodes = Vec::new();
-        let zeta = push_node(
-            &mut nodes,
-            "zeta",
-            NodeKind::Group,
-            "https://example.com/zeta",
-            "zeta",
-            "private",
-            None,
-        );
-        let alpha = push_node(
-            &mut nodes,
-            "alpha",
-            NodeKind::Group,
-            "https://example.com/alpha",
-            "alpha",
-            "private",
-            None,
-        );
-        let beta = push_node(
-            &mut nodes,
-            "beta",
-            NodeKind::Project,
-            "https://example.com/alpha/beta",
-            "alpha/beta",
-            "private",
-            None,
-        );
-        let gamma = push_node(
-            &mut nodes,
-            "gamma",
-            NodeKind::Project,
-            "https://example.com/alpha/gamma",
-            "alpha/gamma",
-            "private",
-            None,
-        );
+    fn from_gitlab_follows_next_page_header() {
+        let server = FakeServer::start(|target| {
+            let page = query_param(target, "page").unwrap_or_else(|| "1".to_string());
+            match (request_path(target), page.as_str()) {
+                ("/api/v4/user", _) => FakeResponse::json(fixture("user.json")),
+                ("/api/v4/personal_access_tokens/self", _) => {
+                    FakeResponse::json(fixture("personal_access_token.json"))
+                }
+                ("/api/v4/groups", "1") => {
+                    let groups: Vec<serde_json::Value> =
+                        serde_json::from_str(fixture("groups.json")).expect("groups fixture");
+                    FakeResponse::json(&serde_json::to_string(&groups[..1]).expect("page 1"))
+                        .with_header("x-next-page", "2")
+                }
+                ("/api/v4/groups", "2") => {
+                    let groups: Vec<serde_json::Value> =
+                        serde_json::from_str(fixture("groups.json")).expect("groups fixture");
+                    FakeResponse::json(&serde_json::to_string(&groups[1..]).expect("page 2"))
+                        .with_header("x-next-page", "")
+                }
+                ("/api/v4/groups/1/projects", _) => {
+                    FakeResponse::json(fixture("group_1_projects.json"))
+                }
+                ("/api/v4/groups/2/projects", _) => {
+                    FakeResponse::json(fixture("group_2_projects.json"))
+                }
+                ("/api/v4/projects", _) => FakeResponse::json(fixture("owned_projects.json")),
+                _ => FakeResponse::status(404, r#"{"message":"404 Not Found"}"#),
+            }
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
 
-        nodes[alpha].children.extend([gamma, beta]);
+        let api = HttpGitLabApi::new(&config).expect("api");
+        let app = App::from_gitlab(&api).expect("load from fake server");
 
-        let mut roots = vec![zeta, alpha];
-        let config = test_config();
-        apply_sorting(&mut nodes, &mut roots, &config);
+        let groups = app
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Group))
+            .count();
+        let projects = app
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Project))
+            .count();
+        assert_eq!(groups, 3);
+        assert_eq!(projects, 4);
+        assert_eq!(app.token.expect("token info").username, "alice");
+        assert!(cache_dir.path().join("cache.json").exists());
+    }
 
-        assert_eq!(roots, vec![alpha, zeta]);
-        assert_eq!(nodes[alpha].children, vec![beta, gamma]);
+    #[test]
+    fn from_gitlab_reports_error_status() {
+        let server = FakeServer::start(|target| match request_path(target) {
+            "/api/v4/groups" => {
+                FakeResponse::status(500, r#"{"message":"500 Internal Server Error"}"#)
+            }
+            _ => FakeResponse::status(401, r#"{"message":"401 Unauthorized"}"#),
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
+
+        let api = HttpGitLabApi::new(&config).expect("api");
+        let err = App::from_gitlab(&api).err().expect("server error should fail the load");
+        assert!(err.to_string().contains("500"));
     }
 
     #[test]
-    fn apply_sorting_orders_by_activity() {
-        let mut nodes = Vec::new();
-        let recent = push_node(
-            &mut nodes,
-            "recent",
-            NodeKind::Project,
-            "https://example.com/recent",
-            "recent",
-            "private",
-            Some("2024-10-02T10:00:00Z".to_string()),
+    fn from_gitlab_reports_malformed_json() {
+        let server = FakeServer::start(|target| match request_path(target) {
+            "/api/v4/groups" => FakeResponse::json(fixture("groups.json")),
+            "/api/v4/groups/1/projects" => FakeResponse::json("[{\"name\": "),
+            _ => FakeResponse::json("[]"),
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
+
+        let api = HttpGitLabApi::new(&config).expect("api");
+        let app = App::from_gitlab(&api).expect("partial load");
+        let platform = app
+            .nodes
+            .iter()
+            .find(|node| node.name == "platform")
+            .expect("platform group");
+        assert!(platform.error.is_some());
+        assert!(!cache_dir.path().join("cache.json").exists());
+
+        let server = FakeServer::start(|_| FakeResponse::json("{\"groups\": "));
+        let config = fixture_config(&server.url, &cache_dir);
+        let api = HttpGitLabApi::new(&config).expect("api");
+        assert!(App::from_gitlab(&api).is_err());
+    }
+
+    #[test]
+    fn parse_next_link_finds_rel_next() {
+        let link = concat!(
+            "<https://gitlab.example.com/api/v4/projects?id_after=5>; rel=\"next\", ",
+            "<https://gitlab.example.com/api/v4/projects?id_after=0>; rel=\"first\""
         );
-        let older = push_node(
-            &mut nodes,
-            "older",
-            NodeKind::Project,
-            "https://example.com/older",
-            "older",
-            "private",
-            Some("2024-01-01T10:00:00Z".to_string()),
+        assert_eq!(
+            parse_next_link(link).as_deref(),
+            Some("https://gitlab.example.com/api/v4/projects?id_after=5")
         );
-        let mut roots = vec![older, recent];
-        let mut config = test_config();
-        config.group_sort = SortOrder::Activity;
+        assert_eq!(parse_next_link("<https://example.com>; rel=\"prev\""), None);
+        assert_eq!(parse_next_link(""), None);
+    }
+
+    #[test]
+    fn commit_count_reads_total_header_from_single_item_page() {
+        let server = FakeServer::start(|target| {
+            if request_path(target) != "/api/v4/projects/10/repository/commits"
+                || query_param(target, "per_page").as_deref() != Some("1")
+            {
+                return FakeResponse::status(400, "unexpected request");
+            }
+            match query_param(target, "ref_name").as_deref() {
+                Some("main..develop") => FakeResponse::json("[{}]").with_header("x-total", "7"),
+                Some("main..broken") => FakeResponse::json("[{}]").with_header("x-total", "many"),
+                _ => FakeResponse::json("[{}]"),
+            }
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
+        let api = HttpGitLabApi::new(&config).expect("api");
+
+        assert_eq!(api.commit_count(10, "main..develop").expect("count"), Some(7));
+        assert_eq!(api.commit_count(10, "develop..main").expect("capped"), None);
+        assert!(api.commit_count(10, "main..broken").is_err());
+        assert_eq!(format_commit_total(None), "10000+");
+    }
+
+    #[test]
+    fn group_details_survive_a_forbidden_member_list() {
+        let server = FakeServer::start(|target| match request_path(target) {
+            "/api/v4/groups/1" => FakeResponse::json(fixture("group_1.json")),
+            _ => FakeResponse::status(403, r#"{"message": "403 Forbidden"}"#),
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
+        let api = HttpGitLabApi::new(&config).expect("api");
+
+        let request = FetchRequest::GroupDetails { group_id: 1, username: Some("alice".into()) };
+        let Ok(FetchPayload::GroupDetails(group, member_count, access_level)) =
+            run_fetch(&api, &request)
+        else {
+            panic!("expected group details");
+        };
+        assert_eq!(group.id, 1);
+        assert_eq!(member_count, None);
+        assert_eq!(access_level, None);
+    }
+
+    #[test]
+    fn paginator_follows_keyset_link_header() {
+        let server = FakeServer::start(|target| {
+            let projects: Vec<serde_json::Value> =
+                serde_json::from_str(fixture("owned_projects.json")).expect("projects fixture");
+            if query_param(target, "pagination").as_deref() != Some("keyset") {
+                return FakeResponse::status(500, "offset pagination should not be used");
+            }
+            match query_param(target, "id_after").as_deref() {
+                None => FakeResponse::json(&serde_json::to_string(&projects[..1]).expect("page 1"))
+                    .with_header(
+                        "link",
+                        "<{base}/api/v4/projects?pagination=keyset&id_after=30>; rel=\"next\"",
+                    ),
+                Some("30") => {
+                    FakeResponse::json(&serde_json::to_string(&projects[1..]).expect("page 2"))
+                }
+                Some(_) => FakeResponse::status(400, "unexpected cursor"),
+            }
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config(&server.url, &cache_dir);
+        config.filters.keyset_pagination = true;
+        let client = build_http_client(&config.http).expect("client");
+
+        let url = format!("{}/api/v4/projects", server.url);
+        let projects: Vec<GitLabProject> = Paginator::new(&client, &config, url, Vec::new())
+            .keyset("id")
+            .fetch_all()
+            .expect("keyset pages");
+
+        let names: Vec<&str> = projects.iter().map(|project| project.name.as_str()).collect();
+        assert_eq!(names, vec!["dotfiles", "tools"]);
+    }
+
+    #[test]
+    fn paginator_falls_back_to_offset_when_keyset_is_rejected() {
+        let server = FakeServer::start(|target| {
+            if query_param(target, "pagination").is_some() {
+                return FakeResponse::status(400, r#"{"error":"keyset pagination not supported"}"#);
+            }
+            match query_param(target, "page").as_deref() {
+                Some("1") => FakeResponse::json(fixture("groups.json")),
+                _ => FakeResponse::status(404, "missing page"),
+            }
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config(&server.url, &cache_dir);
+        config.filters.keyset_pagination = true;
+        let client = build_http_client(&config.http).expect("client");
 
-        apply_sorting(&mut nodes, &mut roots, &config);
-        assert_eq!(roots, vec![recent, older]);
+        let groups = fetch_groups(&client, &config).expect("offset fallback");
+        assert_eq!(groups.len(), 2);
     }
 
     #[test]
-    fn select_clipboard_prefers_arboard() {
-        let probe = MockClipboardProbe {
-            arboard_ok: true,
-            has_wayland: true,
-            has_display: true,
-            has_tmux: true,
-            has_wl_copy: true,
-            has_xclip: true,
-        };
-
-        assert_eq!(
-            select_clipboard_backend(&probe),
-            ClipboardBackend::Arboard
-        );
+    fn paginator_fetches_remaining_pages_from_total_pages() {
+        let server = FakeServer::start(|target| {
+            let page: usize = query_param(target, "page")
+                .and_then(|page| page.parse().ok())
+                .unwrap_or(0);
+            if !(1..=5).contains(&page) {
+                return FakeResponse::status(404, "missing page");
+            }
+            let body = format!(
+                r#"[{{"id":{page},"name":"group-{page}","web_url":"https://example.com/g{page}","full_path":"g{page}","visibility":"private"}}]"#
+            );
+            FakeResponse::json(&body)
+                .with_header("x-total-pages", "5")
+                .with_header("x-next-page", if page < 5 { "ignored" } else { "" })
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config(&server.url, &cache_dir);
+        config.filters.page_concurrency = 2;
+        let client = build_http_client(&config.http).expect("client");
+
+        let groups = fetch_groups(&client, &config).expect("all pages");
+        let ids: Vec<usize> = groups.iter().map(|group| group.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
     }
 
     #[test]
-    fn select_clipboard_uses_wl_copy_on_wayland() {
-        let probe = MockClipboardProbe {
-            arboard_ok: false,
-            has_wayland: true,
-            has_display: true,
-            has_tmux: false,
-            has_wl_copy: true,
-            has_xclip: true,
-        };
+    fn load_graphql_builds_groups_and_projects_across_pages() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config("https://gitlab.example.com", &cache_dir);
+        config.loader = LoaderKind::GraphQl;
+        let api = FakeGitLabApi::from_fixtures();
+
+        let (groups, projects, personal) = load_graphql(&api, &config).expect("graphql load");
+
+        let group_ids: Vec<(usize, Option<usize>)> =
+            groups.iter().map(|group| (group.id, group.parent_id)).collect();
+        assert_eq!(group_ids, vec![(1, None), (2, Some(1))]);
+        let backend = projects.iter().find(|entry| entry.group_id == 2).expect("backend");
+        assert_eq!(backend.projects.len(), 2);
+        let api_project = &backend.projects[0];
+        assert_eq!(api_project.id, 20);
+        assert_eq!(api_project.description.as_deref(), Some("Public API"));
+        assert_eq!(api_project.star_count, Some(12));
+        assert!(backend.projects[1].archived);
+        let platform = projects.iter().find(|entry| entry.group_id == 1).expect("platform");
+        assert_eq!(platform.projects[0].default_branch.as_deref(), Some("main"));
+        let personal = personal.expect("personal projects");
+        assert_eq!(personal.username, "alice");
+        assert_eq!(personal.projects.len(), 1);
+        assert_eq!(personal.projects[0].name, "dotfiles");
+    }
 
-        assert_eq!(
-            select_clipboard_backend(&probe),
-            ClipboardBackend::WlCopy
-        );
+    #[test]
+    fn from_api_uses_graphql_loader_when_configured() {
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let mut config = fixture_config("https://gitlab.example.com", &cache_dir);
+        config.loader = LoaderKind::GraphQl;
+        let mut api = FakeGitLabApi::from_fixtures();
+        api.group_projects.clear();
+
+        let app = App::from_api(&api, config).expect("graphql tree");
+        let names: Vec<&str> = app.roots.iter().map(|id| app.nodes[*id].name.as_str()).collect();
+        assert_eq!(names, vec!["alice", "platform"]);
+        let status = app.status.as_deref().unwrap_or_default();
+        assert!(status.ends_with("graphql: no forks/mirrors/shares"));
+        let warned = app.config.log.entries().iter().any(|entry| {
+            entry.level == LogLevel::Warn && entry.message == GRAPHQL_UNSUPPORTED
+        });
+        assert!(warned);
+        let projects = app
+            .nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Project))
+            .count();
+        assert_eq!(projects, 4);
     }
 
     #[test]
-    fn select_clipboard_uses_xclip_on_x11() {
-        let probe = MockClipboardProbe {
-            arboard_ok: false,
-            has_wayland: false,
-            has_display: true,
-            has_tmux: false,
-            has_wl_copy: false,
-            has_xclip: true,
+    fn graphql_loader_rejects_rest_only_filters_and_missing_visibility() {
+        let reader = |key: &str| match key {
+            "GITLAB_TOKEN" => Some("token".to_string()),
+            "GITLAB_LOADER" => Some("graphql".to_string()),
+            "GITLAB_OWNED" => Some("true".to_string()),
+            _ => None,
         };
+        let err = Config::from_env_reader(reader).err().expect("owned filter rejected");
+        assert_eq!(err.to_string(), "GITLAB_OWNED is not supported with GITLAB_LOADER=graphql");
+
+        let project = serde_json::json!({
+            "id": "gid://gitlab/Project/10",
+            "name": "tools",
+            "fullPath": "platform/tools",
+            "webUrl": "https://gitlab.example.com/platform/tools",
+        });
+        assert!(serde_json::from_value::<GraphQlProject>(project).is_err());
+    }
 
-        assert_eq!(
-            select_clipboard_backend(&probe),
-            ClipboardBackend::Xclip
-        );
+    #[test]
+    fn parse_global_id_reads_trailing_number() {
+        assert_eq!(parse_global_id("gid://gitlab/Group/42"), Some(42));
+        assert_eq!(parse_global_id("gid://gitlab/Project/x"), None);
     }
 
     #[test]
-    fn select_clipboard_none_when_unavailable() {
-        let probe = MockClipboardProbe {
-            arboard_ok: false,
-            has_wayland: false,
-            has_display: false,
-            has_tmux: false,
-            has_wl_copy: false,
-            has_xclip: false,
-        };
+    fn event_log_filters_by_level_and_writes_json_lines() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("logs").join("gitlab-tree.log");
+        let log = EventLog::new(LogLevel::Info);
+        log.open_file(&path).expect("open log file");
+
+        log.record(LogLevel::Debug, "http", "ignored".to_string());
+        log.record(LogLevel::Info, "cache", "cache miss".to_string());
+        log.record(LogLevel::Error, "browser", "open failed".to_string());
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].category, "cache");
+        assert_eq!(entries[1].level, LogLevel::Error);
+        let contents = std::fs::read_to_string(&path).expect("read log file");
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["level"], "error");
+        assert_eq!(lines[1]["category"], "browser");
+        assert_eq!(lines[1]["message"], "open failed");
+    }
 
-        assert_eq!(
-            select_clipboard_backend(&probe),
-            ClipboardBackend::None
-        );
+    #[test]
+    fn event_log_keeps_most_recent_entries() {
+        let log = EventLog::new(LogLevel::Debug);
+        for index in 0..EventLog::CAPACITY + 5 {
+            log.record(LogLevel::Debug, "test", format!("event {index}"));
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), EventLog::CAPACITY);
+        assert_eq!(entries[0].message, "event 5");
     }
 
     #[test]
-    fn select_clipboard_uses_osc52_in_tmux() {
-        let probe = MockClipboardProbe {
-            arboard_ok: false,
-            has_wayland: false,
-            has_display: false,
-            has_tmux: true,
-            has_wl_copy: false,
-            has_xclip: false,
+    fn format_utc_timestamp_renders_iso_time() {
+        assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc_timestamp(1_709_294_400 + 3_723), "2024-03-01T13:02:03Z");
+        let entry = LogEntry {
+            timestamp: 3_723,
+            level: LogLevel::Warn,
+            category: "http",
+            message: "GET /api/v4/groups -> 500 in 12ms".to_string(),
         };
-
         assert_eq!(
-            select_clipboard_backend(&probe),
-            ClipboardBackend::Osc52
+            format_log_entry(&entry),
+            "01:02:03 warn  http      GET /api/v4/groups -> 500 in 12ms"
         );
     }
 
     #[test]
-    fn osc52_sequence_wraps_for_tmux() {
-        let sequence = osc52_sequence("test", true);
-        assert!(sequence.starts_with("\x1bPtmux;"));
-        assert!(sequence.contains("\x1b]52;c;"));
-        assert!(sequence.ends_with("\x1b\\"));
+    fn from_gitlab_logs_http_requests_and_cache_events() {
+        let server = FakeServer::start(|target| match request_path(target) {
+            "/api/v4/groups" => FakeResponse::json("[]"),
+            _ => FakeResponse::status(404, r#"{"message":"404 Not Found"}"#),
+        });
+        let cache_dir = tempfile::tempdir().expect("tempdir");
+        let config = fixture_config(&server.url, &cache_dir);
+        let log = config.log.clone();
+
+        let api = HttpGitLabApi::new(&config).expect("api");
+        App::from_gitlab(&api).expect("first load");
+        App::from_gitlab(&api).expect("second load");
+
+        let messages: Vec<String> = log
+            .entries()
+            .iter()
+            .map(|entry| format!("{} {}", entry.category, entry.message))
+            .collect();
+        assert!(messages.iter().any(|line| line.starts_with("cache cache miss")));
+        assert!(messages.iter().any(|line| line.starts_with("cache cache hit")));
+        assert!(messages
+            .iter()
+            .any(|line| line.starts_with("http GET /api/v4/groups?") && line.contains("-> 200 in")));
+        assert!(messages
+            .iter()
+            .any(|line| line.starts_with("http GET /api/v4/user") && line.contains("-> 404")));
     }
 
-    #[test]
-    fn from_gitlab_data_builds_parent_child_relationships() {
-        let groups = vec![
-            GitLabGroup {
-                id: 1,
-                name: "root".to_string(),
-                web_url: "https://example.com/root".to_string(),
-                full_path: "root".to_string(),
-                visibility: "private".to_string(),
-                parent_id: None,
-            },
-            GitLabGroup {
-                id: 2,
-                name: "child".to_string(),
-                web_url: "https://example.com/root/child".to_string(),
-                full_path: "root/child".to_string(),
-                visibility: "private".to_string(),
-                parent_id: Some(1),
-            },
-        ];
-        let projects = vec![GroupProjects {
-            group_id: 1,
-            projects: vec![GitLabProject {
-                name: "proj".to_string(),
-                web_url: "https://example.com/root/proj".to_string(),
-                path_with_namespace: "root/proj".to_string(),
-                visibility: "private".to_string(),
-                last_activity_at: Some("2024-01-01T00:00:00Z".to_string()),
-                namespace: Some(GitLabNamespace {
-                    kind: "group".to_string(),
-                }),
-            }],
-        }];
+    fn fixture(name: &str) -> &'static str {
+        match name {
+            "user.json" => include_str!("../tests/fixtures/user.json"),
+            "users.json" => include_str!("../tests/fixtures/users.json"),
+            "personal_access_token.json" => {
+                include_str!("../tests/fixtures/personal_access_token.json")
+            }
+            "groups.json" => include_str!("../tests/fixtures/groups.json"),
+            "group_1_projects.json" => include_str!("../tests/fixtures/group_1_projects.json"),
+            "group_2_projects.json" => include_str!("../tests/fixtures/group_2_projects.json"),
+            "owned_projects.json" => include_str!("../tests/fixtures/owned_projects.json"),
+            "group_1_shared_projects.json" => {
+                include_str!("../tests/fixtures/group_1_shared_projects.json")
+            }
+            "group_1_shared_groups.json" => {
+                include_str!("../tests/fixtures/group_1_shared_groups.json")
+            }
+            "graphql_groups.json" => include_str!("../tests/fixtures/graphql_groups.json"),
+            "graphql_projects.json" => include_str!("../tests/fixtures/graphql_projects.json"),
+            "project_10.json" => include_str!("../tests/fixtures/project_10.json"),
+            "group_1.json" => include_str!("../tests/fixtures/group_1.json"),
+            "project_10_readme.md" => include_str!("../tests/fixtures/project_10_readme.md"),
+            "project_10_tree.json" => include_str!("../tests/fixtures/project_10_tree.json"),
+            "project_10_tree_src.json" => {
+                include_str!("../tests/fixtures/project_10_tree_src.json")
+            }
+            "project_10_main.rs" => include_str!("../tests/fixtures/project_10_main.rs"),
+            "project_10_branches.json" => {
+                include_str!("../tests/fixtures/project_10_branches.json")
+            }
+            "project_10_tags.json" => include_str!("../tests/fixtures/project_10_tags.json"),
+            "project_10_releases.json" => {
+                include_str!("../tests/fixtures/project_10_releases.json")
+            }
+            "project_10_commit_counts.json" => {
+                include_str!("../tests/fixtures/project_10_commit_counts.json")
+            }
+            "merge_requests.json" => include_str!("../tests/fixtures/merge_requests.json"),
+            "issues.json" => include_str!("../tests/fixtures/issues.json"),
+            "pipelines.json" => include_str!("../tests/fixtures/pipelines.json"),
+            "pipeline_501_jobs.json" => include_str!("../tests/fixtures/pipeline_501_jobs.json"),
+            "job_7003_trace.txt" => include_str!("../tests/fixtures/job_7003_trace.txt"),
+            "issue_notes_10_3.json" => include_str!("../tests/fixtures/issue_notes_10_3.json"),
+            "members.json" => include_str!("../tests/fixtures/members.json"),
+            "merge_request_approvals.json" => {
+                include_str!("../tests/fixtures/merge_request_approvals.json")
+            }
+            "group_1_members.json" => include_str!("../tests/fixtures/group_1_members.json"),
+            _ => panic!("unknown fixture {name}"),
+        }
+    }
 
-        let app = App::from_gitlab_data(
-            groups,
-            projects,
-            None,
-            test_config(),
-            "groups: 2".to_string(),
-        );
+    struct FakeGitLabApi {
+        user: &'static str,
+        token: &'static str,
+        groups: &'static str,
+        group_projects: HashMap<usize, &'static str>,
+        shared_projects: HashMap<usize, &'static str>,
+        shared_groups: HashMap<usize, &'static str>,
+        owned_projects: &'static str,
+    }
 
-        assert_eq!(app.roots.len(), 1);
-        let root_id = app.roots[0];
-        assert_eq!(app.nodes[root_id].name, "root");
-        assert_eq!(app.nodes[root_id].children.len(), 2);
-        let mut child_ids = app.nodes[root_id].children.clone();
-        child_ids.sort_by_key(|id| app.nodes[*id].name.clone());
+    impl FakeGitLabApi {
+        fn group_members(&self, group_id: usize) -> Result<Vec<GitLabMember>> {
+            match group_id {
+                1 => Ok(serde_json::from_str(fixture("group_1_members.json"))?),
+                _ => anyhow::bail!("404 Not Found: group {group_id}"),
+            }
+        }
 
-        let child_id = child_ids[0];
-        let project_id = child_ids[1];
-        assert_eq!(app.nodes[child_id].name, "child");
-        assert_eq!(app.nodes[project_id].name, "proj");
-        assert_eq!(app.parent[child_id], Some(root_id));
-        assert_eq!(app.parent[project_id], Some(root_id));
+        fn from_fixtures() -> Self {
+            Self {
+                user: fixture("user.json"),
+                token: fixture("personal_access_token.json"),
+                groups: fixture("groups.json"),
+                group_projects: HashMap::from([
+                    (1, fixture("group_1_projects.json")),
+                    (2, fixture("group_2_projects.json")),
+                ]),
+                shared_projects: HashMap::from([(1, fixture("group_1_shared_projects.json"))]),
+                shared_groups: HashMap::from([(1, fixture("group_1_shared_groups.json"))]),
+                owned_projects: fixture("owned_projects.json"),
+            }
+        }
     }
 
-    #[test]
-    fn from_gitlab_data_adds_personal_projects_root() {
-        let personal = PersonalProjects {
-            username: "alice".to_string(),
-            web_url: "https://example.com/alice".to_string(),
-            projects: vec![GitLabProject {
-                name: "notes".to_string(),
-                web_url: "https://example.com/alice/notes".to_string(),
-                path_with_namespace: "alice/notes".to_string(),
-                visibility: "private".to_string(),
-                last_activity_at: None,
-                namespace: Some(GitLabNamespace {
-                    kind: "user".to_string(),
-                }),
-            }],
-        };
+    impl GitLabApi for FakeGitLabApi {
+        fn current_user(&self) -> Result<GitLabUser> {
+            Ok(serde_json::from_str(self.user)?)
+        }
 
-        let app = App::from_gitlab_data(
-            Vec::new(),
-            Vec::new(),
-            Some(personal),
-            test_config(),
-            "personal: 1".to_string(),
-        );
+        fn personal_access_token(&self) -> Result<GitLabPersonalAccessToken> {
+            Ok(serde_json::from_str(self.token)?)
+        }
 
-        assert_eq!(app.roots.len(), 1);
-        let root_id = app.roots[0];
-        assert_eq!(app.nodes[root_id].name, "alice");
-        assert_eq!(app.nodes[root_id].children.len(), 1);
-        let project_id = app.nodes[root_id].children[0];
-        assert_eq!(app.nodes[project_id].name, "notes");
-        assert_eq!(app.parent[project_id], Some(root_id));
-    }
+        fn groups(&self) -> Result<Vec<GitLabGroup>> {
+            Ok(serde_json::from_str(self.groups)?)
+        }
 
-    #[test]
-    fn vim_navigation_helpers_update_selection() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
-        );
-        let child = push_node(
-            &mut nodes,
-            "child",
-            NodeKind::Project,
-            "https://example.com/child",
-            "root/child",
-            "private",
-            None,
-        );
-        nodes[root].children.push(child);
-        nodes[root].expanded = true;
+        fn group_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>> {
+            match self.group_projects.get(&group_id) {
+                Some(body) => Ok(serde_json::from_str(body)?),
+                None => anyhow::bail!("404 Not Found: group {group_id}"),
+            }
+        }
+
+        fn shared_projects(&self, group_id: usize) -> Result<Vec<GitLabProject>> {
+            let body = self.shared_projects.get(&group_id).copied().unwrap_or("[]");
+            Ok(serde_json::from_str(body)?)
+        }
+
+        fn shared_groups(&self, group_id: usize) -> Result<Vec<GitLabGroup>> {
+            let body = self.shared_groups.get(&group_id).copied().unwrap_or("[]");
+            Ok(serde_json::from_str(body)?)
+        }
+
+        fn owned_projects(&self) -> Result<Vec<GitLabProject>> {
+            let projects: Vec<GitLabProject> = serde_json::from_str(self.owned_projects)?;
+            Ok(filter_personal_projects(projects))
+        }
+
+        fn project(&self, project_id: usize) -> Result<GitLabProject> {
+            match project_id {
+                10 => Ok(serde_json::from_str(fixture("project_10.json"))?),
+                _ => anyhow::bail!("404 Not Found: project {project_id}"),
+            }
+        }
+
+        fn user(&self, user_id: usize) -> Result<GitLabUser> {
+            match user_id {
+                7 => Ok(serde_json::from_str(self.user)?),
+                _ => {
+                    let users: HashMap<String, GitLabUser> =
+                        serde_json::from_str(fixture("users.json"))?;
+                    users
+                        .get(&user_id.to_string())
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("404 Not Found: user {user_id}"))
+                }
+            }
+        }
+
+        fn group(&self, group_id: usize) -> Result<GitLabGroup> {
+            match group_id {
+                1 => Ok(serde_json::from_str(fixture("group_1.json"))?),
+                _ => anyhow::bail!("404 Not Found: group {group_id}"),
+            }
+        }
+
+        fn members(&self, scope: ViewScope, inherited: bool) -> Result<Vec<GitLabMember>> {
+            if scope == ViewScope::Project(404) {
+                anyhow::bail!("404 Project Not Found");
+            }
+            let all: HashMap<String, Vec<GitLabMember>> =
+                serde_json::from_str(fixture("members.json"))?;
+            let direct = scope.api_path();
+            let key = if inherited { format!("{direct}/all") } else { direct.clone() };
+            Ok(all.get(&key).or_else(|| all.get(&direct)).cloned().unwrap_or_default())
+        }
+
+        fn group_member_count(&self, group_id: usize) -> Result<usize> {
+            Ok(self.group_members(group_id)?.len())
+        }
+
+        fn group_member(&self, group_id: usize, username: &str) -> Result<Option<GitLabMember>> {
+            let members = self.group_members(group_id)?;
+            Ok(members.into_iter().find(|member| member.username == username))
+        }
+
+        fn file_raw(&self, project_id: usize, path: &str, ref_name: &str) -> Result<String> {
+            match (project_id, path, ref_name) {
+                (10, "docs/README.md", "main") => Ok(fixture("project_10_readme.md").to_string()),
+                (10, "src/main.rs", "main" | "develop") => {
+                    Ok(fixture("project_10_main.rs").to_string())
+                }
+                _ => anyhow::bail!("404 Not Found: {project_id}/{path}@{ref_name}"),
+            }
+        }
+
+        fn repository_tree(
+            &self,
+            project_id: usize,
+            path: &str,
+            ref_name: &str,
+        ) -> Result<Vec<GitLabTreeEntry>> {
+            match (project_id, path, ref_name) {
+                (10, "", "HEAD" | "main" | "develop") => {
+                    Ok(serde_json::from_str(fixture("project_10_tree.json"))?)
+                }
+                (10, "src", "HEAD" | "main" | "develop") => {
+                    Ok(serde_json::from_str(fixture("project_10_tree_src.json"))?)
+                }
+                _ => anyhow::bail!("404 Tree Not Found: {project_id}/{path}@{ref_name}"),
+            }
+        }
+
+        fn commit(&self, project_id: usize, ref_name: &str) -> Result<GitLabCommit> {
+            let branches: Vec<serde_json::Value> =
+                serde_json::from_str(fixture("project_10_branches.json"))?;
+            let id = branches
+                .iter()
+                .find(|branch| project_id == 10 && branch["name"] == ref_name)
+                .and_then(|branch| branch["commit"]["id"].as_str())
+                .ok_or_else(|| anyhow::anyhow!("404 Commit Not Found"))?;
+            Ok(GitLabCommit { id: id.to_string(), ..GitLabCommit::default() })
+        }
+
+        fn branches(&self, project_id: usize) -> Result<Vec<GitLabRef>> {
+            match project_id {
+                10 => Ok(serde_json::from_str(fixture("project_10_branches.json"))?),
+                _ => anyhow::bail!("404 Project Not Found"),
+            }
+        }
 
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 1,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        fn tags(&self, project_id: usize) -> Result<Vec<GitLabRef>> {
+            match project_id {
+                10 => Ok(serde_json::from_str(fixture("project_10_tags.json"))?),
+                _ => anyhow::bail!("404 Project Not Found"),
+            }
+        }
 
-        app.move_top();
-        assert_eq!(app.selected, 0);
+        fn releases(&self, project_id: usize) -> Result<Vec<GitLabRelease>> {
+            match project_id {
+                10 => Ok(serde_json::from_str(fixture("project_10_releases.json"))?),
+                _ => anyhow::bail!("404 Project Not Found"),
+            }
+        }
 
-        app.move_bottom(2);
-        assert_eq!(app.selected, 1);
-    }
+        fn commit_count(&self, project_id: usize, range: &str) -> Result<Option<usize>> {
+            let counts: HashMap<String, usize> =
+                serde_json::from_str(fixture("project_10_commit_counts.json"))?;
+            match project_id {
+                10 => Ok(Some(counts.get(range).copied().unwrap_or(0))),
+                _ => anyhow::bail!("404 Project Not Found"),
+            }
+        }
 
-    #[test]
-    fn vim_navigation_pending_g_toggles() {
-        let mut app = App {
-            nodes: Vec::new(),
-            roots: Vec::new(),
-            parent: Vec::new(),
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        fn merge_requests(
+            &self,
+            scope: ViewScope,
+            query: Vec<(&'static str, String)>,
+        ) -> Result<Vec<GitLabMergeRequest>> {
+            let all: Vec<serde_json::Value> = serde_json::from_str(fixture("merge_requests.json"))?;
+            let param = |key: &str| {
+                query.iter().find(|(name, _)| *name == key).map(|(_, value)| value.as_str())
+            };
+            let has_user = |item: &serde_json::Value, field: &str| {
+                item[field]
+                    .as_array()
+                    .is_some_and(|users| users.iter().any(|user| user["username"] == "alice"))
+            };
+            let mut out = Vec::new();
+            for mut item in all {
+                let in_scope = match scope {
+                    ViewScope::Group(1) => true,
+                    ViewScope::Project(id) => item["project_id"] == id,
+                    ViewScope::Group(_) => false,
+                };
+                let assigned =
+                    param("scope") != Some("assigned_to_me") || has_user(&item, "assignees");
+                let reviewing =
+                    param("reviewer_username").is_none() || has_user(&item, "reviewers");
+                if in_scope && assigned && reviewing {
+                    item.as_object_mut().map(|object| object.remove("head_pipeline"));
+                    out.push(serde_json::from_value(item)?);
+                }
+            }
+            Ok(out)
+        }
 
-        assert!(!app.consume_pending_g());
-        app.set_pending_g();
-        assert!(app.consume_pending_g());
-        assert!(!app.consume_pending_g());
-    }
+        fn merge_request(&self, project_id: usize, iid: usize) -> Result<GitLabMergeRequest> {
+            let all: Vec<GitLabMergeRequest> =
+                serde_json::from_str(fixture("merge_requests.json"))?;
+            all.into_iter()
+                .find(|item| item.project_id == project_id && item.iid == iid)
+                .ok_or_else(|| anyhow::anyhow!("404 Merge Request Not Found"))
+        }
 
-    #[test]
-    fn yank_selected_copies_url() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
-        );
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        fn merge_request_approvals(
+            &self,
+            project_id: usize,
+            iid: usize,
+        ) -> Result<GitLabApprovals> {
+            let all: HashMap<String, GitLabApprovals> =
+                serde_json::from_str(fixture("merge_request_approvals.json"))?;
+            all.get(&format!("{project_id}/{iid}"))
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("404 Not Found"))
+        }
 
-        let visible = app.visible_nodes();
-        let mut clipboard = MockClipboard { text: None };
-        let url = app
-            .yank_selected(&visible, &mut clipboard)
-            .expect("yank should succeed");
+        fn issues(
+            &self,
+            scope: ViewScope,
+            query: Vec<(&'static str, String)>,
+        ) -> Result<Vec<GitLabIssue>> {
+            let all: Vec<GitLabIssue> = serde_json::from_str(fixture("issues.json"))?;
+            let param = |key: &str| {
+                query.iter().find(|(name, _)| *name == key).map(|(_, value)| value.as_str())
+            };
+            Ok(all
+                .into_iter()
+                .filter(|issue| match scope {
+                    ViewScope::Group(id) => id == 1,
+                    ViewScope::Project(id) => issue.project_id == id,
+                })
+                .filter(|issue| match param("state") {
+                    Some("all") | None => true,
+                    Some(state) => issue.state == state,
+                })
+                .filter(|issue| {
+                    param("labels").is_none_or(|labels| {
+                        labels.split(',').all(|label| issue.labels.iter().any(|l| l == label))
+                    })
+                })
+                .filter(|issue| {
+                    param("assignee_username").is_none_or(|username| {
+                        issue.assignees.iter().any(|user| user.username == username)
+                    })
+                })
+                .filter(|issue| {
+                    param("milestone").is_none_or(|title| {
+                        issue.milestone.as_ref().is_some_and(|milestone| milestone.title == title)
+                    })
+                })
+                .filter(|issue| {
+                    param("search").is_none_or(|text| {
+                        issue.title.to_lowercase().contains(&text.to_lowercase())
+                    })
+                })
+                .collect())
+        }
 
-        assert_eq!(url, "https://example.com/root");
-        assert_eq!(clipboard.text.as_deref(), Some("https://example.com/root"));
-    }
+        fn pipelines(
+            &self,
+            project_id: usize,
+            query: Vec<(&'static str, String)>,
+        ) -> Result<Vec<GitLabPipeline>> {
+            let all: Vec<serde_json::Value> = serde_json::from_str(fixture("pipelines.json"))?;
+            let param = |key: &str| {
+                query.iter().find(|(name, _)| *name == key).map(|(_, value)| value.as_str())
+            };
+            let limit = param("per_page").and_then(|value| value.parse().ok()).unwrap_or(20);
+            let mut out = Vec::new();
+            for mut item in all {
+                let matches_ref = param("ref").is_none_or(|name| item["ref"] == name);
+                if item["project_id"] == project_id && matches_ref && out.len() < limit {
+                    item.as_object_mut().map(|object| object.remove("duration"));
+                    out.push(serde_json::from_value(item)?);
+                }
+            }
+            Ok(out)
+        }
 
-    #[test]
-    fn open_selected_opens_url() {
-        let mut nodes = Vec::new();
-        let root = push_node(
-            &mut nodes,
-            "root",
-            NodeKind::Group,
-            "https://example.com/root",
-            "root",
-            "private",
-            None,
-        );
-        let parent = build_parent_map(&nodes);
-        let mut app = App {
-            nodes,
-            roots: vec![root],
-            parent,
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        fn pipeline(&self, project_id: usize, pipeline_id: usize) -> Result<GitLabPipeline> {
+            let all: Vec<serde_json::Value> = serde_json::from_str(fixture("pipelines.json"))?;
+            let item = all
+                .into_iter()
+                .find(|item| item["project_id"] == project_id && item["id"] == pipeline_id)
+                .ok_or_else(|| anyhow::anyhow!("404 Pipeline Not Found"))?;
+            Ok(serde_json::from_value(item)?)
+        }
 
-        let visible = app.visible_nodes();
-        let mut browser = MockBrowser { opened: None };
-        let url = app
-            .open_selected(&visible, &mut browser)
-            .expect("open should succeed");
+        fn pipeline_jobs(&self, project_id: usize, pipeline_id: usize) -> Result<Vec<GitLabJob>> {
+            match (project_id, pipeline_id) {
+                (10, 501) => Ok(serde_json::from_str(fixture("pipeline_501_jobs.json"))?),
+                _ => Ok(Vec::new()),
+            }
+        }
 
-        assert_eq!(url, "https://example.com/root");
-        assert_eq!(browser.opened.as_deref(), Some("https://example.com/root"));
-    }
+        fn job(&self, project_id: usize, job_id: usize) -> Result<GitLabJob> {
+            self.pipeline_jobs(project_id, 501)?
+                .into_iter()
+                .find(|job| job.id == job_id)
+                .ok_or_else(|| anyhow::anyhow!("404 Job Not Found"))
+        }
 
-    #[test]
-    fn toast_expires_after_ticks() {
-        let mut app = App {
-            nodes: Vec::new(),
-            roots: Vec::new(),
-            parent: Vec::new(),
-            selected: 0,
-            config: test_config(),
-            status: None,
-            pending_g: false,
-            toast: None,
-            search_query: None,
-            search_mode: false,
-            show_help: false,
-        };
+        fn job_trace(&self, project_id: usize, job_id: usize) -> Result<String> {
+            match (project_id, job_id) {
+                (10, 7003) => Ok(fixture("job_7003_trace.txt").to_string()),
+                _ => Ok(String::new()),
+            }
+        }
 
-        app.set_toast("Copied URL".to_string());
-        for _ in 0..App::TOAST_TTL {
-            app.tick_toast();
+        fn job_action(
+            &self,
+            project_id: usize,
+            job_id: usize,
+            action: JobAction,
+        ) -> Result<GitLabJob> {
+            let mut job = self.job(project_id, job_id)?;
+            match action {
+                // GitLab answers a retry with a brand new job.
+                JobAction::Retry => {
+                    job.id += 1000;
+                    job.status = "pending".to_string();
+                }
+                JobAction::Cancel => job.status = "canceled".to_string(),
+            }
+            Ok(job)
         }
 
-        assert!(app.toast.is_none());
+        fn issue_notes(&self, project_id: usize, iid: usize) -> Result<Vec<GitLabNote>> {
+            match (project_id, iid) {
+                (10, 3) => Ok(serde_json::from_str(fixture("issue_notes_10_3.json"))?),
+                _ => Ok(Vec::new()),
+            }
+        }
+
+        fn graphql(&self, query: &str, variables: serde_json::Value) -> Result<serde_json::Value> {
+            let fixture = if query.contains("currentUser") {
+                fixture("graphql_groups.json")
+            } else {
+                fixture("graphql_projects.json")
+            };
+            let pages: Vec<serde_json::Value> = serde_json::from_str(fixture)?;
+            let page = variables["after"]
+                .as_str()
+                .and_then(|cursor| cursor.strip_prefix("page-"))
+                .and_then(|page| page.parse::<usize>().ok())
+                .unwrap_or(0);
+            match pages.get(page) {
+                Some(body) => Ok(body["data"].clone()),
+                None => anyhow::bail!("no graphql page {page}"),
+            }
+        }
     }
 
-    #[test]
-    fn cache_is_valid_respects_ttl() {
-        let ttl = Duration::from_secs(10);
-        let now = UNIX_EPOCH + Duration::from_secs(100);
-        assert!(cache_is_valid(95, ttl, now));
-        assert!(!cache_is_valid(80, ttl, now));
+    struct FakeResponse {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: String,
     }
 
-    #[test]
-    fn cache_store_round_trip() {
-        let dir = tempfile::tempdir().expect("tempdir");
-        let path = dir.path().join("cache.json");
-        let store = CacheStore::new(path, Duration::from_secs(60));
-        let created_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let data = CacheData {
-            created_at,
-            groups: vec![GitLabGroup {
-                id: 1,
-                name: "root".to_string(),
-                web_url: "https://example.com/root".to_string(),
-                full_path: "root".to_string(),
-                visibility: "private".to_string(),
-                parent_id: None,
-            }],
-            projects_by_group: vec![GroupProjects {
-                group_id: 1,
-                projects: vec![GitLabProject {
-                    name: "proj".to_string(),
-                    web_url: "https://example.com/root/proj".to_string(),
-                    path_with_namespace: "root/proj".to_string(),
-                    visibility: "private".to_string(),
-                    last_activity_at: Some("2024-01-01T00:00:00Z".to_string()),
-                    namespace: Some(GitLabNamespace {
-                        kind: "group".to_string(),
-                    }),
-                }],
-            }],
-            personal: None,
-        };
+    impl FakeResponse {
+        fn json(body: &str) -> Self {
+            Self::status(200, body)
+        }
 
-        store.store(&data).expect("store cache");
-        let loaded = store.load().expect("load cache");
-        let loaded = loaded.expect("cache should be present");
+        fn status(status: u16, body: &str) -> Self {
+            Self {
+                status,
+                headers: vec![("content-type".to_string(), "application/json".to_string())],
+                body: body.to_string(),
+            }
+        }
 
-        assert_eq!(loaded.groups.len(), 1);
-        assert_eq!(loaded.groups[0].name, "root");
-        assert_eq!(loaded.projects_by_group.len(), 1);
-        assert_eq!(loaded.projects_by_group[0].projects[0].name, "proj");
+        fn with_header(mut self, name: &str, value: &str) -> Self {
+            self.headers.push((name.to_string(), value.to_string()));
+            self
+        }
     }
 
-    #[test]
-    fn personal_projects_filter_only_user_namespace() {
-        let projects = vec![
-            GitLabProject {
-                name: "personal".to_string(),
-                web_url: "https://example.com/alice/personal".to_string(),
-                path_with_namespace: "alice/personal".to_string(),
-                visibility: "private".to_string(),
-                last_activity_at: None,
-                namespace: Some(GitLabNamespace {
-                    kind: "user".to_string(),
-                }),
-            },
-            GitLabProject {
-                name: "grouped".to_string(),
-                web_url: "https://example.com/group/grouped".to_string(),
-                path_with_namespace: "group/grouped".to_string(),
-                visibility: "private".to_string(),
-                last_activity_at: None,
-                namespace: Some(GitLabNamespace {
-                    kind: "group".to_string(),
-                }),
-            },
-        ];
+    struct FakeServer {
+        url: String,
+    }
 
-        let filtered = filter_personal_projects(projects);
-        assert_eq!(filtered.len(), 1);
-        assert_eq!(filtered[0].name, "personal");
+    impl FakeServer {
+        fn start<F>(handler: F) -> Self
+        where
+            F: Fn(&str) -> FakeResponse + Send + 'static,
+        {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind fake server");
+            let url = format!("http://{}", listener.local_addr().expect("local addr"));
+            let base = url.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else {
+                        continue;
+                    };
+                    let Some(target) = read_request_target(&mut stream) else {
+                        continue;
+                    };
+                    let response = handler(&target);
+                    let mut head = format!(
+                        "HTTP/1.1 {} Fake\r\ncontent-length: {}\r\nconnection: close\r\n",
+                        response.status,
+                        response.body.len()
+                    );
+                    // Header values may reference the server itself (e.g. `Link`) via `{base}`.
+                    for (name, value) in &response.headers {
+                        let value = value.replace("{base}", &base);
+                        head.push_str(&format!("{name}: {value}\r\n"));
+                    }
+                    head.push_str("\r\n");
+                    let _ = stream.write_all(head.as_bytes());
+                    let _ = stream.write_all(response.body.as_bytes());
+                }
+            });
+            Self { url }
+        }
+    }
+
+    fn read_request_target(stream: &mut std::net::TcpStream) -> Option<String> {
+        use std::io::{BufRead, BufReader};
+        let mut reader = BufReader::new(stream.try_clone().ok()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).ok()?;
+        loop {
+            let mut header = String::new();
+            let read = reader.read_line(&mut header).ok()?;
+            if read == 0 || header == "\r\n" {
+                break;
+            }
+        }
+        request_line.split_whitespace().nth(1).map(|target| target.to_string())
+    }
+
+    fn request_path(target: &str) -> &str {
+        target.split('?').next().unwrap_or(target)
+    }
+
+    fn query_param(target: &str, name: &str) -> Option<String> {
+        let query = target.split_once('?')?.1;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
     }
 
     struct MockClipboardProbe {
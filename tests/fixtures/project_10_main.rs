@@ -0,0 +1,5 @@
+// Entry point
+fn main() {
+    let name = "tools";
+    println!("{name} 1");
+}